@@ -1,25 +1,63 @@
-use std::{cmp::max, collections::hash_map::Entry};
+use std::{
+  cmp::max,
+  collections::hash_map::Entry,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
 
 use super::*;
 use crate::sync::Arc;
 
+/// The milliseconds after which a state not updated is considered outdated,
+/// same as yjs.
+pub const AWARENESS_OUTDATED_TIMEOUT: u64 = 30_000;
+
 pub type AwarenessCallback = Arc<dyn Fn(&Awareness, AwarenessEvent) + Send + Sync + 'static>;
+/// Returns the milliseconds since the unix epoch.
+pub type AwarenessClock = Arc<dyn Fn() -> u64 + Send + Sync + 'static>;
 
 pub struct Awareness {
   awareness: AwarenessStates,
+  /// the milliseconds since the unix epoch when the states were updated
+  last_updated: HashMap<u64, u64>,
   callback: Option<AwarenessCallback>,
+  /// the clock the states are stamped with, the system time if not set
+  clock: Option<AwarenessClock>,
   local_id: u64,
 }
 
+fn system_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as u64)
+    .unwrap_or_default()
+}
+
 impl Awareness {
   pub fn new(local_id: u64) -> Self {
     Self {
       awareness: AwarenessStates::new(),
+      last_updated: HashMap::new(),
       callback: None,
+      clock: None,
       local_id,
     }
   }
 
+  /// Replace the clock the updates of the states are stamped with, which is
+  /// the system time by default.
+  pub fn set_clock(&mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) {
+    self.clock = Some(Arc::new(clock));
+  }
+
+  fn now(&self) -> u64 {
+    match &self.clock {
+      Some(clock) => clock(),
+      None => system_now(),
+    }
+  }
+
   pub fn on_update(&mut self, f: impl Fn(&Awareness, AwarenessEvent) + Send + Sync + 'static) {
     self.callback = Some(Arc::new(f));
   }
@@ -35,10 +73,31 @@ impl Awareness {
       .map(|state| state.content.clone())
   }
 
+  /// The local state deserialized from JSON, `None` if it's not set or
+  /// cleared.
+  pub fn get_local_state_as<T: DeserializeOwned>(&self) -> JwstCodecResult<Option<T>> {
+    match self.awareness.get(&self.local_id) {
+      Some(state) if !state.is_deleted() => serde_json::from_str(&state.content)
+        .map(Some)
+        .map_err(|e| JwstCodecError::InvalidAwarenessState(e.to_string())),
+      _ => Ok(None),
+    }
+  }
+
   fn mut_local_state(&mut self) -> &mut AwarenessState {
+    self.last_updated.insert(self.local_id, self.now());
     self.awareness.entry(self.local_id).or_default()
   }
 
+  /// Set the local state serialized as JSON.
+  pub fn set_local_state_from<T: Serialize>(&mut self, state: &T) -> JwstCodecResult {
+    let content = serde_json::to_string(state)
+      .map_err(|e| JwstCodecError::InvalidAwarenessState(e.to_string()))?;
+    self.set_local_state(content);
+
+    Ok(())
+  }
+
   pub fn set_local_state(&mut self, content: String) {
     self.mut_local_state().set_content(content);
     if let Some(cb) = self.callback.as_ref() {
@@ -59,8 +118,75 @@ impl Awareness {
     }
   }
 
+  /// Bump the clock of the local state so that the peers don't consider it
+  /// outdated, should be called more often than [AWARENESS_OUTDATED_TIMEOUT].
+  pub fn renew(&mut self) {
+    if self
+      .awareness
+      .get(&self.local_id)
+      .is_none_or(|state| state.is_deleted())
+    {
+      return;
+    }
+
+    self.mut_local_state().add_clock();
+    if let Some(cb) = self.callback.as_ref() {
+      cb(
+        self,
+        AwarenessEventBuilder::new().update(self.local_id).build(),
+      );
+    }
+  }
+
+  /// Remove the remote states not updated in [AWARENESS_OUTDATED_TIMEOUT]
+  /// before `now`, in milliseconds since the unix epoch, e.g. the peers
+  /// disconnected without clearing their states. The local state is renewed if
+  /// it's about to be outdated.
+  pub fn remove_outdated_states(&mut self, now: u64) -> Vec<u64> {
+    if self
+      .last_updated
+      .get(&self.local_id)
+      .is_some_and(|last_updated| {
+        now.saturating_sub(*last_updated) >= AWARENESS_OUTDATED_TIMEOUT / 2
+      })
+    {
+      self.renew();
+    }
+
+    let mut event = AwarenessEventBuilder::new();
+    for (client_id, state) in self.awareness.iter_mut() {
+      if *client_id == self.local_id || state.is_deleted() {
+        continue;
+      }
+
+      let last_updated = self
+        .last_updated
+        .get(client_id)
+        .copied()
+        .unwrap_or_default();
+      if now.saturating_sub(last_updated) >= AWARENESS_OUTDATED_TIMEOUT {
+        state.delete();
+        event.remove(*client_id);
+      }
+    }
+
+    let event = event.build();
+    for client_id in &event.removed {
+      self.last_updated.remove(client_id);
+    }
+    let removed = event.removed.clone();
+    if !removed.is_empty() {
+      if let Some(cb) = self.callback.as_ref() {
+        cb(self, event);
+      }
+    }
+
+    removed
+  }
+
   pub fn apply_update(&mut self, update: AwarenessStates) {
     let mut event = AwarenessEventBuilder::new();
+    let now = self.now();
 
     for (client_id, state) in update {
       match self.awareness.entry(client_id) {
@@ -78,13 +204,36 @@ impl Awareness {
             if state.is_deleted() {
               prev_state.delete();
               event.remove(client_id);
+              self.last_updated.remove(&client_id);
+              continue;
+            }
+
+            // a removed peer comes back
+            if prev_state.is_deleted() {
+              event.add(client_id);
             } else {
-              *prev_state = state;
               event.update(client_id);
             }
+            *prev_state = state;
+          } else if prev_state.clock == state.clock
+            && state.is_deleted()
+            && !prev_state.is_deleted()
+          {
+            // the peer cleared its state without bumping the clock
+            prev_state.delete();
+            event.remove(client_id);
+            self.last_updated.remove(&client_id);
+            continue;
+          } else {
+            // stale or echoed state, which doesn't keep the peer alive
+            continue;
           }
+          self.last_updated.insert(client_id, now);
         }
         Entry::Vacant(entry) => {
+          if client_id != self.local_id && !state.is_deleted() {
+            self.last_updated.insert(client_id, now);
+          }
           entry.insert(state);
           event.add(client_id);
         }
@@ -104,6 +253,18 @@ pub struct AwarenessEvent {
 }
 
 impl AwarenessEvent {
+  pub fn added(&self) -> &[u64] {
+    &self.added
+  }
+
+  pub fn updated(&self) -> &[u64] {
+    &self.updated
+  }
+
+  pub fn removed(&self) -> &[u64] {
+    &self.removed
+  }
+
   pub fn get_updated(&self, states: &AwarenessStates) -> AwarenessStates {
     states
       .iter()
@@ -250,4 +411,81 @@ mod tests {
       }
     });
   }
+
+  #[test]
+  fn test_awareness_outdated() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct User {
+      name: String,
+    }
+
+    loom_model!({
+      let now = Arc::new(Mutex::new(1_000_000));
+      let mut awareness = Awareness::new(0);
+      {
+        let now = now.clone();
+        awareness.set_clock(move || *now.lock().unwrap());
+      }
+      let user = User {
+        name: "alice".into(),
+      };
+      awareness.set_local_state_from(&user).unwrap();
+      assert_eq!(awareness.get_local_state_as::<User>().unwrap(), Some(user));
+      assert!(awareness.get_local_state_as::<Vec<u64>>().is_err());
+
+      let mut states = AwarenessStates::new();
+      states.insert(1, AwarenessState::new(1, r#"{"name":"bob"}"#.to_string()));
+      states.insert(2, AwarenessState::new(1, r#"{"name":"carol"}"#.to_string()));
+      awareness.apply_update(states);
+
+      let events: Arc<Mutex<Vec<AwarenessEvent>>> = Arc::new(Mutex::new(Vec::new()));
+      let callback_events = Arc::clone(&events);
+      awareness.on_update(move |_, event| {
+        callback_events.lock().unwrap().push(event);
+      });
+
+      let time = *now.lock().unwrap();
+      assert!(awareness.remove_outdated_states(time).is_empty());
+      assert!(events.lock().unwrap().is_empty());
+
+      // the echoed state of a peer doesn't keep it alive, a newer one does
+      *now.lock().unwrap() += AWARENESS_OUTDATED_TIMEOUT / 2;
+      let mut states = AwarenessStates::new();
+      states.insert(1, AwarenessState::new(1, r#"{"name":"bob"}"#.to_string()));
+      states.insert(2, AwarenessState::new(2, r#"{"name":"carol"}"#.to_string()));
+      awareness.apply_update(states);
+      events.lock().unwrap().clear();
+      *now.lock().unwrap() += AWARENESS_OUTDATED_TIMEOUT / 2;
+
+      let clock = awareness.get_states().get(&0).unwrap().clock();
+      let time = *now.lock().unwrap();
+      assert_eq!(awareness.remove_outdated_states(time), [1]);
+      // the removed peer is not timed anymore
+      assert!(!awareness.last_updated.contains_key(&1));
+      assert!(awareness.get_states().get(&1).unwrap().is_deleted());
+      // the local state is renewed instead of removed
+      assert_eq!(awareness.get_states().get(&0).unwrap().clock(), clock + 1);
+      {
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].updated(), [0]);
+        assert_eq!(events[1].removed(), [1]);
+      }
+
+      // the peer comes back
+      let mut states = AwarenessStates::new();
+      states.insert(1, AwarenessState::new(3, r#"{"name":"bob"}"#.to_string()));
+      awareness.apply_update(states);
+      assert_eq!(events.lock().unwrap()[2].added(), [1]);
+
+      // a state cleared with the same clock is removed
+      let mut states = AwarenessStates::new();
+      states.insert(2, AwarenessState::new(2, "null".to_string()));
+      awareness.apply_update(states);
+      assert_eq!(events.lock().unwrap()[3].removed(), [2]);
+
+      awareness.clear_local_state();
+      assert_eq!(awareness.get_local_state_as::<User>().unwrap(), None);
+    });
+  }
 }
@@ -139,7 +139,7 @@ impl Any {
   }
 
   pub(crate) fn read_multiple<R: CrdtReader>(reader: &mut R) -> JwstCodecResult<Vec<Any>> {
-    let len = reader.read_var_u64()? as usize;
+    let len = reader.read_len()? as usize;
     let mut vec = Vec::with_capacity(len);
     for _ in 0..len {
       vec.push(Any::read(reader)?);
@@ -149,7 +149,7 @@ impl Any {
   }
 
   pub(crate) fn write_multiple<W: CrdtWriter>(writer: &mut W, any: &[Any]) -> JwstCodecResult {
-    writer.write_var_u64(any.len() as u64)?;
+    writer.write_len(any.len() as u64)?;
     for value in any {
       value.write(writer)?;
     }
@@ -189,8 +189,8 @@ macro_rules! impl_primitive_from {
                     let int: i64 = value.into();
                     // handle the behavior same as yjs
                     if JS_INT_RANGE.contains(&int) {
-                        if int <= i32::MAX as i64 {
-                            Self::Integer(int as i32)
+                        if let Ok(int) = i32::try_from(int) {
+                            Self::Integer(int)
                         } else if int as f32 as i64 == int {
                             Self::Float32((int as f32).into())
                         } else {
@@ -238,7 +238,7 @@ impl From<f32> for Any {
 
 impl From<f64> for Any {
   fn from(value: f64) -> Self {
-    if value.trunc() == value {
+    if value.trunc() == value && value.abs() <= MAX_JS_INT as f64 {
       (value as i64).into()
     } else if value as f32 as f64 == value {
       Self::Float32((value as f32).into())
@@ -352,7 +352,6 @@ impl<T: Into<Any>> From<Option<T>> for Any {
   }
 }
 
-#[cfg(feature = "serde_json")]
 impl From<serde_json::Value> for Any {
   fn from(value: serde_json::Value) -> Self {
     match value {
@@ -365,12 +364,11 @@ impl From<serde_json::Value> for Any {
         }
       }
       serde_json::Value::Number(n) => {
-        if n.is_f64() {
-          Self::Float64(n.as_f64().unwrap().into())
-        } else if n.is_i64() {
-          Self::Integer(n.as_i64().unwrap() as i32)
+        // the same as the number parsed from JSON in js
+        if let Some(int) = n.as_i64() {
+          int.into()
         } else {
-          Self::Integer(n.as_u64().unwrap() as i32)
+          n.as_f64().unwrap_or_default().into()
         }
       }
       serde_json::Value::String(s) => Self::String(s),
@@ -653,6 +651,43 @@ mod tests {
       }
   }
 
+  #[test]
+  fn test_convert_json_number_to_any() {
+    let any: Vec<Any> = serde_json::json!([
+      42,
+      -42,
+      i32::MAX as i64 + 1,
+      -(1_i64 << 40),
+      (1_i64 << 53) + 1,
+      u64::MAX,
+      0.5,
+      2.0,
+      1e300,
+    ])
+    .as_array()
+    .unwrap()
+    .iter()
+    .cloned()
+    .map(Any::from)
+    .collect();
+
+    assert_eq!(
+      any,
+      vec![
+        Any::Integer(42),
+        Any::Integer(-42),
+        Any::Float32((i32::MAX as f32 + 1.0).into()),
+        Any::Float32((-(1_i64 << 40) as f32).into()),
+        // out of the safe integer range of js
+        Any::BigInt64((1 << 53) + 1),
+        Any::Float32((u64::MAX as f32).into()),
+        Any::Float32(0.5.into()),
+        Any::Integer(2),
+        Any::Float64(1e300.into()),
+      ]
+    );
+  }
+
   #[test]
   fn test_convert_to_any() {
     let any: Vec<Any> = vec![
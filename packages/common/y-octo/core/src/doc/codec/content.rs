@@ -1,3 +1,5 @@
+use std::sync::{Arc, Mutex};
+
 use super::*;
 
 #[derive(Clone)]
@@ -20,7 +22,13 @@ pub(crate) enum Content {
   Doc {
     guid: String,
     opts: Any,
+    /// the subdoc instance, created on first access and replaced once it's
+    /// destroyed, see [Doc::destroy]
+    #[cfg_attr(test, proptest(value = "Default::default()"))]
+    doc: Arc<Mutex<Option<Doc>>>,
   },
+  #[cfg_attr(test, proptest(skip))]
+  Move(Box<Move>),
 }
 
 unsafe impl Send for Content {}
@@ -66,6 +74,7 @@ impl PartialEq for Content {
       (Self::Any(any1), Self::Any(any2)) => any1 == any2,
       (Self::Doc { guid: guid1, .. }, Self::Doc { guid: guid2, .. }) => guid1 == guid2,
       (Self::Type(ty1), Self::Type(ty2)) => ty1 == ty2,
+      (Self::Move(move1), Self::Move(move2)) => move1 == move2,
       _ => false,
     }
   }
@@ -95,11 +104,12 @@ impl std::fmt::Debug for Content {
         .field(&arg0.ty().unwrap().kind())
         .finish(),
       Self::Any(arg0) => f.debug_tuple("Any").field(arg0).finish(),
-      Self::Doc { guid, opts } => f
+      Self::Doc { guid, opts, .. } => f
         .debug_struct("Doc")
         .field("guid", guid)
         .field("opts", opts)
         .finish(),
+      Self::Move(arg0) => arg0.fmt(f),
     }
   }
 }
@@ -107,41 +117,33 @@ impl std::fmt::Debug for Content {
 impl Content {
   pub(crate) fn read<R: CrdtReader>(decoder: &mut R, tag_type: u8) -> JwstCodecResult<Self> {
     match tag_type {
-      1 => Ok(Self::Deleted(decoder.read_var_u64()?)), // Deleted
+      1 => Ok(Self::Deleted(decoder.read_len()?)), // Deleted
       2 => {
-        let len = decoder.read_var_u64()?;
+        let len = decoder.read_len()?;
         let strings = (0..len)
           .map(|_| {
             decoder
-              .read_var_string()
+              .read_string()
               .map(|s| (s != "undefined").then_some(s))
           })
           .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self::Json(strings))
       } // JSON
-      3 => Ok(Self::Binary(decoder.read_var_buffer()?.to_vec())), // Binary
-      4 => Ok(Self::String(decoder.read_var_string()?)), // String
-      5 => {
-        let string = decoder.read_var_string()?;
-        let json =
-          serde_json::from_str(&string).map_err(|_| JwstCodecError::DamagedDocumentJson)?;
-
-        Ok(Self::Embed(json))
-      } // Embed
+      3 => Ok(Self::Binary(decoder.read_buf()?)),  // Binary
+      4 => Ok(Self::String(decoder.read_string()?)), // String
+      5 => Ok(Self::Embed(decoder.read_json()?)),  // Embed
       6 => {
-        let key = decoder.read_var_string()?;
-        let value = decoder.read_var_string()?;
-        let value =
-          serde_json::from_str(&value).map_err(|_| JwstCodecError::DamagedDocumentJson)?;
+        let key = decoder.read_key()?;
+        let value = decoder.read_json()?;
 
         Ok(Self::Format { key, value })
       } // Format
       7 => {
-        let type_ref = decoder.read_var_u64()?;
+        let type_ref = decoder.read_type_ref()?;
         let kind = YTypeKind::from(type_ref);
         let tag_name = match kind {
-          YTypeKind::XMLElement | YTypeKind::XMLHook => Some(decoder.read_var_string()?),
+          YTypeKind::XMLElement | YTypeKind::XMLHook => Some(decoder.read_key()?),
           YTypeKind::Unknown => {
             return Err(JwstCodecError::IncompleteDocument(format!(
               "Unknown y type: {type_ref}"
@@ -154,10 +156,15 @@ impl Content {
       } // YType
       8 => Ok(Self::Any(Any::read_multiple(decoder)?)), // Any
       9 => {
-        let guid = decoder.read_var_string()?;
+        let guid = decoder.read_string()?;
         let opts = Any::read(decoder)?;
-        Ok(Self::Doc { guid, opts })
+        Ok(Self::Doc {
+          guid,
+          opts,
+          doc: Default::default(),
+        })
       } // Doc
+      11 => Ok(Self::Move(Box::new(Move::read(decoder)?))), // Move
       tag_type => Err(JwstCodecError::IncompleteDocument(format!(
         "Unknown content type: {tag_type}"
       ))),
@@ -175,57 +182,57 @@ impl Content {
       Self::Type(_) => 7,
       Self::Any(_) => 8,
       Self::Doc { .. } => 9,
+      Self::Move(_) => 11,
     }
   }
 
   pub(crate) fn write<W: CrdtWriter>(&self, encoder: &mut W) -> JwstCodecResult {
     match self {
       Self::Deleted(len) => {
-        encoder.write_var_u64(*len)?;
+        encoder.write_len(*len)?;
       }
       Self::Json(strings) => {
-        encoder.write_var_u64(strings.len() as u64)?;
+        encoder.write_len(strings.len() as u64)?;
         for string in strings {
           match string {
-            Some(string) => encoder.write_var_string(string)?,
-            None => encoder.write_var_string("undefined")?,
+            Some(string) => encoder.write_string(string)?,
+            None => encoder.write_string("undefined")?,
           }
         }
       }
       Self::Binary(buffer) => {
-        encoder.write_var_buffer(buffer)?;
+        encoder.write_buf(buffer)?;
       }
       Self::String(string) => {
-        encoder.write_var_string(string)?;
+        encoder.write_string(string)?;
       }
       Self::Embed(val) => {
-        encoder.write_var_string(
-          serde_json::to_string(val).map_err(|_| JwstCodecError::DamagedDocumentJson)?,
-        )?;
+        encoder.write_json(val)?;
       }
       Self::Format { key, value } => {
-        encoder.write_var_string(key)?;
-        encoder.write_var_string(
-          serde_json::to_string(value).map_err(|_| JwstCodecError::DamagedDocumentJson)?,
-        )?;
+        encoder.write_key(key)?;
+        encoder.write_json(value)?;
       }
       Self::Type(ty) => {
         if let Some(ty) = ty.ty() {
           let type_ref = u64::from(ty.kind());
-          encoder.write_var_u64(type_ref)?;
+          encoder.write_type_ref(type_ref)?;
 
           if matches!(ty.kind(), YTypeKind::XMLElement | YTypeKind::XMLHook) {
-            encoder.write_var_string(ty.name.as_ref().unwrap())?;
+            encoder.write_key(ty.name.as_ref().unwrap())?;
           }
         }
       }
       Self::Any(any) => {
         Any::write_multiple(encoder, any)?;
       }
-      Self::Doc { guid, opts } => {
-        encoder.write_var_string(guid)?;
+      Self::Doc { guid, opts, .. } => {
+        encoder.write_string(guid)?;
         opts.write(encoder)?;
       }
+      Self::Move(mv) => {
+        mv.write(encoder)?;
+      }
     }
     Ok(())
   }
@@ -237,14 +244,55 @@ impl Content {
       // TODO: need a custom wrapper with length cached, this cost too much
       Self::String(string) => string.chars().map(|c| c.len_utf16()).sum::<usize>() as u64,
       Self::Any(any) => any.len() as u64,
-      Self::Binary(_) | Self::Embed(_) | Self::Format { .. } | Self::Type(_) | Self::Doc { .. } => {
-        1
-      }
+      Self::Binary(_)
+      | Self::Embed(_)
+      | Self::Format { .. }
+      | Self::Type(_)
+      | Self::Doc { .. }
+      | Self::Move(_) => 1,
     }
   }
 
   pub fn countable(&self) -> bool {
-    !matches!(self, Content::Format { .. } | Content::Deleted(_))
+    !matches!(
+      self,
+      Content::Format { .. } | Content::Deleted(_) | Content::Move(_)
+    )
+  }
+
+  #[allow(dead_code)]
+  /// the subdoc instance of [Content::Doc]
+  pub fn subdoc(&self) -> Option<Doc> {
+    match self {
+      Self::Doc { guid, opts, doc } => Some(
+        doc
+          .lock()
+          .unwrap()
+          .get_or_insert_with(|| Self::new_subdoc(guid, opts))
+          .clone(),
+      ),
+      _ => None,
+    }
+  }
+
+  /// replace the subdoc instance of [Content::Doc] with a new one not loaded
+  pub fn replace_subdoc(&self) -> Option<Doc> {
+    match self {
+      Self::Doc { guid, opts, doc } => {
+        let subdoc = Self::new_subdoc(guid, opts);
+        subdoc.store.write().unwrap().should_load = false;
+        doc.lock().unwrap().replace(subdoc.clone());
+        Some(subdoc)
+      }
+      _ => None,
+    }
+  }
+
+  fn new_subdoc(guid: &str, opts: &Any) -> Doc {
+    DocOptions::try_from(opts.clone())
+      .unwrap_or_default()
+      .with_guid(guid.to_owned())
+      .build()
   }
 
   #[allow(dead_code)]
@@ -347,7 +395,18 @@ mod tests {
         Content::Doc {
           guid: "my_guid".to_string(),
           opts: Any::BigInt64(42),
+          doc: Default::default(),
         },
+        Content::Move(Box::new(Move::new(
+          Id::new(1, 2),
+          Assoc::After,
+          Id::new(1, 2),
+          Assoc::Before,
+        ))),
+        Content::Move(Box::new(Move {
+          priority: 3,
+          ..Move::new(Id::new(1, 2), Assoc::Before, Id::new(2, 5), Assoc::After)
+        })),
       ];
 
       for content in &contents {
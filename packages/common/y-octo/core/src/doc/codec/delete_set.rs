@@ -8,16 +8,16 @@ use crate::doc::OrderRange;
 
 impl<R: CrdtReader> CrdtRead<R> for Range<u64> {
   fn read(decoder: &mut R) -> JwstCodecResult<Self> {
-    let clock = decoder.read_var_u64()?;
-    let len = decoder.read_var_u64()?;
+    let clock = decoder.read_ds_clock()?;
+    let len = decoder.read_ds_len()?;
     Ok(clock..clock + len)
   }
 }
 
 impl<W: CrdtWriter> CrdtWrite<W> for Range<u64> {
   fn write(&self, encoder: &mut W) -> JwstCodecResult {
-    encoder.write_var_u64(self.start)?;
-    encoder.write_var_u64(self.end - self.start)?;
+    encoder.write_ds_clock(self.start)?;
+    encoder.write_ds_len(self.end - self.start)?;
     Ok(())
   }
 }
@@ -117,6 +117,13 @@ impl DeleteSet {
     }
   }
 
+  pub fn contains(&self, id: &Id) -> bool {
+    self
+      .0
+      .get(&id.client)
+      .is_some_and(|range| range.contains(id.clock))
+  }
+
   pub fn merge(&mut self, other: &Self) {
     for (client, range) in &other.0 {
       match self.0.entry(*client) {
@@ -138,6 +145,7 @@ impl<R: CrdtReader> CrdtRead<R> for DeleteSet {
     let mut map = ClientMap::with_capacity(num_of_clients.min(HASHMAP_SAFE_CAPACITY));
 
     for _ in 0..num_of_clients {
+      decoder.reset_ds_cur_val();
       let client = decoder.read_var_u64()?;
       let deletes = OrderRange::read(decoder)?;
       map.insert(client, deletes);
@@ -157,6 +165,7 @@ impl<W: CrdtWriter> CrdtWrite<W> for DeleteSet {
     clients.sort_by(|a, b| b.cmp(a));
 
     for client in clients {
+      encoder.reset_ds_cur_val();
       encoder.write_var_u64(client)?;
       self.get(&client).unwrap().write(encoder)?;
     }
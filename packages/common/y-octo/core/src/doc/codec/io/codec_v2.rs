@@ -0,0 +1,725 @@
+use super::*;
+
+fn read_var_slice<'b>(decoder: &mut RawDecoder<'b>) -> JwstCodecResult<&'b [u8]> {
+  let len = decoder.read_var_u64()? as usize;
+  let buffer: &'b [u8] = decoder.buffer.get_ref();
+  let start = decoder.buffer.position() as usize;
+  let end = start.saturating_add(len);
+
+  if end > buffer.len() {
+    return Err(JwstCodecError::IncompleteDocument(format!(
+      "buffer length {len} exceeds the remaining {}",
+      buffer.len().saturating_sub(start)
+    )));
+  }
+
+  decoder.buffer.set_position(end as u64);
+  Ok(&buffer[start..end])
+}
+
+/// read a lib0 var int and keep the sign flag, which is used to represent
+/// `-0` in the rle encoding
+fn read_var_int(decoder: &mut RawDecoder) -> JwstCodecResult<(bool, u64)> {
+  let mut byte = decoder.read_u8()?;
+  let negative = byte & 0b0100_0000 != 0;
+  let mut num = (byte & 0b0011_1111) as u64;
+  let mut shift = 6;
+
+  while byte & 0b1000_0000 != 0 {
+    byte = decoder.read_u8()?;
+    if shift >= 64 {
+      return Err(JwstCodecError::IncompleteDocument(
+        "var int out of range".to_string(),
+      ));
+    }
+    num |= ((byte & 0b0111_1111) as u64) << shift;
+    shift += 7;
+  }
+
+  Ok((negative, num))
+}
+
+/// read the length of a rle run, which is stored with the minimum run length
+/// subtracted
+fn read_run_len(decoder: &mut RawDecoder, min: u64) -> JwstCodecResult<u64> {
+  decoder
+    .read_var_u64()?
+    .checked_add(min)
+    .ok_or_else(|| JwstCodecError::IncompleteDocument("run length out of range".to_string()))
+}
+
+fn write_var_int(encoder: &mut RawEncoder, negative: bool, mut num: u64) -> JwstCodecResult {
+  encoder.write_u8(
+    if num > 0b0011_1111 { 0b1000_0000 } else { 0 }
+      | if negative { 0b0100_0000 } else { 0 }
+      | (num as u8 & 0b0011_1111),
+  )?;
+  num >>= 6;
+  while num > 0 {
+    encoder
+      .write_u8(if num > 0b0111_1111 { 0b1000_0000 } else { 0 } | (num as u8 & 0b0111_1111))?;
+    num >>= 7;
+  }
+
+  Ok(())
+}
+
+fn write_signed_var_int(encoder: &mut RawEncoder, num: i64) -> JwstCodecResult {
+  write_var_int(encoder, num < 0, num.unsigned_abs())
+}
+
+// lib0 UintOptRleDecoder
+struct UintOptRleDecoder<'b> {
+  decoder: RawDecoder<'b>,
+  s: u64,
+  count: u64,
+}
+
+impl<'b> UintOptRleDecoder<'b> {
+  fn new(decoder: RawDecoder<'b>) -> Self {
+    Self {
+      decoder,
+      s: 0,
+      count: 0,
+    }
+  }
+
+  fn read(&mut self) -> JwstCodecResult<u64> {
+    if self.count == 0 {
+      let (negative, s) = read_var_int(&mut self.decoder)?;
+      self.s = s;
+      self.count = if negative {
+        read_run_len(&mut self.decoder, 2)?
+      } else {
+        1
+      };
+    }
+    self.count -= 1;
+    Ok(self.s)
+  }
+}
+
+// lib0 IntDiffOptRleDecoder
+struct IntDiffOptRleDecoder<'b> {
+  decoder: RawDecoder<'b>,
+  s: i64,
+  count: u64,
+  diff: i64,
+}
+
+impl<'b> IntDiffOptRleDecoder<'b> {
+  fn new(decoder: RawDecoder<'b>) -> Self {
+    Self {
+      decoder,
+      s: 0,
+      count: 0,
+      diff: 0,
+    }
+  }
+
+  fn read(&mut self) -> JwstCodecResult<u64> {
+    if self.count == 0 {
+      let (negative, num) = read_var_int(&mut self.decoder)?;
+      let diff = if negative { -(num as i64) } else { num as i64 };
+      self.diff = diff >> 1;
+      self.count = if diff & 1 == 1 {
+        read_run_len(&mut self.decoder, 2)?
+      } else {
+        1
+      };
+    }
+    self.s = self.s.wrapping_add(self.diff);
+    self.count -= 1;
+    Ok(self.s as u64)
+  }
+}
+
+// lib0 RleDecoder for u8 values
+struct RleDecoder<'b> {
+  decoder: RawDecoder<'b>,
+  s: u8,
+  // None means the last value repeats forever
+  count: Option<u64>,
+}
+
+impl<'b> RleDecoder<'b> {
+  fn new(decoder: RawDecoder<'b>) -> Self {
+    Self {
+      decoder,
+      s: 0,
+      count: Some(0),
+    }
+  }
+
+  fn read(&mut self) -> JwstCodecResult<u8> {
+    if self.count == Some(0) {
+      self.s = self.decoder.read_u8()?;
+      self.count = if self.decoder.is_empty() {
+        None
+      } else {
+        Some(read_run_len(&mut self.decoder, 1)?)
+      };
+    }
+    if let Some(count) = self.count.as_mut() {
+      *count -= 1;
+    }
+    Ok(self.s)
+  }
+}
+
+// lib0 StringDecoder, all strings are concatenated and sliced by utf-16
+// lengths
+struct StringDecoder<'b> {
+  str: Vec<u16>,
+  pos: usize,
+  lens: UintOptRleDecoder<'b>,
+}
+
+impl<'b> StringDecoder<'b> {
+  fn new(mut decoder: RawDecoder<'b>) -> JwstCodecResult<Self> {
+    let str = decoder.read_var_string()?.encode_utf16().collect();
+    Ok(Self {
+      str,
+      pos: 0,
+      lens: UintOptRleDecoder::new(decoder),
+    })
+  }
+
+  fn read(&mut self) -> JwstCodecResult<String> {
+    let len = self.lens.read()? as usize;
+    let end = self.pos.saturating_add(len);
+    let slice = self.str.get(self.pos..end).ok_or_else(|| {
+      JwstCodecError::IncompleteDocument(format!("string length {len} out of bound"))
+    })?;
+    let str =
+      String::from_utf16(slice).map_err(|e| JwstCodecError::IncompleteDocument(e.to_string()))?;
+    self.pos = end;
+    Ok(str)
+  }
+}
+
+// compatible with ydoc v2
+pub struct RawDecoderV2<'b> {
+  rest: RawDecoder<'b>,
+  keys: Vec<String>,
+  ds_cur_val: u64,
+  key_clock: IntDiffOptRleDecoder<'b>,
+  client: UintOptRleDecoder<'b>,
+  left_clock: IntDiffOptRleDecoder<'b>,
+  right_clock: IntDiffOptRleDecoder<'b>,
+  info: RleDecoder<'b>,
+  string: StringDecoder<'b>,
+  parent_info: RleDecoder<'b>,
+  type_ref: UintOptRleDecoder<'b>,
+  len: UintOptRleDecoder<'b>,
+}
+
+impl<'b> RawDecoderV2<'b> {
+  pub fn new(buffer: &'b [u8]) -> JwstCodecResult<Self> {
+    let mut rest = RawDecoder::new(buffer);
+    // feature flag, currently unused
+    rest.read_var_u64()?;
+
+    let mut next = || read_var_slice(&mut rest).map(RawDecoder::new);
+
+    let key_clock = IntDiffOptRleDecoder::new(next()?);
+    let client = UintOptRleDecoder::new(next()?);
+    let left_clock = IntDiffOptRleDecoder::new(next()?);
+    let right_clock = IntDiffOptRleDecoder::new(next()?);
+    let info = RleDecoder::new(next()?);
+    let string = StringDecoder::new(next()?)?;
+    let parent_info = RleDecoder::new(next()?);
+    let type_ref = UintOptRleDecoder::new(next()?);
+    let len = UintOptRleDecoder::new(next()?);
+
+    Ok(Self {
+      rest,
+      keys: Vec::new(),
+      ds_cur_val: 0,
+      key_clock,
+      client,
+      left_clock,
+      right_clock,
+      info,
+      string,
+      parent_info,
+      type_ref,
+      len,
+    })
+  }
+}
+
+impl CrdtReader for RawDecoderV2<'_> {
+  fn is_empty(&self) -> bool {
+    self.rest.is_empty()
+  }
+
+  fn len(&self) -> u64 {
+    self.rest.len()
+  }
+
+  fn read_var_u64(&mut self) -> JwstCodecResult<u64> {
+    self.rest.read_var_u64()
+  }
+
+  fn read_var_i32(&mut self) -> JwstCodecResult<i32> {
+    self.rest.read_var_i32()
+  }
+
+  fn read_var_string(&mut self) -> JwstCodecResult<String> {
+    self.rest.read_var_string()
+  }
+
+  fn read_var_buffer(&mut self) -> JwstCodecResult<Vec<u8>> {
+    self.rest.read_var_buffer()
+  }
+
+  fn read_u8(&mut self) -> JwstCodecResult<u8> {
+    self.rest.read_u8()
+  }
+
+  fn read_f32_be(&mut self) -> JwstCodecResult<f32> {
+    self.rest.read_f32_be()
+  }
+
+  fn read_f64_be(&mut self) -> JwstCodecResult<f64> {
+    self.rest.read_f64_be()
+  }
+
+  fn read_i64_be(&mut self) -> JwstCodecResult<i64> {
+    self.rest.read_i64_be()
+  }
+
+  #[inline(always)]
+  fn read_info(&mut self) -> JwstCodecResult<u8> {
+    self.info.read()
+  }
+
+  #[inline(always)]
+  fn read_item_id(&mut self) -> JwstCodecResult<Id> {
+    self.read_left_id()
+  }
+
+  fn read_client(&mut self) -> JwstCodecResult<u64> {
+    self.client.read()
+  }
+
+  fn read_left_id(&mut self) -> JwstCodecResult<Id> {
+    Ok(Id::new(self.client.read()?, self.left_clock.read()?))
+  }
+
+  fn read_right_id(&mut self) -> JwstCodecResult<Id> {
+    Ok(Id::new(self.client.read()?, self.right_clock.read()?))
+  }
+
+  fn read_parent_info(&mut self) -> JwstCodecResult<bool> {
+    Ok(self.parent_info.read()? == 1)
+  }
+
+  fn read_type_ref(&mut self) -> JwstCodecResult<u64> {
+    self.type_ref.read()
+  }
+
+  fn read_len(&mut self) -> JwstCodecResult<u64> {
+    self.len.read()
+  }
+
+  fn read_string(&mut self) -> JwstCodecResult<String> {
+    self.string.read()
+  }
+
+  fn read_key(&mut self) -> JwstCodecResult<String> {
+    let key_clock = self.key_clock.read()? as usize;
+    if let Some(key) = self.keys.get(key_clock) {
+      Ok(key.clone())
+    } else {
+      let key = self.string.read()?;
+      self.keys.push(key.clone());
+      Ok(key)
+    }
+  }
+
+  fn read_buf(&mut self) -> JwstCodecResult<Vec<u8>> {
+    self.rest.read_var_buffer()
+  }
+
+  fn read_json(&mut self) -> JwstCodecResult<Any> {
+    Any::read(&mut self.rest)
+  }
+
+  fn reset_ds_cur_val(&mut self) {
+    self.ds_cur_val = 0;
+  }
+
+  fn read_ds_clock(&mut self) -> JwstCodecResult<u64> {
+    self.ds_cur_val = self.ds_cur_val.wrapping_add(self.rest.read_var_u64()?);
+    Ok(self.ds_cur_val)
+  }
+
+  fn read_ds_len(&mut self) -> JwstCodecResult<u64> {
+    let len = read_run_len(&mut self.rest, 1)?;
+    self.ds_cur_val = self.ds_cur_val.wrapping_add(len);
+    Ok(len)
+  }
+}
+
+// lib0 UintOptRleEncoder
+#[derive(Default)]
+struct UintOptRleEncoder {
+  encoder: RawEncoder,
+  s: u64,
+  count: u64,
+}
+
+impl UintOptRleEncoder {
+  fn write(&mut self, value: u64) -> JwstCodecResult {
+    if self.count > 0 && self.s == value {
+      self.count += 1;
+    } else {
+      self.flush()?;
+      self.count = 1;
+      self.s = value;
+    }
+    Ok(())
+  }
+
+  fn flush(&mut self) -> JwstCodecResult {
+    if self.count > 0 {
+      // a negative value marks a run, `-0` is valid here
+      write_var_int(&mut self.encoder, self.count > 1, self.s)?;
+      if self.count > 1 {
+        self.encoder.write_var_u64(self.count - 2)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn into_inner(mut self) -> JwstCodecResult<Vec<u8>> {
+    self.flush()?;
+    Ok(self.encoder.into_inner())
+  }
+}
+
+// lib0 IntDiffOptRleEncoder
+#[derive(Default)]
+struct IntDiffOptRleEncoder {
+  encoder: RawEncoder,
+  s: i64,
+  count: u64,
+  diff: i64,
+}
+
+impl IntDiffOptRleEncoder {
+  fn write(&mut self, value: u64) -> JwstCodecResult {
+    let value = value as i64;
+    let diff = value.wrapping_sub(self.s);
+    if self.count > 0 && self.diff == diff {
+      self.count += 1;
+    } else {
+      self.flush()?;
+      self.count = 1;
+      self.diff = diff;
+    }
+    self.s = value;
+    Ok(())
+  }
+
+  fn flush(&mut self) -> JwstCodecResult {
+    if self.count > 0 {
+      let encoded_diff = self.diff * 2 + if self.count == 1 { 0 } else { 1 };
+      write_signed_var_int(&mut self.encoder, encoded_diff)?;
+      if self.count > 1 {
+        self.encoder.write_var_u64(self.count - 2)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn into_inner(mut self) -> JwstCodecResult<Vec<u8>> {
+    self.flush()?;
+    Ok(self.encoder.into_inner())
+  }
+}
+
+// lib0 RleEncoder for u8 values, the count of the last run is never written
+#[derive(Default)]
+struct RleEncoder {
+  encoder: RawEncoder,
+  s: Option<u8>,
+  count: u64,
+}
+
+impl RleEncoder {
+  fn write(&mut self, value: u8) -> JwstCodecResult {
+    if self.s == Some(value) {
+      self.count += 1;
+    } else {
+      if self.count > 0 {
+        self.encoder.write_var_u64(self.count - 1)?;
+      }
+      self.count = 1;
+      self.encoder.write_u8(value)?;
+      self.s = Some(value);
+    }
+    Ok(())
+  }
+
+  fn into_inner(self) -> Vec<u8> {
+    self.encoder.into_inner()
+  }
+}
+
+// lib0 StringEncoder
+#[derive(Default)]
+struct StringEncoder {
+  str: String,
+  lens: UintOptRleEncoder,
+}
+
+impl StringEncoder {
+  fn write(&mut self, s: &str) -> JwstCodecResult {
+    self.str.push_str(s);
+    self.lens.write(s.encode_utf16().count() as u64)
+  }
+
+  fn into_inner(self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoder::default();
+    encoder.write_var_string(&self.str)?;
+    let mut buffer = encoder.into_inner();
+    buffer.extend(self.lens.into_inner()?);
+    Ok(buffer)
+  }
+}
+
+// compatible with ydoc v2
+#[derive(Default)]
+pub struct RawEncoderV2 {
+  rest: RawEncoder,
+  key_clock: u64,
+  ds_cur_val: u64,
+  key_clock_encoder: IntDiffOptRleEncoder,
+  client_encoder: UintOptRleEncoder,
+  left_clock_encoder: IntDiffOptRleEncoder,
+  right_clock_encoder: IntDiffOptRleEncoder,
+  info_encoder: RleEncoder,
+  string_encoder: StringEncoder,
+  parent_info_encoder: RleEncoder,
+  type_ref_encoder: UintOptRleEncoder,
+  len_encoder: UintOptRleEncoder,
+}
+
+impl RawEncoderV2 {
+  pub fn into_inner(self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoder::default();
+    // feature flag, currently unused
+    encoder.write_var_u64(0)?;
+    encoder.write_var_buffer(&self.key_clock_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.client_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.left_clock_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.right_clock_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.info_encoder.into_inner())?;
+    encoder.write_var_buffer(&self.string_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.parent_info_encoder.into_inner())?;
+    encoder.write_var_buffer(&self.type_ref_encoder.into_inner()?)?;
+    encoder.write_var_buffer(&self.len_encoder.into_inner()?)?;
+
+    // the rest buffer is appended without length prefix
+    let mut buffer = encoder.into_inner();
+    buffer.extend(self.rest.into_inner());
+    Ok(buffer)
+  }
+}
+
+impl CrdtWriter for RawEncoderV2 {
+  fn write_var_u64(&mut self, num: u64) -> JwstCodecResult {
+    self.rest.write_var_u64(num)
+  }
+  fn write_var_i32(&mut self, num: i32) -> JwstCodecResult {
+    self.rest.write_var_i32(num)
+  }
+  fn write_var_string<S: AsRef<str>>(&mut self, s: S) -> JwstCodecResult {
+    self.rest.write_var_string(s)
+  }
+  fn write_var_buffer(&mut self, buf: &[u8]) -> JwstCodecResult {
+    self.rest.write_var_buffer(buf)
+  }
+  fn write_u8(&mut self, num: u8) -> JwstCodecResult {
+    self.rest.write_u8(num)
+  }
+  fn write_f32_be(&mut self, num: f32) -> JwstCodecResult {
+    self.rest.write_f32_be(num)
+  }
+  fn write_f64_be(&mut self, num: f64) -> JwstCodecResult {
+    self.rest.write_f64_be(num)
+  }
+  fn write_i64_be(&mut self, num: i64) -> JwstCodecResult {
+    self.rest.write_i64_be(num)
+  }
+
+  #[inline(always)]
+  fn write_info(&mut self, num: u8) -> JwstCodecResult {
+    self.info_encoder.write(num)
+  }
+
+  #[inline(always)]
+  fn write_item_id(&mut self, id: &Id) -> JwstCodecResult {
+    self.write_left_id(id)
+  }
+
+  fn write_client(&mut self, client: u64) -> JwstCodecResult {
+    self.client_encoder.write(client)
+  }
+
+  fn write_left_id(&mut self, id: &Id) -> JwstCodecResult {
+    self.client_encoder.write(id.client)?;
+    self.left_clock_encoder.write(id.clock)
+  }
+
+  fn write_right_id(&mut self, id: &Id) -> JwstCodecResult {
+    self.client_encoder.write(id.client)?;
+    self.right_clock_encoder.write(id.clock)
+  }
+
+  fn write_parent_info(&mut self, is_root_name: bool) -> JwstCodecResult {
+    self.parent_info_encoder.write(is_root_name as u8)
+  }
+
+  fn write_type_ref(&mut self, type_ref: u64) -> JwstCodecResult {
+    self.type_ref_encoder.write(type_ref)
+  }
+
+  fn write_len(&mut self, len: u64) -> JwstCodecResult {
+    self.len_encoder.write(len)
+  }
+
+  fn write_string<S: AsRef<str>>(&mut self, s: S) -> JwstCodecResult {
+    self.string_encoder.write(s.as_ref())
+  }
+
+  fn write_key<S: AsRef<str>>(&mut self, key: S) -> JwstCodecResult {
+    // keys are not deduplicated yet, which is the same as yjs
+    self.key_clock_encoder.write(self.key_clock)?;
+    self.key_clock += 1;
+    self.string_encoder.write(key.as_ref())
+  }
+
+  fn write_buf(&mut self, buf: &[u8]) -> JwstCodecResult {
+    self.rest.write_var_buffer(buf)
+  }
+
+  fn write_json(&mut self, value: &Any) -> JwstCodecResult {
+    value.write(&mut self.rest)
+  }
+
+  fn reset_ds_cur_val(&mut self) {
+    self.ds_cur_val = 0;
+  }
+
+  fn write_ds_clock(&mut self, clock: u64) -> JwstCodecResult {
+    let diff = clock.checked_sub(self.ds_cur_val).ok_or_else(|| {
+      JwstCodecError::InvalidWriteBuffer(format!(
+        "delete set clock {clock} is smaller than {}",
+        self.ds_cur_val
+      ))
+    })?;
+    self.ds_cur_val = clock;
+    self.rest.write_var_u64(diff)
+  }
+
+  fn write_ds_len(&mut self, len: u64) -> JwstCodecResult {
+    if len == 0 {
+      return Err(JwstCodecError::InvalidWriteBuffer(
+        "delete set range can not be empty".to_string(),
+      ));
+    }
+    self.ds_cur_val += len;
+    self.rest.write_var_u64(len - 1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_uint_opt_rle() {
+    let values = [1, 1, 1, 0, 0, 5, 7, 7, 0, 300, 300];
+
+    let mut encoder = UintOptRleEncoder::default();
+    for v in values {
+      encoder.write(v).unwrap();
+    }
+    let buffer = encoder.into_inner().unwrap();
+    // run of `0` is stored as `-0`
+    assert_eq!(&buffer[2..4], &[0b0100_0000, 0]);
+
+    let mut decoder = UintOptRleDecoder::new(RawDecoder::new(&buffer));
+    for v in values {
+      assert_eq!(decoder.read().unwrap(), v);
+    }
+  }
+
+  #[test]
+  fn test_int_diff_opt_rle() {
+    let values = [0, 1, 2, 3, 10, 5, 5, 5, 100_000, 0];
+
+    let mut encoder = IntDiffOptRleEncoder::default();
+    for v in values {
+      encoder.write(v).unwrap();
+    }
+    let buffer = encoder.into_inner().unwrap();
+
+    let mut decoder = IntDiffOptRleDecoder::new(RawDecoder::new(&buffer));
+    for v in values {
+      assert_eq!(decoder.read().unwrap(), v);
+    }
+  }
+
+  #[test]
+  fn test_rle_run_len_overflow() {
+    let mut encoder = RawEncoder::default();
+    write_var_int(&mut encoder, true, 1).unwrap();
+    encoder.write_var_u64(u64::MAX).unwrap();
+    let buffer = encoder.into_inner();
+
+    assert!(matches!(
+      UintOptRleDecoder::new(RawDecoder::new(&buffer)).read(),
+      Err(JwstCodecError::IncompleteDocument(_))
+    ));
+    assert!(matches!(
+      IntDiffOptRleDecoder::new(RawDecoder::new(&buffer)).read(),
+      Err(JwstCodecError::IncompleteDocument(_))
+    ));
+  }
+
+  #[test]
+  fn test_rle_and_string() {
+    let values = [1, 1, 4, 4, 4, 8];
+
+    let mut encoder = RleEncoder::default();
+    for v in values {
+      encoder.write(v).unwrap();
+    }
+    let buffer = encoder.into_inner();
+
+    let mut decoder = RleDecoder::new(RawDecoder::new(&buffer));
+    for v in values {
+      assert_eq!(decoder.read().unwrap(), v);
+    }
+    // the last value repeats
+    assert_eq!(decoder.read().unwrap(), 8);
+
+    let strings = ["hello", "", "😀 world", "你好"];
+
+    let mut encoder = StringEncoder::default();
+    for s in strings {
+      encoder.write(s).unwrap();
+    }
+    let buffer = encoder.into_inner().unwrap();
+
+    let mut decoder = StringDecoder::new(RawDecoder::new(&buffer)).unwrap();
+    for s in strings {
+      assert_eq!(decoder.read().unwrap(), s);
+    }
+  }
+}
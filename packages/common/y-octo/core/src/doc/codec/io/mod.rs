@@ -1,8 +1,10 @@
 mod codec_v1;
+mod codec_v2;
 mod reader;
 mod writer;
 
 pub use codec_v1::{RawDecoder, RawEncoder};
+pub use codec_v2::{RawDecoderV2, RawEncoderV2};
 pub use reader::{CrdtRead, CrdtReader};
 pub use writer::{CrdtWrite, CrdtWriter};
 
@@ -19,6 +19,72 @@ pub trait CrdtWriter {
 
   fn write_info(&mut self, num: u8) -> JwstCodecResult;
   fn write_item_id(&mut self, id: &Id) -> JwstCodecResult;
+
+  // see [CrdtReader] for the semantic methods
+
+  #[inline(always)]
+  fn write_client(&mut self, client: u64) -> JwstCodecResult {
+    self.write_var_u64(client)
+  }
+
+  #[inline(always)]
+  fn write_left_id(&mut self, id: &Id) -> JwstCodecResult {
+    self.write_item_id(id)
+  }
+
+  #[inline(always)]
+  fn write_right_id(&mut self, id: &Id) -> JwstCodecResult {
+    self.write_item_id(id)
+  }
+
+  #[inline(always)]
+  fn write_parent_info(&mut self, is_root_name: bool) -> JwstCodecResult {
+    self.write_var_u64(is_root_name as u64)
+  }
+
+  #[inline(always)]
+  fn write_type_ref(&mut self, type_ref: u64) -> JwstCodecResult {
+    self.write_var_u64(type_ref)
+  }
+
+  #[inline(always)]
+  fn write_len(&mut self, len: u64) -> JwstCodecResult {
+    self.write_var_u64(len)
+  }
+
+  #[inline(always)]
+  fn write_string<S: AsRef<str>>(&mut self, s: S) -> JwstCodecResult {
+    self.write_var_string(s)
+  }
+
+  #[inline(always)]
+  fn write_key<S: AsRef<str>>(&mut self, key: S) -> JwstCodecResult {
+    self.write_var_string(key)
+  }
+
+  #[inline(always)]
+  fn write_buf(&mut self, buf: &[u8]) -> JwstCodecResult {
+    self.write_var_buffer(buf)
+  }
+
+  fn write_json(&mut self, value: &Any) -> JwstCodecResult {
+    self.write_var_string(
+      serde_json::to_string(value).map_err(|_| JwstCodecError::DamagedDocumentJson)?,
+    )
+  }
+
+  #[inline(always)]
+  fn reset_ds_cur_val(&mut self) {}
+
+  #[inline(always)]
+  fn write_ds_clock(&mut self, clock: u64) -> JwstCodecResult {
+    self.write_var_u64(clock)
+  }
+
+  #[inline(always)]
+  fn write_ds_len(&mut self, len: u64) -> JwstCodecResult {
+    self.write_var_u64(len)
+  }
 }
 
 pub trait CrdtWrite<W: CrdtWriter> {
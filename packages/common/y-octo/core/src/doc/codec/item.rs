@@ -26,6 +26,9 @@ pub(crate) struct Item {
   pub content: Content,
   #[cfg_attr(all(test, not(loom)), proptest(value = "ItemFlag::default()"))]
   pub flags: ItemFlag,
+  /// the move item that shows this item at its position, see [Move]
+  #[cfg_attr(all(test, not(loom)), proptest(value = "None"))]
+  pub moved: Option<Id>,
 }
 
 // make all Item readonly
@@ -87,6 +90,7 @@ impl Default for Item {
       parent_sub: None,
       content: Content::Deleted(0),
       flags: ItemFlag::from(0),
+      moved: None,
     }
   }
 }
@@ -116,6 +120,7 @@ impl Item {
       parent_sub,
       content,
       flags,
+      moved: None,
     }
   }
 
@@ -181,7 +186,7 @@ impl Item {
     let right_id = Id::new(id.client, id.clock + offset);
     let (left_content, right_content) = self.content.split(offset)?;
 
-    let left_item = Item::new(
+    let mut left_item = Item::new(
       id,
       left_content,
       // let caller connect left <-> node <-> right
@@ -191,7 +196,7 @@ impl Item {
       self.parent_sub.clone(),
     );
 
-    let right_item = Item::new(
+    let mut right_item = Item::new(
       right_id,
       right_content,
       // let caller connect left <-> node <-> right
@@ -200,6 +205,8 @@ impl Item {
       self.parent.clone(),
       self.parent_sub.clone(),
     );
+    left_item.moved = self.moved;
+    right_item.moved = self.moved;
 
     if left_item.deleted() {
       left_item.flags.set_deleted();
@@ -249,29 +256,29 @@ impl Item {
     let item = Self {
       id,
       origin_left_id: if has_left_id {
-        Some(decoder.read_item_id()?)
+        Some(decoder.read_left_id()?)
       } else {
         None
       },
       origin_right_id: if has_right_id {
-        Some(decoder.read_item_id()?)
+        Some(decoder.read_right_id()?)
       } else {
         None
       },
       parent: {
         if has_not_sibling {
-          let has_parent = decoder.read_var_u64()? == 1;
+          let has_parent = decoder.read_parent_info()?;
           Some(if has_parent {
-            Parent::String(SmolStr::new(decoder.read_var_string()?))
+            Parent::String(SmolStr::new(decoder.read_string()?))
           } else {
-            Parent::Id(decoder.read_item_id()?)
+            Parent::Id(decoder.read_left_id()?)
           })
         } else {
           None
         }
       },
       parent_sub: if has_not_sibling && has_parent_sub {
-        Some(SmolStr::new(decoder.read_var_string()?))
+        Some(SmolStr::new(decoder.read_string()?))
       } else {
         None
       },
@@ -284,6 +291,7 @@ impl Item {
       left: Somr::none(),
       right: Somr::none(),
       flags: ItemFlag::from(0),
+      moved: None,
     };
 
     if item.content.countable() {
@@ -306,31 +314,31 @@ impl Item {
     encoder.write_info(info)?;
 
     if let Some(left_id) = self.origin_left_id {
-      encoder.write_item_id(&left_id)?;
+      encoder.write_left_id(&left_id)?;
     }
     if let Some(right_id) = self.origin_right_id {
-      encoder.write_item_id(&right_id)?;
+      encoder.write_right_id(&right_id)?;
     }
 
     if has_not_sibling {
       if let Some(parent) = &self.parent {
         match parent {
           Parent::String(s) => {
-            encoder.write_var_u64(1)?;
-            encoder.write_var_string(s)?;
+            encoder.write_parent_info(true)?;
+            encoder.write_string(s)?;
           }
           Parent::Id(id) => {
-            encoder.write_var_u64(0)?;
-            encoder.write_item_id(id)?;
+            encoder.write_parent_info(false)?;
+            encoder.write_left_id(id)?;
           }
           Parent::Type(ty) => {
             if let Some(ty) = ty.ty() {
               if let Some(item) = ty.item.get() {
-                encoder.write_var_u64(0)?;
-                encoder.write_item_id(&item.id)?;
+                encoder.write_parent_info(false)?;
+                encoder.write_left_id(&item.id)?;
               } else if let Some(name) = &ty.root_name {
-                encoder.write_var_u64(1)?;
-                encoder.write_var_string(name)?;
+                encoder.write_parent_info(true)?;
+                encoder.write_string(name)?;
               }
             }
           }
@@ -342,7 +350,7 @@ impl Item {
       }
 
       if let Some(parent_sub) = &self.parent_sub {
-        encoder.write_var_string(parent_sub)?;
+        encoder.write_string(parent_sub)?;
       }
     }
 
@@ -5,6 +5,7 @@ mod id;
 mod io;
 mod item;
 mod item_flag;
+mod moving;
 mod refs;
 mod update;
 #[cfg(test)]
@@ -14,11 +15,14 @@ pub use any::Any;
 pub(crate) use content::Content;
 pub use delete_set::DeleteSet;
 pub use id::{Client, Clock, Id};
-pub use io::{CrdtRead, CrdtReader, CrdtWrite, CrdtWriter, RawDecoder, RawEncoder};
+pub use io::{
+  CrdtRead, CrdtReader, CrdtWrite, CrdtWriter, RawDecoder, RawDecoderV2, RawEncoder, RawEncoderV2,
+};
 pub(crate) use item::{Item, ItemRef, Parent};
 pub(crate) use item_flag::{item_flags, ItemFlag};
+pub(crate) use moving::Move;
 pub(crate) use refs::Node;
-pub use update::Update;
+pub use update::{Update, UpdateMeta};
 #[cfg(test)]
 pub(crate) use utils::*;
 
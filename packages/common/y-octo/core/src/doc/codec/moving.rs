@@ -0,0 +1,149 @@
+use super::*;
+
+/// The content of a move item, the items in the range are shown at the
+/// position of the move item instead of their original position, a.k.a
+/// `ContentMove` in yjs.
+///
+/// The range starts at `start` and ends before `end`, both are adjusted by
+/// their [Assoc] the same way as a [StickyIndex]: an [Assoc::After] boundary
+/// is the item with the id, an [Assoc::Before] boundary is the item right
+/// after it.
+#[derive(Clone)]
+pub(crate) struct Move {
+  pub start: Id,
+  pub start_assoc: Assoc,
+  pub end: Id,
+  pub end_assoc: Assoc,
+  /// the move with the higher priority wins when concurrent moves overlap,
+  /// negative for a local move, it will be assigned on integration
+  pub priority: i32,
+  /// the item starting at `start` for [Assoc::After] or ending at `start` for
+  /// [Assoc::Before], resolved on integration
+  pub start_anchor: ItemRef,
+  /// same as `start_anchor` for `end`
+  pub end_anchor: ItemRef,
+  /// the moves this one has taken items from or lost items to, they are
+  /// integrated again once this one is deleted
+  pub overrides: Vec<Id>,
+}
+
+impl Move {
+  pub fn new(start: Id, start_assoc: Assoc, end: Id, end_assoc: Assoc) -> Self {
+    Self {
+      start,
+      start_assoc,
+      end,
+      end_assoc,
+      priority: -1,
+      start_anchor: Somr::none(),
+      end_anchor: Somr::none(),
+      overrides: Vec::new(),
+    }
+  }
+
+  /// whether the move only moves the single item that the range starts at
+  pub fn is_collapsed(&self) -> bool {
+    self.start == self.end
+  }
+
+  /// the first item in the range
+  pub fn start_item(&self) -> ItemRef {
+    Self::boundary(&self.start_anchor, self.start, self.start_assoc)
+  }
+
+  /// the first item after the range
+  pub fn end_item(&self) -> ItemRef {
+    Self::boundary(&self.end_anchor, self.end, self.end_assoc)
+  }
+
+  /// the item right after the id is looked up on every read, items may be
+  /// inserted there, or the anchor may be split, after the integration
+  fn boundary(anchor: &ItemRef, id: Id, assoc: Assoc) -> ItemRef {
+    if assoc == Assoc::After {
+      return anchor.clone();
+    }
+
+    let mut cur = anchor.clone();
+    while let Some(item) = cur.get() {
+      if item.id.client == id.client
+        && item.id.clock <= id.clock
+        && id.clock < item.id.clock + item.len()
+      {
+        return item.right.clone();
+      }
+      cur = item.right.clone();
+    }
+
+    Somr::none()
+  }
+
+  pub(crate) fn read<R: CrdtReader>(decoder: &mut R) -> JwstCodecResult<Self> {
+    let flags = decoder.read_var_i32()?;
+    let is_collapsed = flags & 0b001 != 0;
+    let assoc = |bit: i32| {
+      if flags & bit != 0 {
+        Assoc::After
+      } else {
+        Assoc::Before
+      }
+    };
+
+    let start = Id::new(decoder.read_var_u64()?, decoder.read_var_u64()?);
+    let end = if is_collapsed {
+      start
+    } else {
+      Id::new(decoder.read_var_u64()?, decoder.read_var_u64()?)
+    };
+
+    Ok(Self {
+      priority: flags >> 6,
+      ..Self::new(start, assoc(0b010), end, assoc(0b100))
+    })
+  }
+
+  pub(crate) fn write<W: CrdtWriter>(&self, encoder: &mut W) -> JwstCodecResult {
+    let is_collapsed = self.is_collapsed();
+    let mut flags = self.priority << 6;
+    if is_collapsed {
+      flags |= 0b001;
+    }
+    if self.start_assoc == Assoc::After {
+      flags |= 0b010;
+    }
+    if self.end_assoc == Assoc::After {
+      flags |= 0b100;
+    }
+
+    encoder.write_var_i32(flags)?;
+    encoder.write_var_u64(self.start.client)?;
+    encoder.write_var_u64(self.start.clock)?;
+    if !is_collapsed {
+      encoder.write_var_u64(self.end.client)?;
+      encoder.write_var_u64(self.end.clock)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl PartialEq for Move {
+  fn eq(&self, other: &Self) -> bool {
+    self.start == other.start
+      && self.start_assoc == other.start_assoc
+      && self.end == other.end
+      && self.end_assoc == other.end_assoc
+      && self.priority == other.priority
+  }
+}
+
+impl std::fmt::Debug for Move {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Move")
+      .field("start", &self.start)
+      .field("start_assoc", &self.start_assoc)
+      .field("end", &self.end)
+      .field("end_assoc", &self.end_assoc)
+      .field("priority", &self.priority)
+      .finish()
+  }
+}
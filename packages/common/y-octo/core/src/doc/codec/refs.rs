@@ -22,7 +22,7 @@ impl<W: CrdtWriter> CrdtWrite<W> for Node {
     match self {
       Node::GC(item) => {
         writer.write_info(0)?;
-        writer.write_var_u64(item.len)
+        writer.write_len(item.len)
       }
       Node::Skip(item) => {
         writer.write_info(10)?;
@@ -69,7 +69,7 @@ impl Node {
 
     match first_5_bit {
       0 => {
-        let len = decoder.read_var_u64()?;
+        let len = decoder.read_len()?;
         Ok(Node::new_gc(id, len))
       }
       10 => {
@@ -204,7 +204,7 @@ impl Node {
       let right_id = Id::new(id.client, id.clock + offset);
       let (left_content, right_content) = item.content.split(offset)?;
 
-      let left_item = Somr::new(Item::new(
+      let mut left_item = Item::new(
         id,
         left_content,
         // let caller connect left <-> node <-> right
@@ -212,9 +212,11 @@ impl Node {
         Somr::none(),
         item.parent.clone(),
         item.parent_sub.clone(),
-      ));
+      );
+      left_item.origin_left_id = item.origin_left_id;
+      left_item.origin_right_id = item.origin_right_id;
 
-      let right_item = Somr::new(Item::new(
+      let mut right_item = Item::new(
         right_id,
         right_content,
         // let caller connect left <-> node <-> right
@@ -222,11 +224,28 @@ impl Node {
         Somr::none(),
         item.parent.clone(),
         item.parent_sub.clone(),
-      ));
-
-      Ok((Self::Item(left_item), Self::Item(right_item)))
+      );
+      right_item.origin_left_id = Some(left_item.last_id());
+      right_item.origin_right_id = item.origin_right_id;
+      left_item.moved = item.moved;
+      right_item.moved = item.moved;
+
+      Ok((
+        Self::Item(Somr::new(left_item)),
+        Self::Item(Somr::new(right_item)),
+      ))
     } else {
-      Err(JwstCodecError::ItemSplitNotSupport)
+      let id = self.id();
+      let right_id = Id::new(id.client, id.clock + offset);
+      let right_len = self.len() - offset;
+
+      Ok(match self {
+        Self::GC(_) => (Node::new_gc(id, offset), Node::new_gc(right_id, right_len)),
+        _ => (
+          Node::new_skip(id, offset),
+          Node::new_skip(right_id, right_len),
+        ),
+      })
     }
   }
 
@@ -265,6 +284,9 @@ impl Node {
                     || litem.origin_right_id != ritem.origin_right_id
                     // not runtime continuous
                     || litem.right != rref
+                    // the boundaries of a move range must be kept
+                    || litem.moved.is_some()
+                    || ritem.moved.is_some()
         {
           return false;
         }
@@ -26,7 +26,7 @@ impl<R: CrdtReader> CrdtRead<R> for Update {
     let mut map = ClientMap::with_capacity(num_of_clients.min(HASHMAP_SAFE_CAPACITY));
     for _ in 0..num_of_clients {
       let num_of_structs = decoder.read_var_u64()? as usize;
-      let client = decoder.read_var_u64()?;
+      let client = decoder.read_client()?;
       let mut clock = decoder.read_var_u64()?;
 
       // same reason as above
@@ -73,7 +73,7 @@ impl<W: CrdtWriter> CrdtWrite<W> for Update {
       let structs = self.structs.get(&client).unwrap();
 
       encoder.write_var_u64(structs.len() as u64)?;
-      encoder.write_var_u64(client)?;
+      encoder.write_client(client)?;
       encoder.write_var_u64(structs.front().map(|s| s.clock()).unwrap_or(0))?;
 
       for struct_info in structs {
@@ -87,7 +87,47 @@ impl<W: CrdtWriter> CrdtWrite<W> for Update {
   }
 }
 
+/// The clock ranges of the structs in an update, see [parse_update_meta].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UpdateMeta {
+  /// the clock of the first struct of each client
+  pub from: StateVector,
+  /// the clock next to the last struct of each client
+  pub to: StateVector,
+}
+
 impl Update {
+  /// Read the structs of an update one by one without collecting them into
+  /// an [Update], returns the delete set of the update.
+  pub(crate) fn read_structs<R: CrdtReader>(
+    decoder: &mut R,
+    mut f: impl FnMut(Node) -> JwstCodecResult,
+  ) -> JwstCodecResult<DeleteSet> {
+    let num_of_clients = decoder.read_var_u64()?;
+
+    for _ in 0..num_of_clients {
+      let num_of_structs = decoder.read_var_u64()?;
+      let client = decoder.read_client()?;
+      let mut clock = decoder.read_var_u64()?;
+
+      for _ in 0..num_of_structs {
+        let struct_info = Node::read(decoder, Id::new(client, clock))?;
+        clock += struct_info.len();
+        f(struct_info)?;
+      }
+    }
+
+    let delete_set = DeleteSet::read(decoder)?;
+
+    if !decoder.is_empty() {
+      return Err(JwstCodecError::UpdateNotFullyConsumed(
+        decoder.len() as usize
+      ));
+    }
+
+    Ok(delete_set)
+  }
+
   // decode from ydoc v1
   pub fn decode_v1<T: AsRef<[u8]>>(buffer: T) -> JwstCodecResult<Update> {
     Update::read(&mut RawDecoder::new(buffer.as_ref()))
@@ -99,6 +139,17 @@ impl Update {
     Ok(encoder.into_inner())
   }
 
+  // decode from ydoc v2
+  pub fn decode_v2<T: AsRef<[u8]>>(buffer: T) -> JwstCodecResult<Update> {
+    Update::read(&mut RawDecoderV2::new(buffer.as_ref())?)
+  }
+
+  pub fn encode_v2(&self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoderV2::default();
+    self.write(&mut encoder)?;
+    encoder.into_inner()
+  }
+
   pub(crate) fn iter(&mut self, state: StateVector) -> UpdateIterator {
     UpdateIterator::new(self, state)
   }
@@ -107,6 +158,66 @@ impl Update {
     DeleteSetIterator::new(self, state)
   }
 
+  /// The clock of each client that has to be integrated before the update
+  /// can be applied on top of the given state, same as the `missing` state
+  /// of the pending update in yjs.
+  pub(crate) fn missing_state(&self, state: &StateVector) -> StateVector {
+    let mut update = self.clone();
+    update.missing_state = StateVector::default();
+
+    let mut state = state.clone();
+    for (node, _) in update.iter(state.clone()) {
+      state.set_max(node.client(), node.clock() + node.len());
+    }
+    for _ in update.delete_set_iter(state.clone()) {}
+
+    let mut missing = update.missing_state;
+    for (client, _) in update.pending_delete_set.iter() {
+      missing.set_min(*client, state.get(client));
+    }
+
+    missing
+  }
+
+  /// Clone the update with the items not shared with the original one, as
+  /// the items of a decoded update are owned by it.
+  pub(crate) fn deep_clone(&self) -> Update {
+    let clone_structs = |structs: &ClientMap<VecDeque<Node>>| {
+      structs
+        .iter()
+        .map(|(client, nodes)| {
+          let nodes = nodes
+            .iter()
+            .map(|node| match node.as_item().get() {
+              Some(item) => {
+                let mut item = item.clone();
+                if let Content::Type(ty) = &mut item.content {
+                  let cloned = ty
+                    .ty()
+                    .map(|inner| YTypeRef::new(inner.kind(), inner.name.clone()));
+                  if let Some(cloned) = cloned {
+                    *ty = cloned;
+                  }
+                }
+                Node::Item(Somr::new(item))
+              }
+              None => node.clone(),
+            })
+            .collect();
+          (*client, nodes)
+        })
+        .collect()
+    };
+
+    Update {
+      structs: clone_structs(&self.structs),
+      delete_set: self.delete_set.clone(),
+      pending_structs: clone_structs(&self.pending_structs),
+      missing_state: self.missing_state.clone(),
+      pending_delete_set: self.pending_delete_set.clone(),
+    }
+  }
+
   // take all pending structs and delete set to [self] update struct
   pub fn drain_pending_state(&mut self) {
     debug_assert!(self.is_empty());
@@ -277,7 +388,7 @@ impl<'a> UpdateIterator<'a> {
     }
   }
 
-  /// tell if current update's dependencies(left, right, parent) has already
+  /// tell if current update's dependencies(left, right, parent, moved range) has already
   /// been consumed and recorded and return the client of them if not.
   fn get_missing_dep(&self, struct_info: &Node) -> Option<Client> {
     if let Some(item) = struct_info.as_item().get() {
@@ -305,6 +416,15 @@ impl<'a> UpdateIterator<'a> {
           _ => {}
         }
       }
+
+      // the range of a move has to exist to be shown at the move item
+      if let Content::Move(mv) = &item.content {
+        for boundary in [mv.start, mv.end] {
+          if boundary.client != id.client && boundary.clock >= self.state.get(&boundary.client) {
+            return Some(boundary.client);
+          }
+        }
+      }
     }
 
     None
@@ -458,7 +578,7 @@ mod tests {
   use serde::Deserialize;
 
   use super::*;
-  use crate::doc::common::OrderRange;
+  use crate::doc::{common::OrderRange, convert_update_v1_to_v2, convert_update_v2_to_v1};
 
   fn struct_item(id: (Client, Clock), len: usize) -> Node {
     Node::Item(Somr::new(
@@ -507,6 +627,69 @@ mod tests {
     }
   }
 
+  fn assert_update_eq(left: &Update, right: &Update) {
+    assert_eq!(left.delete_set, right.delete_set);
+    assert_eq!(left.structs.len(), right.structs.len());
+
+    for (client, structs) in left.structs.iter() {
+      let other = right.structs.get(client).unwrap();
+      assert_eq!(structs.len(), other.len());
+
+      for (left, right) in structs.iter().zip(other) {
+        assert_eq!(left.id(), right.id());
+        assert_eq!(left.len(), right.len());
+
+        if let (Node::Item(left), Node::Item(right)) = (left, right) {
+          let (left, right) = (left.get().unwrap(), right.get().unwrap());
+          assert_eq!(left.origin_left_id, right.origin_left_id);
+          assert_eq!(left.origin_right_id, right.origin_right_id);
+          assert_eq!(format!("{:?}", left.parent), format!("{:?}", right.parent));
+          assert_eq!(left.parent_sub, right.parent_sub);
+          assert_eq!(left.content, right.content);
+        } else {
+          assert_eq!(left, right);
+        }
+      }
+    }
+  }
+
+  #[test]
+  #[cfg_attr(any(miri, loom), ignore)]
+  fn test_update_v2_round_trip() {
+    use yrs::updates::{decoder::Decode, encoder::Encode};
+
+    let docs = [
+      include_bytes!("../../fixtures/basic.bin").to_vec(),
+      include_bytes!("../../fixtures/database.bin").to_vec(),
+      include_bytes!("../../fixtures/large.bin").to_vec(),
+      include_bytes!("../../fixtures/with-subdoc.bin").to_vec(),
+      include_bytes!("../../fixtures/edge-case-left-right-same-node.bin").to_vec(),
+    ];
+
+    for doc in docs {
+      let update = Update::decode_v1(&doc).unwrap();
+      let v2 = convert_update_v1_to_v2(&doc).unwrap();
+
+      assert_update_eq(&Update::decode_v2(&v2).unwrap(), &update);
+      assert_update_eq(
+        &Update::decode_v1(convert_update_v2_to_v1(&v2).unwrap()).unwrap(),
+        &update,
+      );
+
+      // yrs may normalize some values while re-encoding,
+      // so compare the results with its own v1 output
+      let yrs_update = yrs::Update::decode_v1(&doc).unwrap();
+      let yrs_v1 = Update::decode_v1(yrs_update.encode_v1()).unwrap();
+
+      // yrs should read the same update from both formats
+      let yrs_v2 = yrs::Update::decode_v2(&v2).unwrap().encode_v1();
+      assert_update_eq(&Update::decode_v1(yrs_v2).unwrap(), &yrs_v1);
+
+      // and y-octo should read the v2 binary encoded by yrs
+      assert_update_eq(&Update::decode_v2(yrs_update.encode_v2()).unwrap(), &yrs_v1);
+    }
+  }
+
   fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
     (0..s.len())
       .step_by(2)
@@ -1,7 +1,9 @@
+mod origin;
 mod range;
 mod somr;
 mod state;
 
+pub use origin::*;
 pub use range::*;
 pub use somr::*;
 pub use state::*;
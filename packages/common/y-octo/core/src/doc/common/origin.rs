@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use smol_str::SmolStr;
+
+/// The origin of local changes, used to tell who made the changes.
+///
+/// ```
+/// use y_octo::{Doc, Origin};
+///
+/// let doc = Doc::new();
+/// let mut text = doc.get_or_create_text("text").unwrap();
+///
+/// doc.with_origin("migration", |_| text.insert(0, "hello")).unwrap();
+/// assert_eq!(Origin::from("migration").as_str(), "migration");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin(SmolStr);
+
+impl Origin {
+  pub fn as_str(&self) -> &str {
+    self.0.as_str()
+  }
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl From<&str> for Origin {
+  fn from(value: &str) -> Self {
+    Self(SmolStr::new(value))
+  }
+}
+
+impl From<String> for Origin {
+  fn from(value: String) -> Self {
+    Self(SmolStr::new(value))
+  }
+}
+
+macro_rules! impl_origin_from_number {
+  ($($ty: ty),*) => {
+    $(
+      impl From<$ty> for Origin {
+        fn from(value: $ty) -> Self {
+          Self(SmolStr::new(value.to_string()))
+        }
+      }
+    )*
+  };
+}
+
+impl_origin_from_number!(u32, u64, i32, i64, usize);
@@ -2,7 +2,7 @@ use std::ops::{Deref, DerefMut};
 
 use super::{
   Client, ClientMap, Clock, CrdtRead, CrdtReader, CrdtWrite, CrdtWriter, HashMapExt, Id,
-  JwstCodecResult, HASHMAP_SAFE_CAPACITY,
+  JwstCodecResult, RawDecoder, RawEncoder, HASHMAP_SAFE_CAPACITY,
 };
 
 #[derive(Default, Debug, PartialEq, Clone)]
@@ -48,6 +48,16 @@ impl StateVector {
       self.set_min(*client, *clock);
     }
   }
+
+  pub fn decode_v1<T: AsRef<[u8]>>(buffer: T) -> JwstCodecResult<Self> {
+    Self::read(&mut RawDecoder::new(buffer.as_ref()))
+  }
+
+  pub fn encode_v1(&self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoder::default();
+    self.write(&mut encoder)?;
+    Ok(encoder.into_inner())
+  }
 }
 
 impl Deref for StateVector {
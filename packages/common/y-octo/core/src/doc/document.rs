@@ -1,14 +1,74 @@
-use super::{history::StoreHistory, publisher::DocPublisher, store::StoreRef, *};
+use std::ops::Range;
+
+use super::{
+  history::StoreHistory, publisher::DocPublisher, store::StoreRef, transaction::transact, *,
+};
 use crate::sync::{Arc, RwLock};
 
-#[cfg(feature = "debug")]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DocStoreStatus {
   pub nodes: usize,
   pub delete_sets: usize,
   pub types: usize,
   pub dangling_types: usize,
   pub pending_nodes: usize,
+  /// the number of items, including the deleted ones, of each client
+  pub items_per_client: HashMap<Client, usize>,
+  /// the number of deleted items whose content is not collected yet
+  pub deleted_items: usize,
+  /// the number of collected structs and the clocks they cover
+  pub gc_ranges: usize,
+  pub gc_clocks: u64,
+  /// the size of the item contents encoded in v1 format
+  pub content_bytes: usize,
+  /// the part of [DocStoreStatus::content_bytes] that belongs to deleted
+  /// items and can be reclaimed by gc
+  pub deleted_content_bytes: usize,
+}
+
+/// Which deleted content is collected by [Doc::gc_with], everything deleted
+/// is collected by default.
+///
+/// ```
+/// use y_octo::{DocOptions, GcOptions};
+///
+/// let doc = DocOptions::new().auto_gc(false).build();
+/// let mut text = doc.get_or_create_text("text").unwrap();
+/// text.insert(0, "hello").unwrap();
+/// let snapshot = doc.snapshot();
+/// text.remove(0, 5).unwrap();
+///
+/// let stats = doc
+///   .gc_with(&GcOptions {
+///     keep_snapshot: Some(snapshot.clone()),
+///     ..Default::default()
+///   })
+///   .unwrap();
+/// assert_eq!(stats.items, 0);
+/// assert_eq!(text.to_string_at(&snapshot), "hello");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GcOptions {
+  /// keep the deleted items inserted after the state vector
+  pub keep_newer_than: Option<StateVector>,
+  /// keep the deleted items visible in the snapshot, so the doc can still be
+  /// read as of it
+  pub keep_snapshot: Option<Snapshot>,
+  /// only collect the items belonging to these root types, all types if empty
+  pub roots: Vec<String>,
+  /// only report what would be collected without modifying the doc
+  pub dry_run: bool,
+}
+
+/// What is collected, or would be collected in dry run, by [Doc::gc_with].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GcStats {
+  /// the number of items collected
+  pub items: usize,
+  /// the clocks covered by the collected content
+  pub clocks: u64,
+  /// the size of the collected content encoded in v1 format
+  pub bytes: usize,
 }
 
 /// [DocOptions] used to create a new [Doc]
@@ -29,6 +89,12 @@ pub struct DocOptions {
   pub guid: String,
   pub client_id: u64,
   pub gc: bool,
+  /// load the doc automatically once it's added as a subdoc
+  pub auto_load: bool,
+  /// whether the content of the subdoc is requested
+  pub should_load: bool,
+  /// the unit of the indexes and lengths taken and returned by [Text]
+  pub offset_kind: OffsetKind,
 }
 
 impl Default for DocOptions {
@@ -38,35 +104,41 @@ impl Default for DocOptions {
         client_id: 1,
         guid: "test".into(),
         gc: true,
+        auto_load: false,
+        should_load: true,
+        offset_kind: OffsetKind::default(),
       }
     } else {
-      /// It tends to generate small numbers.
-      /// Since the client id will be included in all crdt items, the
-      /// small client helps to reduce the binary size.
-      ///
-      /// NOTE: The probability of 36% of the random number generated by
-      /// this function is greater than [u32::MAX]
-      fn prefer_small_random() -> u64 {
-        use rand::{distr::Distribution, rng};
-        use rand_distr::Exp;
-
-        let scale_factor = u16::MAX as f64;
-        let v: f64 = Exp::new(1.0 / scale_factor)
-          .map(|exp| exp.sample(&mut rng()))
-          .unwrap_or_else(|_| rand::random());
-
-        (v * scale_factor) as u64
-      }
-
       Self {
         client_id: prefer_small_random(),
         guid: nanoid::nanoid!(),
         gc: true,
+        auto_load: false,
+        should_load: true,
+        offset_kind: OffsetKind::default(),
       }
     }
   }
 }
 
+/// It tends to generate small numbers.
+/// Since the client id will be included in all crdt items, the
+/// small client helps to reduce the binary size.
+///
+/// NOTE: The probability of 36% of the random number generated by
+/// this function is greater than [u32::MAX]
+pub(crate) fn prefer_small_random() -> u64 {
+  use rand::{distr::Distribution, rng};
+  use rand_distr::Exp;
+
+  let scale_factor = u16::MAX as f64;
+  let v: f64 = Exp::new(1.0 / scale_factor)
+    .map(|exp| exp.sample(&mut rng()))
+    .unwrap_or_else(|_| rand::random());
+
+  (v * scale_factor) as u64
+}
+
 impl DocOptions {
   pub fn new() -> Self {
     Self::default()
@@ -87,6 +159,21 @@ impl DocOptions {
     self
   }
 
+  pub fn auto_load(mut self, auto_load: bool) -> Self {
+    self.auto_load = auto_load;
+    self
+  }
+
+  pub fn should_load(mut self, should_load: bool) -> Self {
+    self.should_load = should_load;
+    self
+  }
+
+  pub fn with_offset_kind(mut self, offset_kind: OffsetKind) -> Self {
+    self.offset_kind = offset_kind;
+    self
+  }
+
   pub fn build(self) -> Doc {
     Doc::with_options(self)
   }
@@ -94,10 +181,15 @@ impl DocOptions {
 
 impl From<DocOptions> for Any {
   fn from(value: DocOptions) -> Self {
-    Any::Object(HashMap::from_iter([
+    let mut opts = HashMap::from_iter([
       ("gc".into(), value.gc.into()),
       ("guid".into(), value.guid.into()),
-    ]))
+    ]);
+    if value.auto_load {
+      opts.insert("autoLoad".into(), true.into());
+    }
+
+    Any::Object(opts)
   }
 }
 
@@ -107,7 +199,8 @@ impl TryFrom<Any> for DocOptions {
   fn try_from(value: Any) -> Result<Self, Self::Error> {
     match value {
       Any::Object(map) => {
-        let mut options = DocOptions::default();
+        // the subdocs are not loaded unless requested
+        let mut options = DocOptions::default().should_load(false);
         for (key, value) in map {
           match key.as_str() {
             "gc" => {
@@ -116,6 +209,13 @@ impl TryFrom<Any> for DocOptions {
             "guid" => {
               options.guid = String::try_from(value)?;
             }
+            "autoLoad" => {
+              options.auto_load = bool::try_from(value)?;
+              options.should_load |= options.auto_load;
+            }
+            "shouldLoad" => {
+              options.should_load |= bool::try_from(value)?;
+            }
             _ => {}
           }
         }
@@ -129,7 +229,6 @@ impl TryFrom<Any> for DocOptions {
 
 #[derive(Debug, Clone)]
 pub struct Doc {
-  client_id: u64,
   opts: DocOptions,
 
   pub(crate) store: StoreRef,
@@ -147,7 +246,7 @@ impl Default for Doc {
 
 impl PartialEq for Doc {
   fn eq(&self, other: &Self) -> bool {
-    self.client_id == other.client_id
+    self.client() == other.client()
   }
 }
 
@@ -157,11 +256,13 @@ impl Doc {
   }
 
   pub fn with_options(options: DocOptions) -> Self {
-    let store = Arc::new(RwLock::new(DocStore::with_client(options.client_id)));
+    let mut store = DocStore::with_client(options.client_id);
+    store.should_load = options.should_load || options.auto_load;
+    store.offset_kind = options.offset_kind;
+    let store = Arc::new(RwLock::new(store));
     let publisher = Arc::new(DocPublisher::new(store.clone()));
 
     Self {
-      client_id: options.client_id,
       opts: options,
       store,
       publisher,
@@ -172,8 +273,10 @@ impl Doc {
     DocOptions::new().with_client_id(client_id).build()
   }
 
+  /// The client id of the local changes, which is replaced once a remote
+  /// update is found using it, see [Doc::observe_client_id].
   pub fn client(&self) -> Client {
-    self.client_id
+    self.store.read().unwrap().client()
   }
 
   pub fn clients(&self) -> Vec<u64> {
@@ -186,30 +289,69 @@ impl Doc {
     history
   }
 
-  #[cfg(feature = "debug")]
   pub fn store_status(&self) -> DocStoreStatus {
-    let store = self.store.read().unwrap();
-
-    DocStoreStatus {
-      nodes: store.total_nodes(),
-      delete_sets: store.total_delete_sets(),
-      types: store.total_types(),
-      dangling_types: store.total_dangling_types(),
-      pending_nodes: store.total_pending_nodes(),
-    }
+    self.store.read().unwrap().status()
   }
 
   pub fn options(&self) -> &DocOptions {
     &self.opts
   }
 
+  /// Create an independent copy of the doc with a new client id, the changes
+  /// made to the copy are merged back with [Doc::diff_since_fork].
+  ///
+  /// ```
+  /// use y_octo::Doc;
+  ///
+  /// let mut doc = Doc::new();
+  /// doc.get_or_create_text("text").unwrap().insert(0, "hello").unwrap();
+  ///
+  /// let fork = doc.fork();
+  /// fork.get_or_create_text("text").unwrap().insert(5, " world").unwrap();
+  ///
+  /// doc.apply_update_from_binary_v1(fork.diff_since_fork().unwrap()).unwrap();
+  /// assert_eq!(doc.get_or_create_text("text").unwrap().to_string(), "hello world");
+  /// ```
+  pub fn fork(&self) -> Doc {
+    let clients = self.clients();
+    let client_id = loop {
+      let client_id = prefer_small_random();
+      if client_id != self.client() && !clients.contains(&client_id) {
+        break client_id;
+      }
+    };
+
+    let doc = Doc::with_options(DocOptions {
+      client_id,
+      should_load: true,
+      ..self.opts.clone()
+    });
+    self.store.read().unwrap().clone_into(&doc.store);
+
+    let state = doc.get_state_vector();
+    doc.store.write().unwrap().fork_state = Some(state);
+
+    doc
+  }
+
+  /// The state of the doc this one is forked from at the time of
+  /// [Doc::fork], [None] if the doc is not a fork.
+  pub fn fork_state(&self) -> Option<StateVector> {
+    self.store.read().unwrap().fork_state.clone()
+  }
+
+  /// Encode the changes made to the fork since [Doc::fork], including the
+  /// ones it received from others, as the update to bring the original doc
+  /// up to date.
+  pub fn diff_since_fork(&self) -> JwstCodecResult<Vec<u8>> {
+    let state = self.fork_state().ok_or(JwstCodecError::NotForked)?;
+    self.encode_state_as_update_v1(&state)
+  }
+
   pub fn guid(&self) -> &str {
     self.opts.guid.as_str()
   }
 
-  // TODO:
-  //   provide a better way instead of `_v1` methods
-  //   when implementing `v2` binary format
   pub fn try_from_binary_v1<T: AsRef<[u8]>>(binary: T) -> JwstCodecResult<Self> {
     Self::try_from_binary_v1_with_options(binary, DocOptions::default())
   }
@@ -229,11 +371,83 @@ impl Doc {
     self.apply_update(update)
   }
 
-  pub fn apply_update(&mut self, mut update: Update) -> JwstCodecResult {
-    let mut store = self.store.write().unwrap();
+  pub fn try_from_binary_v2<T: AsRef<[u8]>>(binary: T) -> JwstCodecResult<Self> {
+    Self::try_from_binary_v2_with_options(binary, DocOptions::default())
+  }
+
+  pub fn try_from_binary_v2_with_options<T: AsRef<[u8]>>(
+    binary: T,
+    options: DocOptions,
+  ) -> JwstCodecResult<Self> {
+    let mut doc = Doc::with_options(options);
+    doc.apply_update_from_binary_v2(binary)?;
+    Ok(doc)
+  }
+
+  pub fn apply_update_from_binary_v2<T: AsRef<[u8]>>(&mut self, binary: T) -> JwstCodecResult {
+    self.apply_update(Update::decode_v2(binary)?)
+  }
+
+  pub fn apply_update(&mut self, update: Update) -> JwstCodecResult {
+    let store_ref = self.store.clone();
+    transact(&store_ref, None, || {
+      let mut store = store_ref.write().unwrap();
+
+      // changes from remote are not local changes, hide them from the recorders
+      let recorders = std::mem::take(&mut store.change_recorders);
+      let result = self.integrate_update(&mut store, update, None);
+      store.change_recorders = recorders;
+
+      result
+    })
+  }
+
+  /// integrate the update into the store, the structs and deletions failed to
+  /// integrate are dropped and reported if a [RepairReport] is given
+  pub(crate) fn integrate_update(
+    &self,
+    store: &mut DocStore,
+    update: Update,
+    report: Option<&mut RepairReport>,
+  ) -> JwstCodecResult {
+    let client = store.client();
+    let state = store.get_state(client);
+
+    let result = self.integrate_update_structs(store, update, report);
+
+    // someone else is using our client id, the following local changes would
+    // conflict with theirs, switch to a new one as yjs does
+    if store.get_state(client) != state {
+      let new = store.rotate_client();
+      warn!("client id {client} is used by a remote update, switched to {new}");
+      if let Some(txn) = store.txn.as_mut() {
+        txn.client_id_changed = Some(ClientIdEvent {
+          old: txn
+            .client_id_changed
+            .map(|event| event.old)
+            .unwrap_or(client),
+          new,
+        });
+      }
+    }
+
+    result
+  }
+
+  fn integrate_update_structs(
+    &self,
+    store: &mut DocStore,
+    mut update: Update,
+    mut report: Option<&mut RepairReport>,
+  ) -> JwstCodecResult {
     let mut retry = false;
     loop {
       for (mut s, offset) in update.iter(store.get_state_vector()) {
+        if let Some(report) = report.as_deref_mut() {
+          store.integrate_with_repair(s, offset, self.store.clone(), report)?;
+          continue;
+        }
+
         if let Node::Item(item) = &mut s {
           debug_assert!(item.is_owned());
           let mut item = unsafe { item.get_mut_unchecked() };
@@ -243,7 +457,7 @@ impl Doc {
       }
 
       for (client, range) in update.delete_set_iter(store.get_state_vector()) {
-        store.delete_range(client, range)?;
+        Self::delete_range(store, client, range, report.as_deref_mut())?;
       }
 
       if let Some(mut pending_update) = store.pending.take() {
@@ -257,7 +471,7 @@ impl Doc {
         }
 
         for (client, range) in pending_update.delete_set_iter(store.get_state_vector()) {
-          store.delete_range(client, range)?;
+          Self::delete_range(store, client, range, report.as_deref_mut())?;
         }
 
         if update.is_pending_empty() {
@@ -293,6 +507,58 @@ impl Doc {
     Ok(())
   }
 
+  fn delete_range(
+    store: &mut DocStore,
+    client: Client,
+    range: Range<Clock>,
+    report: Option<&mut RepairReport>,
+  ) -> JwstCodecResult {
+    match (store.delete_range(client, range.clone()), report) {
+      (Err(_), Some(report)) => {
+        report.fail_deletion(client, range);
+        Ok(())
+      }
+      (result, _) => result,
+    }
+  }
+
+  /// Run `f` in a transaction tagged with the given [Origin], a shorthand of
+  /// [Doc::transact].
+  ///
+  /// See [UndoManager] for how origins are tracked.
+  pub fn with_origin<T, O: Into<Origin>>(&self, origin: O, f: impl FnOnce(&Self) -> T) -> T {
+    self.transact(origin, |_| f(self))
+  }
+
+  /// Get the value at the path, see [TypePath].
+  pub fn get_by_path(&self, path: &TypePath) -> Option<Value> {
+    let root = self.store.read().unwrap().types.get(&path.root).cloned()?;
+    let kind = root.ty()?.kind();
+    let mut value = Value::from_type(root, kind);
+
+    for segment in &path.segments {
+      value = value.as_type()?.get_by_segment(segment)?;
+    }
+
+    Some(value)
+  }
+
+  /// Get the type created by the item with the given id, [None] if the item
+  /// is not a type or has been deleted.
+  pub fn get_by_id(&self, id: Id) -> Option<Value> {
+    let node = {
+      let store = self.store.read().unwrap();
+      if store.get_state(id.client) <= id.clock {
+        return None;
+      }
+      store.get_node(id)?
+    };
+    let item = node.as_item();
+    let item = item.get().filter(|item| item.id == id && !item.deleted())?;
+
+    matches!(item.content, Content::Type(_)).then(|| Value::from(&item.content))
+  }
+
   pub fn keys(&self) -> Vec<String> {
     let store = self.store.read().unwrap();
     store.types.keys().cloned().collect()
@@ -337,6 +603,39 @@ impl Doc {
       .build()
   }
 
+  pub fn get_or_create_xml_fragment<S: AsRef<str>>(&self, str: S) -> JwstCodecResult<XMLFragment> {
+    YTypeBuilder::new(self.store.clone())
+      .with_kind(YTypeKind::XMLFragment)
+      .set_name(str.as_ref().to_string())
+      .build()
+  }
+
+  pub fn create_xml_fragment(&self) -> JwstCodecResult<XMLFragment> {
+    YTypeBuilder::new(self.store.clone())
+      .with_kind(YTypeKind::XMLFragment)
+      .build()
+  }
+
+  pub fn create_xml_element<S: AsRef<str>>(&self, node_name: S) -> JwstCodecResult<XMLElement> {
+    YTypeBuilder::new(self.store.clone())
+      .with_kind(YTypeKind::XMLElement)
+      .set_tag_name(node_name.as_ref().to_string())
+      .build()
+  }
+
+  pub fn create_xml_text(&self) -> JwstCodecResult<XMLText> {
+    YTypeBuilder::new(self.store.clone())
+      .with_kind(YTypeKind::XMLText)
+      .build()
+  }
+
+  pub fn create_xml_hook<S: AsRef<str>>(&self, hook_name: S) -> JwstCodecResult<XMLHook> {
+    YTypeBuilder::new(self.store.clone())
+      .with_kind(YTypeKind::XMLHook)
+      .set_tag_name(hook_name.as_ref().to_string())
+      .build()
+  }
+
   pub fn get_map(&self, str: &str) -> JwstCodecResult<Map> {
     YTypeBuilder::new(self.store.clone())
       .with_kind(YTypeKind::Map)
@@ -344,6 +643,89 @@ impl Doc {
       .build_exists()
   }
 
+  /// Export the root types as a JSON object, same as `Y.Doc#toJSON`.
+  ///
+  /// The kind of a root type only decoded from updates is guessed by its
+  /// content: [Text] if it holds strings, [Array] if it holds other values,
+  /// or [Map] if it holds entries, the empty ones are left out.
+  pub fn to_json(&self) -> JwstCodecResult<serde_json::Value> {
+    let types = {
+      let store = self.store.read().unwrap();
+      store
+        .types
+        .iter()
+        .map(|(name, ty)| (name.clone(), ty.clone()))
+        .collect::<Vec<_>>()
+    };
+
+    let mut json = serde_json::Map::new();
+    for (name, ty) in types {
+      let kind = match ty.ty() {
+        Some(inner) if inner.kind() != YTypeKind::Unknown => inner.kind(),
+        Some(inner) if inner.start.is_some() => {
+          let mut kind = YTypeKind::Array;
+          let mut cur = inner.start.clone();
+          while let Some(item) = cur.get() {
+            if matches!(
+              item.content,
+              Content::String(_) | Content::Format { .. } | Content::Embed(_)
+            ) {
+              kind = YTypeKind::Text;
+              break;
+            }
+            cur = item.right.clone();
+          }
+          kind
+        }
+        Some(inner) if !inner.map.is_empty() => YTypeKind::Map,
+        _ => continue,
+      };
+
+      let value = serde_json::to_value(Value::from_type(ty, kind))
+        .map_err(|_| JwstCodecError::DamagedDocumentJson)?;
+      json.insert(name, value);
+    }
+
+    Ok(serde_json::Value::Object(json))
+  }
+
+  /// Create a doc from a JSON object, the root types are created by the kind
+  /// of the values: [Map] for objects, [Array] for arrays and [Text] for
+  /// strings, the nested values are inserted by [Map::insert_json].
+  pub fn from_json(json: serde_json::Value) -> JwstCodecResult<Self> {
+    let serde_json::Value::Object(roots) = json else {
+      return Err(JwstCodecError::UnexpectedType("object"));
+    };
+
+    let doc = Doc::new();
+    transact(&doc.store, None, || -> JwstCodecResult {
+      for (name, value) in roots {
+        match value {
+          serde_json::Value::Object(entries) => {
+            let mut map = doc.get_or_create_map(&name)?;
+            for (key, value) in entries {
+              map.insert_json(key, value)?;
+            }
+          }
+          serde_json::Value::Array(values) => {
+            let mut array = doc.get_or_create_array(&name)?;
+            for value in values {
+              array.push_json(value)?;
+            }
+          }
+          serde_json::Value::String(str) => {
+            doc.get_or_create_text(&name)?.insert(0, str)?;
+          }
+          _ => return Err(JwstCodecError::UnexpectedType("object, array or string")),
+        }
+      }
+
+      Ok(())
+    })?;
+
+    Ok(doc)
+  }
+
   pub fn encode_update_v1(&self) -> JwstCodecResult<Vec<u8>> {
     self.encode_state_as_update_v1(&StateVector::default())
   }
@@ -356,6 +738,14 @@ impl Doc {
     Ok(encoder.into_inner())
   }
 
+  pub fn encode_update_v2(&self) -> JwstCodecResult<Vec<u8>> {
+    self.encode_state_as_update_v2(&StateVector::default())
+  }
+
+  pub fn encode_state_as_update_v2(&self, sv: &StateVector) -> JwstCodecResult<Vec<u8>> {
+    self.encode_state_as_update(sv)?.encode_v2()
+  }
+
   pub fn encode_update(&self) -> JwstCodecResult<Update> {
     self.encode_state_as_update(&StateVector::default())
   }
@@ -364,10 +754,59 @@ impl Doc {
     self.store.read().unwrap().diff_state_vector(sv, true)
   }
 
+  /// Encode the changes since the state vector made to the types allowed by
+  /// the filter only, see [UpdateFilter].
+  pub fn encode_state_as_update_filtered(
+    &self,
+    sv: &StateVector,
+    filter: &UpdateFilter,
+  ) -> JwstCodecResult<Vec<u8>> {
+    let update = self
+      .store
+      .read()
+      .unwrap()
+      .diff_state_vector_filtered(sv, filter)?;
+
+    let mut encoder = RawEncoder::default();
+    update.write(&mut encoder)?;
+    Ok(encoder.into_inner())
+  }
+
   pub fn get_state_vector(&self) -> StateVector {
     self.store.read().unwrap().get_state_vector()
   }
 
+  /// Whether the doc holds the structs or deletions of applied updates that
+  /// can't be integrated until their dependencies arrive.
+  pub fn has_pending(&self) -> bool {
+    self.store.read().unwrap().pending.is_some()
+  }
+
+  /// The missing dependencies of the pending updates, the clock of each
+  /// client that has to be integrated before they can be applied. The
+  /// updates of the clients since [Doc::get_state_vector] fill the gap.
+  pub fn pending_state(&self) -> StateVector {
+    let store = self.store.read().unwrap();
+    store
+      .pending
+      .as_ref()
+      .map(|pending| pending.missing_state(&store.get_state_vector()))
+      .unwrap_or_default()
+  }
+
+  /// Encode the pending updates not integrated into the doc, e.g. to persist
+  /// them along with the doc or to forward them.
+  pub fn encode_pending_update_v1(&self) -> JwstCodecResult<Option<Vec<u8>>> {
+    self
+      .store
+      .read()
+      .unwrap()
+      .pending
+      .as_ref()
+      .map(|pending| pending.encode_v1())
+      .transpose()
+  }
+
   pub fn subscribe(&self, cb: impl Fn(&[u8], &[History]) + Sync + Send + 'static) {
     self.publisher.subscribe(cb);
   }
@@ -380,14 +819,118 @@ impl Doc {
     self.publisher.count()
   }
 
+  /// The subdocs integrated into this doc.
+  pub fn subdocs(&self) -> Vec<Doc> {
+    self
+      .store
+      .read()
+      .unwrap()
+      .subdocs
+      .values()
+      .cloned()
+      .collect()
+  }
+
+  pub fn subdoc_guids(&self) -> Vec<String> {
+    self.store.read().unwrap().subdocs.keys().cloned().collect()
+  }
+
+  /// Whether the content of the doc is requested, a subdoc is not loaded
+  /// unless [DocOptions::auto_load] is set or [Doc::load] is called.
+  pub fn should_load(&self) -> bool {
+    self.store.read().unwrap().should_load
+  }
+
+  /// Request to load the content of the subdoc, the parent doc will notify
+  /// the [Doc::observe_subdocs] observers with the subdoc in
+  /// [SubdocsEvent::loaded].
+  pub fn load(&self) {
+    let parent = {
+      let mut store = self.store.write().unwrap();
+      if store.should_load {
+        return;
+      }
+      store.should_load = true;
+      store.parent.as_ref().and_then(|parent| parent.upgrade())
+    };
+
+    if let Some(parent) = parent {
+      transact(&parent, None, || {
+        if let Some(txn) = &mut parent.write().unwrap().txn {
+          txn.subdocs_loaded.push(self.clone());
+        }
+      });
+    }
+  }
+
+  /// Unload the subdoc by replacing it in the parent doc with a new instance
+  /// not loaded, same as `destroy` of yjs. The parent doc notifies the
+  /// [Doc::observe_subdocs] observers with this instance in
+  /// [SubdocsEvent::removed] and the new one in [SubdocsEvent::added]. The
+  /// subdocs of this doc are destroyed as well.
+  pub fn destroy(&self) {
+    for subdoc in self.subdocs() {
+      subdoc.destroy();
+    }
+
+    let (parent, id) = {
+      let mut store = self.store.write().unwrap();
+      let parent = store.parent.take().and_then(|parent| parent.upgrade());
+      (parent, store.parent_item.take())
+    };
+    let (Some(parent), Some(id)) = (parent, id) else {
+      return;
+    };
+
+    transact(&parent, None, || {
+      let mut store = parent.write().unwrap();
+      let item_ref = store
+        .get_node(id)
+        .map(|node| node.as_item())
+        .unwrap_or_default();
+      let Some(item) = item_ref.get() else {
+        return;
+      };
+      let Some(doc) = item.content.replace_subdoc() else {
+        return;
+      };
+
+      {
+        let mut subdoc = doc.store.write().unwrap();
+        subdoc.parent = Some(Arc::downgrade(&parent));
+        subdoc.parent_item = Some(id);
+      }
+      let deleted = item.deleted();
+      if !deleted {
+        store.subdocs.insert(doc.guid().to_owned(), doc.clone());
+      }
+      if let Some(txn) = &mut store.txn {
+        if !deleted {
+          txn.subdocs_added.push(doc);
+        }
+        txn.subdocs_removed.push(self.clone());
+      }
+    });
+  }
+
+  /// Whether the two docs are the same instance.
+  pub fn ptr_eq(&self, other: &Doc) -> bool {
+    Arc::ptr_eq(&self.store, &other.store)
+  }
+
   pub fn gc(&self) -> JwstCodecResult<()> {
     self.store.write().unwrap().optimize()
   }
+
+  /// Collect the deleted content allowed by the [GcOptions].
+  pub fn gc_with(&self, options: &GcOptions) -> JwstCodecResult<GcStats> {
+    self.store.write().unwrap().optimize_with(options)
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use yrs::{types::ToJson, updates::decoder::Decode, Array, Map, Options, Transact};
+  use yrs::{types::ToJson, updates::decoder::Decode, Array, Map, Options, ReadTxn, Transact};
 
   use super::*;
   use crate::sync::{AtomicU8, Ordering};
@@ -451,6 +994,91 @@ mod tests {
     });
   }
 
+  #[test]
+  #[cfg_attr(any(miri, loom), ignore)]
+  fn test_update_v2_compatibility() {
+    use yrs::{GetString, ReadTxn, Text as _, WriteTxn, XmlElementPrelim, XmlFragment};
+
+    let binary = {
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello world").unwrap();
+      text.remove(5, 6).unwrap();
+      text
+        .format(
+          0,
+          5,
+          [("bold".to_string(), Any::True)].into_iter().collect(),
+        )
+        .unwrap();
+
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map.insert("a".to_string(), 1).unwrap();
+      map.insert("b".to_string(), "b").unwrap();
+      map.insert("a".to_string(), 2).unwrap();
+
+      let mut array = doc.get_or_create_array("array").unwrap();
+      for i in 0..10 {
+        array.push(i).unwrap();
+      }
+      array.remove(2, 3).unwrap();
+
+      let mut fragment = doc.get_or_create_xml_fragment("xml").unwrap();
+      let mut element = doc.create_xml_element("p").unwrap();
+      fragment.push(element.clone()).unwrap();
+      element
+        .insert_attribute("class".to_string(), "title")
+        .unwrap();
+
+      doc.encode_update_v2().unwrap()
+    };
+
+    let ydoc = yrs::Doc::new();
+    let text = ydoc.get_or_insert_text("text");
+    let map = ydoc.get_or_insert_map("map");
+    let array = ydoc.get_or_insert_array("array");
+    let fragment = ydoc.get_or_insert_xml_fragment("xml");
+    {
+      let mut trx = ydoc.transact_mut();
+      trx
+        .apply_update(yrs::Update::decode_v2(&binary).unwrap())
+        .unwrap();
+
+      assert_eq!(text.get_string(&trx), "hello");
+      assert_eq!(
+        map.to_json(&trx),
+        yrs::Any::from_json(r#"{"a":2,"b":"b"}"#).unwrap()
+      );
+      assert_eq!(
+        array.to_json(&trx),
+        yrs::Any::from_json("[0,1,5,6,7,8,9]").unwrap()
+      );
+      assert_eq!(fragment.get_string(&trx), r#"<p class="title"></p>"#);
+
+      // edit on yrs side and sync back
+      text.insert(&mut trx, 5, " yrs");
+      let element = fragment.push_back(&mut trx, XmlElementPrelim::empty("div"));
+      yrs::Xml::insert_attribute(&element, &mut trx, "id", "root");
+      trx.get_or_insert_map("map").remove(&mut trx, "b");
+    }
+
+    let binary = ydoc
+      .transact()
+      .encode_state_as_update_v2(&yrs::StateVector::default());
+
+    let doc = Doc::try_from_binary_v2(binary).unwrap();
+    assert_eq!(
+      doc.get_or_create_text("text").unwrap().to_string(),
+      "hello yrs"
+    );
+    assert_eq!(doc.get_or_create_map("map").unwrap().len(), 1);
+    assert_eq!(doc.get_or_create_array("array").unwrap().len(), 7);
+    assert_eq!(
+      doc.get_or_create_xml_fragment("xml").unwrap().to_string(),
+      r#"<p class="title"></p><div id="root"></div>"#
+    );
+  }
+
   #[test]
   #[cfg_attr(any(miri, loom), ignore)]
   fn test_array_create() {
@@ -519,7 +1147,6 @@ mod tests {
   }
 
   #[test]
-  #[ignore = "inaccurate timing on ci, need for more accurate timing testing"]
   fn test_subscribe() {
     loom_model!({
       let doc = Doc::default();
@@ -542,9 +1169,7 @@ mod tests {
         .insert(0, 42)
         .unwrap();
 
-      // wait observer, cycle once every 100mm
-      std::thread::sleep(std::time::Duration::from_millis(200));
-
+      // subscribers are called once the transaction is committed
       assert_eq!(count.load(Ordering::SeqCst), 2);
     });
   }
@@ -637,6 +1262,563 @@ mod tests {
     });
   }
 
+  #[test]
+  fn test_pending_state() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "a").unwrap();
+      let sv = doc.get_state_vector();
+      text.insert(1, "b").unwrap();
+      let sv2 = doc.get_state_vector();
+      text.insert(2, "c").unwrap();
+
+      let mut doc2 = Doc::with_client(2);
+      doc2
+        .apply_update_from_binary_v1(doc.encode_state_as_update_v1(&sv2).unwrap())
+        .unwrap();
+      assert!(doc2.has_pending());
+      assert_eq!(doc2.pending_state(), StateVector::from([(1, 1)]));
+
+      // the pending update can be carried to another doc
+      let pending = doc2.encode_pending_update_v1().unwrap().unwrap();
+      let doc3 = Doc::try_from_binary_v1(&pending).unwrap();
+      assert_eq!(doc3.pending_state(), StateVector::from([(1, 1)]));
+
+      // the dependencies arrive partially
+      doc2
+        .apply_update_from_binary_v1(doc.encode_state_as_update_v1(&sv).unwrap())
+        .unwrap();
+      assert!(doc2.has_pending());
+      let missing = doc
+        .encode_state_as_update_v1(&doc2.get_state_vector())
+        .unwrap();
+      doc2.apply_update_from_binary_v1(missing).unwrap();
+      assert!(!doc2.has_pending());
+      assert_eq!(doc2.pending_state(), StateVector::default());
+      assert_eq!(doc2.encode_pending_update_v1().unwrap(), None);
+      assert_eq!(doc2.get_or_create_text("text").unwrap().to_string(), "abc");
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_json() {
+    loom_model!({
+      let json = serde_json::json!({
+        "map": {
+          "title": "page",
+          "count": 42,
+          "ratio": 0.5,
+          "done": false,
+          "none": null,
+          "tags": ["a", "b"],
+          "meta": { "nested": [{ "id": 1 }, [1, 2]] },
+        },
+        "array": [1, "a", { "b": true }],
+        "text": "hello world",
+      });
+
+      let doc = Doc::from_json(json.clone()).unwrap();
+      assert_eq!(doc.to_json().unwrap(), json);
+      let meta = doc.get_map("map").unwrap().get("meta").unwrap();
+      assert!(meta.to_map().is_some());
+
+      // the kinds of the decoded root types are guessed by their content
+      let update = doc.encode_update_v1().unwrap();
+      let decoded = Doc::try_from_binary_v1(&update).unwrap();
+      assert_eq!(decoded.to_json().unwrap(), json);
+
+      let ydoc = yrs::Doc::new();
+      let map = ydoc.get_or_insert_map("map");
+      let mut trx = ydoc.transact_mut();
+      trx
+        .apply_update(yrs::Update::decode_v1(&update).unwrap())
+        .unwrap();
+      let config = assert_json_diff::Config::new(assert_json_diff::CompareMode::Strict)
+        .numeric_mode(assert_json_diff::NumericMode::AssumeFloat);
+      assert_json_diff::assert_json_matches!(map.to_json(&trx), json["map"], config);
+
+      // the nested values are inserted in one transaction
+      let updates = Arc::new(RwLock::new(0));
+      let updates_clone = updates.clone();
+      doc.observe_update(move |_, _| *updates_clone.write().unwrap() += 1);
+      let mut map = doc.get_map("map").unwrap();
+      map
+        .insert_json("list".into(), serde_json::json!([{ "k": "v" }]))
+        .unwrap();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      array
+        .push_json(serde_json::json!({ "c": [1, { "d": 2 }] }))
+        .unwrap();
+      assert_eq!(*updates.read().unwrap(), 2);
+      let list = map.get("list").unwrap().to_array().unwrap();
+      assert_eq!(
+        list.get(0).unwrap().to_map().unwrap().get("k"),
+        Some(Value::Any(Any::String("v".into())))
+      );
+
+      assert_eq!(
+        Doc::from_json(serde_json::json!([1])),
+        Err(JwstCodecError::UnexpectedType("object"))
+      );
+      assert_eq!(
+        Doc::from_json(serde_json::json!({ "root": 1 })),
+        Err(JwstCodecError::UnexpectedType("object, array or string"))
+      );
+    });
+  }
+
+  #[test]
+  fn test_gc_with_options() {
+    loom_model!({
+      let doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut map = doc.get_or_create_map("map").unwrap();
+
+      text.insert(0, "hello world").unwrap();
+      map.insert("a".into(), "x").unwrap();
+      map.insert("a".into(), "y").unwrap();
+      let state = doc.get_state_vector();
+
+      text.remove(0, 6).unwrap();
+      text.insert(0, "abc").unwrap();
+      text.remove(0, 3).unwrap();
+
+      let status = doc.store_status();
+      assert_eq!(status.deleted_items, 3);
+      assert_eq!(status.gc_ranges, 0);
+      assert!(status.items_per_client.contains_key(&1));
+      assert!(status.deleted_content_bytes > 0);
+
+      let dry_run = |options: GcOptions| {
+        doc
+          .gc_with(&GcOptions {
+            dry_run: true,
+            ..options
+          })
+          .unwrap()
+      };
+
+      let all = dry_run(GcOptions::default());
+      assert_eq!((all.items, all.clocks), (3, 10));
+      assert!(all.bytes > 0);
+
+      let roots = dry_run(GcOptions {
+        roots: vec!["map".into()],
+        ..Default::default()
+      });
+      assert_eq!((roots.items, roots.clocks), (1, 1));
+
+      let snapshot = dry_run(GcOptions {
+        keep_snapshot: Some(Snapshot::new(state.clone(), DeleteSet::default())),
+        ..Default::default()
+      });
+      assert_eq!((snapshot.items, snapshot.clocks), (1, 3));
+
+      // nothing is collected in dry run
+      assert_eq!(doc.store_status().deleted_items, 3);
+
+      let stats = doc
+        .gc_with(&GcOptions {
+          keep_newer_than: Some(state),
+          ..Default::default()
+        })
+        .unwrap();
+      assert_eq!((stats.items, stats.clocks), (2, 7));
+      assert_eq!(text.to_string(), "world");
+
+      let status = doc.store_status();
+      assert_eq!(status.deleted_items, 1);
+      assert_eq!(status.gc_clocks, 7);
+
+      doc.gc().unwrap();
+      let status = doc.store_status();
+      assert_eq!(status.deleted_items, 0);
+      assert_eq!(status.deleted_content_bytes, 0);
+      assert_eq!(status.gc_clocks, 10);
+    });
+  }
+
+  #[test]
+  fn test_gc_keeps_types_of_kept_items() {
+    loom_model!({
+      let doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map
+        .insert("text".into(), doc.create_text().unwrap())
+        .unwrap();
+      let state = doc.get_state_vector();
+
+      let mut text = map.get("text").and_then(|v| v.to_text()).unwrap();
+      text.insert(0, "hello").unwrap();
+      let snapshot = doc.snapshot();
+      map.remove("text");
+
+      // the text is inserted before the state, but its content is kept
+      let stats = doc
+        .gc_with(&GcOptions {
+          keep_newer_than: Some(state),
+          ..Default::default()
+        })
+        .unwrap();
+      assert_eq!(stats.items, 0);
+      assert_eq!(text.to_string_at(&snapshot), "hello");
+
+      doc.gc().unwrap();
+      assert_eq!(text.to_string_at(&snapshot), "");
+    });
+  }
+
+  #[test]
+  fn test_fork_deep_clone() {
+    loom_model!({
+      let doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map
+        .insert_json(
+          "block".into(),
+          serde_json::json!({ "title": "a", "list": [1, 2] }),
+        )
+        .unwrap();
+      map
+        .insert("text".into(), doc.create_text().unwrap())
+        .unwrap();
+      let mut text = map.get("text").and_then(|v| v.to_text()).unwrap();
+      text.insert(0, "hello world").unwrap();
+      text.remove(5, 6).unwrap();
+      map
+        .insert(
+          "subdoc".into(),
+          DocOptions::new().with_guid("sub".into()).build(),
+        )
+        .unwrap();
+
+      // an update missing its dependency stays pending
+      let remote = Doc::with_client(2);
+      let mut remote_map = remote.get_or_create_map("map").unwrap();
+      remote_map.insert("a".into(), 1).unwrap();
+      let missing = remote.encode_update_v1().unwrap();
+      let sv = remote.get_state_vector();
+      remote_map.insert("b".into(), 2).unwrap();
+      let mut doc = doc;
+      doc
+        .apply_update_from_binary_v1(remote.encode_state_as_update_v1(&sv).unwrap())
+        .unwrap();
+
+      let mut fork = doc.fork();
+      assert_eq!(fork.to_json().unwrap(), doc.to_json().unwrap());
+      assert_eq!(fork.encode_update_v1(), doc.encode_update_v1());
+      assert_eq!(fork.get_state_vector(), doc.get_state_vector());
+      assert!(fork.has_pending());
+      assert_eq!(fork.subdoc_guids(), vec!["sub".to_string()]);
+      let expected = doc.to_json().unwrap();
+      drop((map, text, doc));
+
+      // the fork keeps working once the original is dropped
+      assert_eq!(fork.to_json().unwrap(), expected);
+      let fork_map = fork.get_or_create_map("map").unwrap();
+      let mut fork_text = fork_map.get("text").and_then(|v| v.to_text()).unwrap();
+      fork_text.insert(5, "!").unwrap();
+      assert_eq!(fork_text.to_string(), "hello!");
+      fork.apply_update_from_binary_v1(missing).unwrap();
+      assert!(!fork.has_pending());
+      assert_eq!(
+        fork_map.get("b").and_then(|v| v.to_any()),
+        Some(Any::Integer(2))
+      );
+
+      let mut other = Doc::new();
+      other
+        .apply_update_from_binary_v1(fork.encode_update_v1().unwrap())
+        .unwrap();
+      assert_eq!(other.to_json().unwrap(), fork.to_json().unwrap());
+    });
+  }
+
+  #[test]
+  fn test_fork() {
+    loom_model!({
+      let mut doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello").unwrap();
+      assert!(matches!(
+        doc.diff_since_fork(),
+        Err(JwstCodecError::NotForked)
+      ));
+
+      let mut fork = doc.fork();
+      assert_ne!(fork.client(), doc.client());
+      assert_eq!(fork.guid(), doc.guid());
+      assert_eq!(fork.fork_state(), Some(doc.get_state_vector()));
+
+      // the fork shares nothing with the original
+      let mut fork_text = fork.get_or_create_text("text").unwrap();
+      fork_text.insert(5, " world").unwrap();
+      text.insert(0, "> ").unwrap();
+      assert_eq!(text.to_string(), "> hello");
+      assert_eq!(fork_text.to_string(), "hello world");
+
+      // the changes received by the fork are part of the diff as well
+      let mut other = Doc::with_client(3);
+      other
+        .get_or_create_map("map")
+        .unwrap()
+        .insert("key".into(), 1)
+        .unwrap();
+      fork
+        .apply_update_from_binary_v1(other.encode_update_v1().unwrap())
+        .unwrap();
+
+      let diff = fork.diff_since_fork().unwrap();
+      let update = Update::decode_v1(&diff).unwrap();
+      assert!(!update.structs.contains_key(&doc.client()));
+      doc.apply_update_from_binary_v1(diff).unwrap();
+      assert_eq!(text.to_string(), "> hello world");
+      assert_eq!(
+        doc
+          .get_or_create_map("map")
+          .unwrap()
+          .get("key")
+          .and_then(|v| v.to_any()),
+        Some(Any::Integer(1))
+      );
+
+      // and the original catches the fork up the usual way
+      fork
+        .apply_update_from_binary_v1(
+          doc
+            .encode_state_as_update_v1(&fork.get_state_vector())
+            .unwrap(),
+        )
+        .unwrap();
+      assert_eq!(fork_text.to_string(), "> hello world");
+      other
+        .apply_update_from_binary_v1(fork.encode_update_v1().unwrap())
+        .unwrap();
+      assert_eq!(
+        other.get_or_create_text("text").unwrap().to_string(),
+        "> hello world"
+      );
+    });
+  }
+
+  #[test]
+  fn test_client_id_collision() {
+    loom_model!({
+      let mut doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut users = PermanentUserData::new(&doc).unwrap();
+      users.set_user_mapping(1, "alice").unwrap();
+      let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+      let events_clone = events.clone();
+      doc.observe_client_id(move |event| events_clone.lock().unwrap().push(*event));
+
+      // applying the changes made locally is fine
+      doc
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      assert_eq!(doc.client(), 1);
+
+      // another client picked the same id and made more changes
+      let remote = Doc::with_client(1);
+      remote
+        .get_or_create_text("remote")
+        .unwrap()
+        .insert(0, "a".repeat(100))
+        .unwrap();
+      doc
+        .apply_update_from_binary_v1(remote.encode_update_v1().unwrap())
+        .unwrap();
+
+      let client = doc.client();
+      assert_ne!(client, 1);
+      assert_eq!(
+        events.lock().unwrap().as_slice(),
+        &[ClientIdEvent {
+          old: 1,
+          new: client
+        }]
+      );
+      assert_eq!(
+        users.get_user_by_client_id(client).as_deref(),
+        Some("alice")
+      );
+
+      // the following local changes are made with the new client id
+      let mut text = doc.get_or_create_text("local").unwrap();
+      text.insert(0, "hello").unwrap();
+      assert!(doc.get_state_vector().get(&client) > 0);
+      let mut other = Doc::new();
+      other
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      assert_eq!(
+        other.get_or_create_text("local").unwrap().to_string(),
+        "hello"
+      );
+    });
+  }
+
+  #[test]
+  fn test_subdocs() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut map = doc.get_or_create_map("map").unwrap();
+
+      let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+      let events_clone = events.clone();
+      doc.observe_subdocs(move |event| {
+        let guids = |docs: &[Doc]| docs.iter().map(|d| d.guid().to_owned()).collect::<Vec<_>>();
+        events_clone.lock().unwrap().push((
+          guids(event.added()),
+          guids(event.removed()),
+          guids(event.loaded()),
+        ));
+      });
+
+      let subdoc = DocOptions::new().with_guid("sub".into()).build();
+      map.insert("sub".into(), subdoc.clone()).unwrap();
+      assert!(map.get("sub").unwrap().to_doc().unwrap().ptr_eq(&subdoc));
+      assert_eq!(doc.subdoc_guids(), vec!["sub"]);
+
+      // subdocs from remote are not loaded until requested
+      let mut remote = Doc::with_client(2);
+      let remote_events = Arc::new(std::sync::Mutex::new(Vec::new()));
+      let remote_events_clone = remote_events.clone();
+      remote.observe_subdocs(move |event| {
+        remote_events_clone.lock().unwrap().push((
+          event.added().len(),
+          event.removed().len(),
+          event.loaded().len(),
+        ));
+      });
+      remote
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      let remote_subdoc = remote
+        .get_or_create_map("map")
+        .unwrap()
+        .get("sub")
+        .unwrap()
+        .to_doc()
+        .unwrap();
+      assert_eq!(remote_subdoc.guid(), "sub");
+      assert!(remote.subdocs()[0].ptr_eq(&remote_subdoc));
+      assert!(!remote_subdoc.should_load());
+
+      remote_subdoc.load();
+      assert!(remote_subdoc.should_load());
+
+      map.remove("sub");
+      remote
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      assert!(remote.subdocs().is_empty());
+
+      assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+          (vec!["sub".to_string()], vec![], vec!["sub".to_string()]),
+          (vec![], vec!["sub".to_string()], vec![]),
+        ]
+      );
+      assert_eq!(
+        *remote_events.lock().unwrap(),
+        vec![(1, 0, 0), (0, 0, 1), (0, 1, 0)]
+      );
+    });
+  }
+
+  #[test]
+  fn test_subdoc_destroy() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map
+        .insert(
+          "sub".into(),
+          DocOptions::new().with_guid("sub".into()).build(),
+        )
+        .unwrap();
+      let subdoc = map.get("sub").unwrap().to_doc().unwrap();
+      assert!(subdoc.should_load());
+
+      let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+      let events_clone = events.clone();
+      doc.observe_subdocs(move |event| {
+        events_clone.lock().unwrap().push((
+          event.added().to_vec(),
+          event.removed().to_vec(),
+          event.loaded().len(),
+        ));
+      });
+
+      subdoc.destroy();
+      let new_subdoc = map.get("sub").unwrap().to_doc().unwrap();
+      assert!(!new_subdoc.ptr_eq(&subdoc));
+      assert_eq!(new_subdoc.guid(), "sub");
+      assert!(!new_subdoc.should_load());
+      assert!(doc.subdocs()[0].ptr_eq(&new_subdoc));
+      {
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let (added, removed, loaded) = &events[0];
+        assert!(added.len() == 1 && added[0].ptr_eq(&new_subdoc));
+        assert!(removed.len() == 1 && removed[0].ptr_eq(&subdoc));
+        assert_eq!(*loaded, 0);
+      }
+
+      // the new instance can be loaded again
+      new_subdoc.load();
+      assert_eq!(events.lock().unwrap()[1].2, 1);
+
+      // a destroyed instance is detached from the parent
+      subdoc.destroy();
+      assert_eq!(events.lock().unwrap().len(), 2);
+    });
+  }
+
+  #[test]
+  fn test_subdocs_yrs_compatibility() {
+    let yrs_doc = yrs::Doc::new();
+    let map = yrs_doc.get_or_insert_map("map");
+    let subdoc = yrs::Doc::with_options(Options {
+      guid: "sub".into(),
+      auto_load: true,
+      ..Default::default()
+    });
+    map.insert(&mut yrs_doc.transact_mut(), "sub", subdoc);
+
+    let doc = Doc::try_from_binary_v1(
+      yrs_doc
+        .transact()
+        .encode_state_as_update_v1(&Default::default()),
+    )
+    .unwrap();
+    let subdoc = doc
+      .get_or_create_map("map")
+      .unwrap()
+      .get("sub")
+      .unwrap()
+      .to_doc()
+      .unwrap();
+    assert_eq!(subdoc.guid(), "sub");
+    assert!(subdoc.options().auto_load);
+    assert!(subdoc.should_load());
+
+    let yrs_doc = yrs::Doc::new();
+    yrs_doc
+      .transact_mut()
+      .apply_update(yrs::Update::decode_v1(&doc.encode_update_v1().unwrap()).unwrap())
+      .unwrap();
+    let yrs_subdocs = yrs_doc
+      .transact()
+      .subdoc_guids()
+      .map(|guid| guid.to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(yrs_subdocs, vec!["sub"]);
+  }
+
   #[test]
   #[cfg_attr(any(miri, loom), ignore)]
   fn test_apply_update() {
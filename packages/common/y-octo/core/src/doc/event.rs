@@ -0,0 +1,651 @@
+use super::{
+  transaction::{TransactionState, TypeKey},
+  *,
+};
+
+/// The change of a key in [MapEvent].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryChange {
+  Inserted(Value),
+  /// the old value and the new value
+  Updated(Value, Value),
+  Removed(Value),
+}
+
+/// The change of [Array] in the same format as the text delta.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayDelta {
+  Insert(Vec<Value>),
+  Retain(u64),
+  Delete(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct MapEvent {
+  target: Map,
+  origin: Option<Origin>,
+  keys: HashMap<String, EntryChange>,
+}
+
+impl MapEvent {
+  pub fn target(&self) -> &Map {
+    &self.target
+  }
+
+  pub fn origin(&self) -> Option<&Origin> {
+    self.origin.as_ref()
+  }
+
+  pub fn keys(&self) -> &HashMap<String, EntryChange> {
+    &self.keys
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayEvent {
+  target: Array,
+  origin: Option<Origin>,
+  delta: Vec<ArrayDelta>,
+}
+
+impl ArrayEvent {
+  pub fn target(&self) -> &Array {
+    &self.target
+  }
+
+  pub fn origin(&self) -> Option<&Origin> {
+    self.origin.as_ref()
+  }
+
+  pub fn delta(&self) -> &[ArrayDelta] {
+    &self.delta
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextEvent {
+  target: Text,
+  origin: Option<Origin>,
+  delta: Vec<TextDelta>,
+}
+
+impl TextEvent {
+  pub fn target(&self) -> &Text {
+    &self.target
+  }
+
+  pub fn origin(&self) -> Option<&Origin> {
+    self.origin.as_ref()
+  }
+
+  pub fn delta(&self) -> &[TextDelta] {
+    &self.delta
+  }
+}
+
+/// The local client id is replaced because a remote update has structs with
+/// it that are not made locally, see [Doc::observe_client_id].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientIdEvent {
+  pub old: Client,
+  pub new: Client,
+}
+
+/// The subdocs added, removed and requested to load in a transaction, see
+/// [Doc::observe_subdocs].
+#[derive(Debug, Clone)]
+pub struct SubdocsEvent {
+  origin: Option<Origin>,
+  added: Vec<Doc>,
+  removed: Vec<Doc>,
+  loaded: Vec<Doc>,
+}
+
+impl SubdocsEvent {
+  pub(crate) fn new(txn: &TransactionState) -> Self {
+    Self {
+      origin: txn.origin.clone(),
+      added: txn.subdocs_added.clone(),
+      removed: txn.subdocs_removed.clone(),
+      loaded: txn.subdocs_loaded.clone(),
+    }
+  }
+
+  pub fn origin(&self) -> Option<&Origin> {
+    self.origin.as_ref()
+  }
+
+  pub fn added(&self) -> &[Doc] {
+    &self.added
+  }
+
+  pub fn removed(&self) -> &[Doc] {
+    &self.removed
+  }
+
+  pub fn loaded(&self) -> &[Doc] {
+    &self.loaded
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.loaded.is_empty()
+  }
+}
+
+/// The changes made to a type in a transaction, changes from remote updates
+/// have no origin.
+#[derive(Debug, Clone)]
+pub enum Event {
+  Array(ArrayEvent),
+  Map(MapEvent),
+  Text(TextEvent),
+}
+
+impl Event {
+  pub fn target(&self) -> Value {
+    match self {
+      Event::Array(e) => Value::Array(e.target.clone()),
+      Event::Map(e) => Value::Map(e.target.clone()),
+      Event::Text(e) => Value::Text(e.target.clone()),
+    }
+  }
+
+  pub fn origin(&self) -> Option<&Origin> {
+    match self {
+      Event::Array(e) => e.origin(),
+      Event::Map(e) => e.origin(),
+      Event::Text(e) => e.origin(),
+    }
+  }
+}
+
+/// collect the events of the observed types changed in the transaction
+pub(crate) fn collect_events(
+  store: &DocStore,
+  txn: &TransactionState,
+) -> Vec<(TypeKey, YTypeRef, Event)> {
+  // keys of changed map entries, [None] for the list content
+  let mut changed: Vec<(TypeKey, YTypeRef, HashSet<Option<SmolStr>>)> = Vec::new();
+  // the index of the type in `changed`, [None] for the types without events
+  let mut changed_index: HashMap<TypeKey, Option<usize>> = HashMap::new();
+  let mut mark_changed = |item: &Item| {
+    let Some(Parent::Type(ty)) = &item.parent else {
+      return;
+    };
+    let Some(key) = ty.key() else {
+      return;
+    };
+
+    match changed_index.get(&key) {
+      Some(Some(idx)) => {
+        changed[*idx].2.insert(item.parent_sub.clone());
+      }
+      Some(None) => {}
+      None => {
+        // types created or deleted in the transaction have no events
+        let skip = ty.ty().is_some_and(|ty| {
+          ty.item
+            .get()
+            .is_some_and(|item| item.deleted() || txn.adds(item))
+        });
+        if skip || !store.observers.observes(&key, ty) {
+          changed_index.insert(key, None);
+          return;
+        }
+
+        changed_index.insert(key.clone(), Some(changed.len()));
+        changed.push((
+          key,
+          ty.clone(),
+          HashSet::from_iter([item.parent_sub.clone()]),
+        ));
+      }
+    }
+  };
+
+  for (client, nodes) in store.items.iter() {
+    let before = txn.before_state.get(client);
+    let Some(last) = nodes.back() else {
+      continue;
+    };
+    if last.clock() + last.len() <= before {
+      continue;
+    }
+
+    let start = nodes
+      .front()
+      .map(|n| n.clock())
+      .unwrap_or_default()
+      .max(before);
+    if let Some(idx) = DocStore::get_node_index(nodes, start) {
+      for node in nodes.iter().skip(idx) {
+        if let Some(item) = node.as_item().get() {
+          mark_changed(item);
+        }
+      }
+    }
+  }
+
+  for (client, ranges) in txn.deletions.iter() {
+    let Some(nodes) = store.items.get(client) else {
+      continue;
+    };
+    let before = txn.before_state.get(client);
+
+    for range in ranges {
+      let Some(idx) = DocStore::get_node_index(nodes, range.start) else {
+        continue;
+      };
+      for node in nodes.iter().skip(idx) {
+        if node.clock() >= range.end {
+          break;
+        }
+        if let Some(item) = node.as_item().get() {
+          if item.id.clock < before {
+            mark_changed(item);
+          }
+        }
+      }
+    }
+  }
+
+  changed
+    .into_iter()
+    .filter_map(|(key, ty, keys)| {
+      let origin = txn.origin.clone();
+      let event = match ty.ty()?.kind() {
+        YTypeKind::Map => Event::Map(MapEvent {
+          target: Map::from_unchecked(ty.clone()),
+          origin,
+          keys: map_changes(&ty, keys, txn),
+        }),
+        YTypeKind::Array => Event::Array(ArrayEvent {
+          target: Array::from_unchecked(ty.clone()),
+          origin,
+          delta: array_delta(&ty, txn),
+        }),
+        YTypeKind::Text => Event::Text(TextEvent {
+          target: Text::from_unchecked(ty.clone()),
+          origin,
+          delta: text_delta(&ty, txn),
+        }),
+        _ => return None,
+      };
+
+      Some((key, ty, event))
+    })
+    .collect()
+}
+
+fn map_changes(
+  ty: &YTypeRef,
+  keys: HashSet<Option<SmolStr>>,
+  txn: &TransactionState,
+) -> HashMap<String, EntryChange> {
+  let mut changes = HashMap::new();
+  let Some(ty) = ty.ty() else {
+    return changes;
+  };
+
+  for key in keys.into_iter().flatten() {
+    let Some(item) = ty.map.get(&key).and_then(|item| item.get()) else {
+      continue;
+    };
+
+    let change = if txn.adds(item) {
+      // find the value before the transaction
+      let mut prev = item.left.clone();
+      while let Some(p) = prev.get().filter(|p| txn.adds(p)) {
+        prev = p.left.clone();
+      }
+      let prev = prev.get().filter(|p| txn.deletes(p));
+
+      match (item.deleted(), prev) {
+        (true, Some(prev)) => EntryChange::Removed(Value::from(&prev.content)),
+        (true, None) => continue,
+        (false, Some(prev)) => {
+          EntryChange::Updated(Value::from(&prev.content), Value::from(&item.content))
+        }
+        (false, None) => EntryChange::Inserted(Value::from(&item.content)),
+      }
+    } else if txn.deletes(item) {
+      EntryChange::Removed(Value::from(&item.content))
+    } else {
+      continue;
+    };
+
+    changes.insert(key.to_string(), change);
+  }
+
+  changes
+}
+
+fn array_delta(ty: &YTypeRef, txn: &TransactionState) -> Vec<ArrayDelta> {
+  let mut delta: Vec<ArrayDelta> = Vec::new();
+  let mut push = |op: ArrayDelta| match (delta.last_mut(), op) {
+    (Some(ArrayDelta::Retain(l)), ArrayDelta::Retain(r)) => *l += r,
+    (Some(ArrayDelta::Delete(l)), ArrayDelta::Delete(r)) => *l += r,
+    (Some(ArrayDelta::Insert(l)), ArrayDelta::Insert(r)) => l.extend(r),
+    (_, op) => delta.push(op),
+  };
+
+  let mut item_ref = ty.ty().map(|ty| ty.start.clone()).unwrap_or_default();
+  while let Some(item) = item_ref.get() {
+    if item.countable() {
+      if item.deleted() {
+        if txn.deletes(item) && !txn.adds(item) {
+          push(ArrayDelta::Delete(item.len()));
+        }
+      } else if txn.adds(item) {
+        let values = match &item.content {
+          Content::Any(any) => any.iter().cloned().map(Value::Any).collect(),
+          content => vec![Value::from(content)],
+        };
+        push(ArrayDelta::Insert(values));
+      } else {
+        push(ArrayDelta::Retain(item.len()));
+      }
+    }
+
+    item_ref = item.right.clone();
+  }
+
+  if matches!(delta.last(), Some(ArrayDelta::Retain(_))) {
+    delta.pop();
+  }
+
+  delta
+}
+
+fn text_delta(ty: &YTypeRef, txn: &TransactionState) -> Vec<TextDelta> {
+  let mut delta: Vec<TextDelta> = Vec::new();
+  let mut push = |op: TextDelta| match (delta.last_mut(), op) {
+    (
+      Some(TextDelta::Retain {
+        retain: l,
+        attributes: l_attrs,
+      }),
+      TextDelta::Retain {
+        retain: r,
+        attributes: r_attrs,
+      },
+    ) if *l_attrs == r_attrs => *l += r,
+    (Some(TextDelta::Delete { delete: l }), TextDelta::Delete { delete: r }) => *l += r,
+    (
+      Some(TextDelta::Insert {
+        insert: TextInsert::Text(l),
+        attributes: l_attrs,
+      }),
+      TextDelta::Insert {
+        insert: TextInsert::Text(r),
+        attributes: r_attrs,
+      },
+    ) if *l_attrs == r_attrs => l.push_str(&r),
+    (_, op) => delta.push(op),
+  };
+
+  // attributes active before and after the transaction
+  let mut before = TextAttributes::new();
+  let mut after = TextAttributes::new();
+
+  let mut item_ref = ty.ty().map(|ty| ty.start.clone()).unwrap_or_default();
+  while let Some(item) = item_ref.get() {
+    let adds = txn.adds(item);
+    let deletes = txn.deletes(item);
+
+    match &item.content {
+      Content::Format { key, value } => {
+        if !adds && (!item.deleted() || deletes) {
+          update_attributes(&mut before, key, value);
+        }
+        if !item.deleted() {
+          update_attributes(&mut after, key, value);
+        }
+      }
+      Content::String(_) | Content::Embed(_) => {
+        if item.deleted() {
+          if deletes && !adds {
+            push(TextDelta::Delete { delete: item.len() });
+          }
+        } else if adds {
+          let insert = match &item.content {
+            Content::String(s) => TextInsert::Text(s.to_string()),
+            Content::Embed(embed) => TextInsert::Embed(embed.clone()),
+            _ => unreachable!(),
+          };
+          push(TextDelta::Insert {
+            insert,
+            attributes: (!after.is_empty()).then(|| after.clone()),
+          });
+        } else {
+          // attributes changed by the formats in the transaction
+          let mut changed = TextAttributes::new();
+          for (key, value) in &after {
+            if before.get(key) != Some(value) {
+              changed.insert(key.clone(), value.clone());
+            }
+          }
+          for key in before.keys() {
+            if !after.contains_key(key) {
+              changed.insert(key.clone(), Any::Null);
+            }
+          }
+
+          push(TextDelta::Retain {
+            retain: item.len(),
+            attributes: (!changed.is_empty()).then_some(changed),
+          });
+        }
+      }
+      _ => {}
+    }
+
+    item_ref = item.right.clone();
+  }
+
+  if matches!(
+    delta.last(),
+    Some(TextDelta::Retain {
+      attributes: None,
+      ..
+    })
+  ) {
+    delta.pop();
+  }
+
+  delta
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+  use crate::loom_model;
+
+  fn collect<E: Clone + Send + 'static>() -> (Arc<Mutex<Vec<E>>>, impl Fn(&E) + Send + Sync) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+    (events, move |e: &E| {
+      events_clone.lock().unwrap().push(e.clone())
+    })
+  }
+
+  #[test]
+  fn test_map_event() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map.insert("updated".into(), 1).unwrap();
+      map.insert("removed".into(), 2).unwrap();
+
+      let (events, cb) = collect::<MapEvent>();
+      map.observe(cb);
+
+      doc.transact("test", |_| {
+        map.insert("inserted".into(), 0).unwrap();
+        map.insert("updated".into(), 3).unwrap();
+        map.insert("updated".into(), 4).unwrap();
+        map.remove("removed");
+        map.insert("temp".into(), 5).unwrap();
+        map.remove("temp");
+      });
+
+      let events = events.lock().unwrap();
+      assert_eq!(events.len(), 1);
+      assert_eq!(events[0].origin(), Some(&Origin::from("test")));
+
+      let keys = events[0].keys();
+      assert_eq!(keys.len(), 3);
+      assert_eq!(
+        keys["inserted"],
+        EntryChange::Inserted(Any::Integer(0).into())
+      );
+      assert_eq!(
+        keys["updated"],
+        EntryChange::Updated(Any::Integer(1).into(), Any::Integer(4).into())
+      );
+      assert_eq!(
+        keys["removed"],
+        EntryChange::Removed(Any::Integer(2).into())
+      );
+    });
+  }
+
+  #[test]
+  fn test_array_event() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      array.push("a").unwrap();
+      array.push("b").unwrap();
+      array.push("c").unwrap();
+
+      let (events, cb) = collect::<ArrayEvent>();
+      array.observe(cb);
+
+      doc.transact("test", |_| {
+        array.insert(1, "x").unwrap();
+        array.remove(2, 1).unwrap();
+      });
+      array.push("d").unwrap();
+
+      let events = events.lock().unwrap();
+      assert_eq!(events.len(), 2);
+      assert_eq!(
+        events[0].delta(),
+        &[
+          ArrayDelta::Retain(1),
+          ArrayDelta::Insert(vec!["x".into()]),
+          ArrayDelta::Delete(1),
+        ]
+      );
+      assert_eq!(
+        events[1].delta(),
+        &[ArrayDelta::Retain(3), ArrayDelta::Insert(vec!["d".into()])]
+      );
+      assert_eq!(events[1].origin(), None);
+    });
+  }
+
+  #[test]
+  fn test_text_event() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello world").unwrap();
+
+      let (events, cb) = collect::<TextEvent>();
+      text.observe(cb);
+
+      let bold = TextAttributes::from_iter([("bold".to_string(), Any::True)]);
+      text.format(0, 5, bold.clone()).unwrap();
+      text.insert_with_attributes(5, "!", bold.clone()).unwrap();
+      text.remove(6, 6).unwrap();
+
+      let events = events.lock().unwrap();
+      assert_eq!(
+        events[0].delta(),
+        &[TextDelta::Retain {
+          retain: 5,
+          attributes: Some(bold.clone()),
+        }]
+      );
+      assert_eq!(
+        events[1].delta(),
+        &[
+          TextDelta::Retain {
+            retain: 5,
+            attributes: None,
+          },
+          TextDelta::Insert {
+            insert: TextInsert::Text("!".into()),
+            attributes: Some(bold),
+          },
+        ]
+      );
+      assert_eq!(
+        events[2].delta(),
+        &[
+          TextDelta::Retain {
+            retain: 6,
+            attributes: None,
+          },
+          TextDelta::Delete { delete: 6 },
+        ]
+      );
+      assert_eq!(text.to_string(), "hello!");
+    });
+  }
+
+  #[test]
+  fn test_remote_and_deep_events() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map
+        .insert("list".into(), doc.create_array().unwrap())
+        .unwrap();
+
+      let mut remote = Doc::new();
+      remote
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      let remote_map = remote.get_or_create_map("map").unwrap();
+
+      let (events, cb) = collect::<Vec<Event>>();
+      remote_map.observe_deep(move |events| cb(&events.to_vec()));
+
+      let mut list = map.get("list").unwrap().to_array().unwrap();
+      let sv = remote.get_state_vector();
+      doc.transact("local", |_| {
+        list.push(1).unwrap();
+        map.insert("key".into(), "value").unwrap();
+      });
+      remote
+        .apply_update_from_binary_v1(doc.encode_state_as_update_v1(&sv).unwrap())
+        .unwrap();
+
+      let events = events.lock().unwrap();
+      assert_eq!(events.len(), 1);
+      assert_eq!(events[0].len(), 2);
+      for event in &events[0] {
+        // remote changes have no origin
+        assert_eq!(event.origin(), None);
+        match event {
+          Event::Array(e) => {
+            assert_eq!(
+              e.delta(),
+              &[ArrayDelta::Insert(vec![Any::Integer(1).into()])]
+            )
+          }
+          Event::Map(e) => assert_eq!(
+            e.keys()["key"],
+            EntryChange::Inserted(Any::String("value".into()).into())
+          ),
+          _ => unreachable!(),
+        }
+      }
+    });
+  }
+}
@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// The types an update encoded by [Doc::encode_state_as_update_filtered] is
+/// restricted to.
+///
+/// The items of the types and all the types nested in them are encoded, along
+/// with the items holding the nested types listed. The rest of the clock
+/// ranges are encoded as gc structs, so the update can be applied to an empty
+/// doc without waiting for the items left out.
+///
+/// NOTE: the gc structs are treated as deleted, so the state of a doc built
+/// from the filtered updates must not be synced back to the full doc, or the
+/// content left out is deleted there.
+///
+/// ```
+/// use y_octo::{Doc, UpdateFilter};
+///
+/// let doc = Doc::new();
+/// doc.get_or_create_text("public").unwrap().insert(0, "hello").unwrap();
+/// doc.get_or_create_text("private").unwrap().insert(0, "secret").unwrap();
+///
+/// let filter = UpdateFilter::new().with_root("public");
+/// let update = doc
+///   .encode_state_as_update_filtered(&Default::default(), &filter)
+///   .unwrap();
+/// let doc = Doc::try_from_binary_v1(update).unwrap();
+/// assert_eq!(doc.get_or_create_text("public").unwrap().to_string(), "hello");
+/// assert!(doc.get_or_create_text("private").unwrap().is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UpdateFilter {
+  roots: HashSet<String>,
+  types: HashSet<Id>,
+}
+
+impl UpdateFilter {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Include the root type with the name.
+  pub fn with_root<S: ToString>(mut self, name: S) -> Self {
+    self.roots.insert(name.to_string());
+    self
+  }
+
+  /// Include the nested type held by the item with the id, see `Map::id`.
+  pub fn with_type(mut self, id: Id) -> Self {
+    self.types.insert(id);
+    self
+  }
+
+  fn includes_type(&self, ty: &YType) -> bool {
+    if let Some(name) = &ty.root_name {
+      return self.roots.contains(name);
+    }
+
+    ty.item
+      .get()
+      .is_some_and(|item| self.types.contains(&item.id))
+  }
+
+  /// whether the item belongs to the types included, directly or nested
+  fn includes(&self, item: &Item) -> bool {
+    let mut parent = item.parent.clone();
+    loop {
+      match parent {
+        Some(Parent::Type(ty)) => {
+          let Some(ty) = ty.ty() else {
+            return false;
+          };
+          if self.includes_type(&ty) {
+            return true;
+          }
+          parent = ty.item.get().and_then(|item| item.parent.clone());
+        }
+        Some(Parent::String(name)) => return self.roots.contains(name.as_str()),
+        _ => return false,
+      }
+    }
+  }
+}
+
+impl DocStore {
+  /// the items holding the nested types included and the types they are
+  /// nested in, which are encoded without their neighbours
+  fn filter_anchors(&self, filter: &UpdateFilter) -> HashSet<Id> {
+    let mut anchors = HashSet::new();
+
+    for id in &filter.types {
+      if self.get_state(id.client) <= id.clock {
+        continue;
+      }
+
+      let mut item_ref = self
+        .get_node(*id)
+        .map(|node| node.as_item())
+        .unwrap_or_default();
+      loop {
+        let parent = {
+          let Some(item) = item_ref.get() else {
+            break;
+          };
+          if !anchors.insert(item.id) {
+            break;
+          }
+          match &item.parent {
+            // the item of a root type is none
+            Some(Parent::Type(ty)) => ty.ty().map(|ty| ty.item.clone()).unwrap_or_default(),
+            _ => break,
+          }
+        };
+        item_ref = parent;
+      }
+    }
+
+    anchors
+  }
+
+  pub fn diff_state_vector_filtered(
+    &self,
+    sv: &StateVector,
+    filter: &UpdateFilter,
+  ) -> JwstCodecResult<Update> {
+    let anchors = self.filter_anchors(filter);
+    let mut update = self.diff_state_vector(sv, false)?;
+
+    for nodes in update.structs.values_mut() {
+      let mut filtered: VecDeque<Node> = VecDeque::with_capacity(nodes.len());
+      for node in nodes.drain(..) {
+        let node = match &node {
+          Node::Item(item_ref) => {
+            let item = unsafe { item_ref.get_unchecked() };
+            if filter.includes(item) {
+              node
+            } else if anchors.contains(&item.id) {
+              // the neighbours are left out, place it with the parent only
+              let mut item = item.clone();
+              item.origin_left_id = None;
+              item.origin_right_id = None;
+              item.left = Somr::none();
+              item.right = Somr::none();
+              Node::from(item)
+            } else {
+              Node::new_gc(item.id, item.len())
+            }
+          }
+          _ => node,
+        };
+
+        // merge the adjacent gc structs left by the filtered out items
+        if let (Some(Node::GC(last)), Node::GC(gc)) = (filtered.back_mut(), &node) {
+          last.len += gc.len;
+        } else {
+          filtered.push_back(node);
+        }
+      }
+      *nodes = filtered;
+    }
+
+    let mut delete_set = DeleteSet::default();
+    for (client, nodes) in &self.items {
+      for node in nodes {
+        if let Some(item) = node.as_item().get() {
+          if item.deleted() && (filter.includes(item) || anchors.contains(&item.id)) {
+            delete_set.add(*client, item.id.clock, item.len());
+          }
+        }
+      }
+    }
+    update.delete_set = delete_set;
+
+    Ok(update)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_encode_filtered() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut public = doc.get_or_create_text("public").unwrap();
+      let mut private = doc.get_or_create_text("private").unwrap();
+      let mut blocks = doc.get_or_create_map("blocks").unwrap();
+      public.insert(0, "hello world").unwrap();
+      private.insert(0, "secret").unwrap();
+      for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+        blocks
+          .insert_json(
+            key.into(),
+            serde_json::json!({ "title": key, "children": [i, i + 1] }),
+          )
+          .unwrap();
+      }
+      public.remove(5, 6).unwrap();
+
+      let update = doc
+        .encode_state_as_update_filtered(
+          &StateVector::default(),
+          &UpdateFilter::new().with_root("public"),
+        )
+        .unwrap();
+      let mut remote = Doc::try_from_binary_v1(update).unwrap();
+      assert!(!remote.has_pending());
+      assert_eq!(
+        remote.get_or_create_text("public").unwrap().to_string(),
+        "hello"
+      );
+      assert!(remote.get_or_create_text("private").unwrap().is_empty());
+      assert!(remote.get_or_create_map("blocks").unwrap().is_empty());
+
+      // a nested type comes with the types it is nested in only
+      let mut block = blocks.get("b").and_then(|v| v.to_map()).unwrap();
+      let update = doc
+        .encode_state_as_update_filtered(
+          &StateVector::default(),
+          &UpdateFilter::new().with_type(block.id().unwrap()),
+        )
+        .unwrap();
+      let mut nested = Doc::try_from_binary_v1(update).unwrap();
+      assert!(!nested.has_pending());
+      let nested_blocks = nested.get_or_create_map("blocks").unwrap();
+      assert_eq!(nested_blocks.keys().collect::<Vec<_>>(), vec!["b"]);
+      assert_eq!(
+        nested_blocks
+          .get("b")
+          .and_then(|v| v.to_map())
+          .map(|block| serde_json::to_value(block).unwrap()),
+        Some(serde_json::to_value(&block).unwrap())
+      );
+      assert!(nested.get_or_create_text("public").unwrap().is_empty());
+
+      // the following changes are synced with the state vector
+      block.insert("title".into(), "B").unwrap();
+      private.insert(0, "top ").unwrap();
+      public.insert(5, "!").unwrap();
+      let filter = UpdateFilter::new()
+        .with_root("public")
+        .with_type(block.id().unwrap());
+      for remote in [&mut remote, &mut nested] {
+        let update = doc
+          .encode_state_as_update_filtered(&remote.get_state_vector(), &filter)
+          .unwrap();
+        remote.apply_update_from_binary_v1(update).unwrap();
+        assert!(!remote.has_pending());
+      }
+      assert_eq!(
+        remote.get_or_create_text("public").unwrap().to_string(),
+        "hello!"
+      );
+      assert_eq!(
+        nested
+          .get_or_create_map("blocks")
+          .unwrap()
+          .get("b")
+          .and_then(|v| v.to_map())
+          .map(|block| serde_json::to_value(block).unwrap()),
+        Some(serde_json::to_value(&block).unwrap())
+      );
+      assert!(nested.get_or_create_text("private").unwrap().is_empty());
+
+      // the filtered update is merged into the full doc without conflicts
+      let mut full = Doc::try_from_binary_v1(doc.encode_update_v1().unwrap()).unwrap();
+      full
+        .apply_update_from_binary_v1(
+          doc
+            .encode_state_as_update_filtered(&StateVector::default(), &filter)
+            .unwrap(),
+        )
+        .unwrap();
+      assert_eq!(
+        full.get_or_create_text("private").unwrap().to_string(),
+        "top secret"
+      );
+    });
+  }
+}
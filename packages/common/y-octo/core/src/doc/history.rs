@@ -57,6 +57,36 @@ impl StoreHistory {
     }
   }
 
+  /// resolve the parents of the given nodes only, instead of every item in
+  /// the store
+  pub(crate) fn resolve_nodes<'a>(
+    &self,
+    store: &DocStore,
+    nodes: impl IntoIterator<Item = &'a Node>,
+  ) {
+    let mut parents = self.parents.write().unwrap();
+
+    for node in nodes {
+      let mut cur = node.as_item();
+      while let Some(item) = cur.get().and_then(|item| item.find_node_with_parent_info()) {
+        let parent = match &item.parent {
+          Some(Parent::Type(ty)) => ty.ty().map(|ty| ty.item.clone()),
+          Some(Parent::Id(id)) => store.get_node(*id).map(|node| node.as_item()),
+          _ => None,
+        };
+        let Some(id) = parent.as_ref().and_then(|p| p.get()).map(|p| p.id) else {
+          break;
+        };
+        let parent = parent.unwrap();
+        // the ancestors have been resolved along with another node
+        if parents.insert(id, parent.clone()).is_some() {
+          break;
+        }
+        cur = parent;
+      }
+    }
+  }
+
   pub fn parse_update(&self, update: &Update) -> Vec<History> {
     let store_items = SortedNodes::new(update.structs.iter().collect::<Vec<_>>())
       .filter_map(|n| n.as_item().get().cloned())
@@ -151,6 +181,7 @@ impl StoreHistory {
       histories.push(History {
         id: item.id.to_string(),
         parent: Self::parse_path(item, &parents),
+        path: Self::type_path(item, &parents),
         content: Value::from(&item.content).to_string(),
         action: HistoryAction::Update,
       })
@@ -167,6 +198,7 @@ impl StoreHistory {
       histories.push(History {
         id: item.id.to_string(),
         parent: Self::parse_path(&item, &parents),
+        path: Self::type_path(&item, &parents),
         content: Value::from(&item.content).to_string(),
         action: HistoryAction::Delete,
       })
@@ -204,6 +236,21 @@ impl StoreHistory {
     path
   }
 
+  /// the path of the type the item belongs to
+  fn type_path(item: &Item, parents: &HashMap<Id, Somr<Item>>) -> Option<TypePath> {
+    match item.find_node_with_parent_info()?.parent? {
+      Parent::Type(ty) => ty.path(),
+      Parent::String(name) => Some(TypePath {
+        root: name.to_string(),
+        segments: Vec::new(),
+      }),
+      Parent::Id(id) => match &parents.get(&id)?.get()?.content {
+        Content::Type(ty) => ty.path(),
+        _ => None,
+      },
+    }
+  }
+
   fn get_node_name(item: &Item) -> String {
     if let Some(name) = item.parent_sub.clone() {
       name.to_string()
@@ -252,6 +299,9 @@ pub enum HistoryAction {
 pub struct History {
   pub id: String,
   pub parent: Vec<String>,
+  /// the path of the type the item belongs to, [None] if the type has been
+  /// deleted
+  pub path: Option<TypePath>,
   pub content: String,
   pub action: HistoryAction,
 }
@@ -0,0 +1,368 @@
+use std::{
+  collections::{BTreeMap, VecDeque},
+  ops::Range,
+};
+
+use serde::Serialize;
+
+use super::*;
+
+/// A human-readable dump of an update or a doc, made for debugging corrupt
+/// data offline. Serialize it with `serde_json` to get a JSON dump.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct UpdateInspection {
+  /// the structs of each client, ordered by clock
+  pub structs: BTreeMap<Client, Vec<StructInspection>>,
+  /// the deleted clock ranges of each client
+  pub delete_set: BTreeMap<Client, Vec<Range<Clock>>>,
+  /// the structs and deletions that can not be applied yet
+  pub pending: PendingInspection,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct PendingInspection {
+  pub structs: BTreeMap<Client, Vec<StructInspection>>,
+  pub delete_set: BTreeMap<Client, Vec<Range<Clock>>>,
+  /// the smallest missing clock of each client the pending structs depend on
+  pub missing: BTreeMap<Client, Clock>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StructKind {
+  Item,
+  GC,
+  Skip,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StructInspection {
+  pub id: Id,
+  pub len: u64,
+  pub kind: StructKind,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub origin_left: Option<Id>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub origin_right: Option<Id>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub parent: Option<ParentInspection>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub parent_sub: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub content: Option<ContentInspection>,
+  pub deleted: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParentInspection {
+  /// a root type with the given name
+  Root(String),
+  /// a nested type created by the item with the given id
+  Item(Id),
+  Unknown,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ContentInspection {
+  pub kind: &'static str,
+  pub len: u64,
+}
+
+impl From<&Content> for ContentInspection {
+  fn from(content: &Content) -> Self {
+    let kind = match content {
+      Content::Deleted(_) => "deleted",
+      Content::Json(_) => "json",
+      Content::Binary(_) => "binary",
+      Content::String(_) => "string",
+      Content::Embed(_) => "embed",
+      Content::Format { .. } => "format",
+      Content::Type(_) => "type",
+      Content::Any(_) => "any",
+      Content::Doc { .. } => "doc",
+      Content::Move(_) => "move",
+    };
+
+    Self {
+      kind,
+      len: content.clock_len(),
+    }
+  }
+}
+
+impl From<&Parent> for ParentInspection {
+  fn from(parent: &Parent) -> Self {
+    match parent {
+      Parent::String(name) => Self::Root(name.to_string()),
+      Parent::Id(id) => Self::Item(*id),
+      Parent::Type(ty) => ty
+        .ty()
+        .and_then(|ty| {
+          ty.item
+            .get()
+            .map(|item| Self::Item(item.id))
+            .or_else(|| ty.root_name.clone().map(Self::Root))
+        })
+        .unwrap_or(Self::Unknown),
+    }
+  }
+}
+
+impl StructInspection {
+  fn new(node: &Node, delete_set: &DeleteSet) -> Self {
+    let id = node.id();
+    let mut inspection = Self {
+      id,
+      len: node.len(),
+      kind: match node {
+        Node::Item(_) => StructKind::Item,
+        Node::GC(_) => StructKind::GC,
+        Node::Skip(_) => StructKind::Skip,
+      },
+      origin_left: None,
+      origin_right: None,
+      parent: None,
+      parent_sub: None,
+      content: None,
+      deleted: node.is_gc() || delete_set.contains(&id),
+    };
+
+    if let Some(item) = node.as_item().get() {
+      inspection.origin_left = item.origin_left_id;
+      inspection.origin_right = item.origin_right_id;
+      inspection.parent = item.parent.as_ref().map(ParentInspection::from);
+      inspection.parent_sub = item.parent_sub.as_ref().map(|sub| sub.to_string());
+      inspection.content = Some(ContentInspection::from(&item.content));
+      inspection.deleted |= item.deleted();
+    }
+
+    inspection
+  }
+}
+
+fn inspect_structs(
+  structs: &ClientMap<VecDeque<Node>>,
+  delete_set: &DeleteSet,
+) -> BTreeMap<Client, Vec<StructInspection>> {
+  structs
+    .iter()
+    .map(|(client, nodes)| {
+      (
+        *client,
+        nodes
+          .iter()
+          .map(|node| StructInspection::new(node, delete_set))
+          .collect(),
+      )
+    })
+    .collect()
+}
+
+fn inspect_delete_set(delete_set: &DeleteSet) -> BTreeMap<Client, Vec<Range<Clock>>> {
+  delete_set
+    .iter()
+    .map(|(client, ranges)| (*client, ranges.into_iter().collect()))
+    .collect()
+}
+
+fn inspect_pending(pending: &Update) -> PendingInspection {
+  PendingInspection {
+    structs: inspect_structs(&pending.structs, &pending.delete_set),
+    delete_set: inspect_delete_set(&pending.delete_set),
+    missing: pending
+      .missing_state
+      .iter()
+      .map(|(client, clock)| (*client, *clock))
+      .collect(),
+  }
+}
+
+impl Update {
+  /// Dump the structs and the delete set of the update, along with the ones
+  /// that can not be applied on top of the given state.
+  pub fn inspect(&self, state: &StateVector) -> UpdateInspection {
+    let mut pending = self.clone();
+    let mut state = state.clone();
+    for (node, _) in pending.iter(state.clone()) {
+      state.set_max(node.client(), node.clock() + node.len());
+    }
+    for _ in pending.delete_set_iter(state) {}
+    pending.drain_pending_state();
+
+    UpdateInspection {
+      structs: inspect_structs(&self.structs, &self.delete_set),
+      delete_set: inspect_delete_set(&self.delete_set),
+      pending: inspect_pending(&pending),
+    }
+  }
+}
+
+/// Decode a v1 update and dump it, see [Update::inspect]. The structs that
+/// can not be applied to an empty doc are reported as pending.
+pub fn inspect_update_v1<T: AsRef<[u8]>>(update: T) -> JwstCodecResult<UpdateInspection> {
+  Ok(Update::decode_v1(update)?.inspect(&StateVector::default()))
+}
+
+/// Read the structs of a v1 update one by one, returns the ones read before
+/// the decoding fails along with the error, to locate the corruption of an
+/// update that [inspect_update_v1] can not decode.
+pub fn inspect_structs_v1<T: AsRef<[u8]>>(update: T) -> (Vec<StructInspection>, JwstCodecResult) {
+  let delete_set = DeleteSet::default();
+  let mut structs = Vec::new();
+  let result = Update::read_structs(&mut RawDecoder::new(update.as_ref()), |node| {
+    structs.push(StructInspection::new(&node, &delete_set));
+    Ok(())
+  });
+
+  (structs, result.map(|_| ()))
+}
+
+impl Doc {
+  /// Dump the structs, the delete set and the pending update of the doc.
+  pub fn inspect(&self) -> UpdateInspection {
+    let store = self.store.read().unwrap();
+    let delete_set = store.generate_delete_set();
+
+    UpdateInspection {
+      structs: inspect_structs(&store.items, &delete_set),
+      delete_set: inspect_delete_set(&delete_set),
+      pending: store
+        .pending
+        .as_ref()
+        .map(inspect_pending)
+        .unwrap_or_default(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use yrs::{Map as _, ReadTxn, Text as _, Transact};
+
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_inspect_update() {
+    let doc = yrs::Doc::with_client_id(1);
+    let text = doc.get_or_insert_text("text");
+    text.insert(&mut doc.transact_mut(), 0, "hello");
+    text.remove_range(&mut doc.transact_mut(), 1, 2);
+    let update = doc
+      .transact()
+      .encode_state_as_update_v1(&Default::default());
+
+    let inspection = inspect_update_v1(&update).unwrap();
+    let structs = &inspection.structs[&1];
+    assert_eq!(
+      structs
+        .iter()
+        .map(|s| (s.id.clock, s.len, s.deleted))
+        .collect::<Vec<_>>(),
+      vec![(0, 1, false), (1, 2, true), (3, 2, false)]
+    );
+    assert_eq!(
+      structs[0].parent,
+      Some(ParentInspection::Root("text".into()))
+    );
+    assert_eq!(
+      structs[0].content,
+      Some(ContentInspection {
+        kind: "string",
+        len: 1
+      })
+    );
+    assert_eq!(structs[1].origin_left, Some(Id::new(1, 0)));
+    assert_eq!(inspection.delete_set[&1], vec![1..3]);
+    assert_eq!(inspection.pending, PendingInspection::default());
+
+    let json = serde_json::to_value(&inspection).unwrap();
+    assert_eq!(json["structs"]["1"][0]["kind"], "item");
+    assert_eq!(json["structs"]["1"][0]["parent"]["root"], "text");
+    assert_eq!(json["delete_set"]["1"][0]["start"], 1);
+  }
+
+  #[test]
+  fn test_inspect_corrupt_structs() {
+    let doc = yrs::Doc::with_client_id(1);
+    let text = doc.get_or_insert_text("text");
+    text.insert(&mut doc.transact_mut(), 0, "hello");
+    let title = doc.get_or_insert_text("title");
+    title.insert(&mut doc.transact_mut(), 0, "world");
+    let update = doc
+      .transact()
+      .encode_state_as_update_v1(&Default::default());
+
+    let (structs, result) = inspect_structs_v1(&update);
+    assert!(result.is_ok());
+    assert_eq!(structs.len(), 2);
+
+    // the structs before the truncated one are still read
+    let truncated = &update[..update.len() - 4];
+    assert!(inspect_update_v1(truncated).is_err());
+    let (structs, result) = inspect_structs_v1(truncated);
+    assert!(result.is_err());
+    assert_eq!(structs.len(), 1);
+    assert_eq!(structs[0].id, Id::new(1, 0));
+  }
+
+  #[test]
+  fn test_inspect_pending() {
+    let doc = yrs::Doc::with_client_id(1);
+    let map = doc.get_or_insert_map("map");
+    map.insert(&mut doc.transact_mut(), "a", 1);
+    let sv = doc.transact().state_vector();
+    map.insert(&mut doc.transact_mut(), "b", 2);
+    map.remove(&mut doc.transact_mut(), "a");
+    let update = doc.transact().encode_state_as_update_v1(&sv);
+
+    let inspection = inspect_update_v1(&update).unwrap();
+    assert_eq!(inspection.pending.missing, BTreeMap::from([(1, 0)]));
+    assert_eq!(inspection.pending.structs[&1].len(), 1);
+    assert_eq!(
+      inspection.pending.structs[&1][0].parent_sub.as_deref(),
+      Some("b")
+    );
+    assert_eq!(inspection.pending.delete_set[&1], vec![0..1]);
+
+    loom_model!({
+      let mut doc = Doc::default();
+      doc.apply_update_from_binary_v1(update.clone()).unwrap();
+
+      let inspection = doc.inspect();
+      assert!(inspection.structs.is_empty());
+      assert_eq!(inspection.pending.missing, BTreeMap::from([(1, 0)]));
+      assert_eq!(inspection.pending.structs[&1][0].id, Id::new(1, 1));
+    });
+  }
+
+  #[test]
+  fn test_inspect_doc() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut map = doc.get_or_create_map("map").unwrap();
+      map
+        .insert("text".into(), doc.create_text().unwrap())
+        .unwrap();
+      let mut text = map.get("text").unwrap().to_text().unwrap();
+      text.insert(0, "hi").unwrap();
+      map.insert("a".into(), 1).unwrap();
+      map.remove("a");
+
+      let inspection = doc.inspect();
+      let structs = &inspection.structs[&1];
+      assert_eq!(
+        structs[0].parent,
+        Some(ParentInspection::Root("map".into()))
+      );
+      assert_eq!(structs[0].content.as_ref().unwrap().kind, "type");
+      assert_eq!(
+        structs[1].parent,
+        Some(ParentInspection::Item(Id::new(1, 0)))
+      );
+      assert!(structs.last().unwrap().deleted);
+      assert!(inspection.pending.structs.is_empty());
+    });
+  }
+}
@@ -2,24 +2,50 @@ mod awareness;
 mod codec;
 mod common;
 mod document;
+mod event;
+mod filter;
 mod hasher;
 mod history;
+mod inspector;
+mod permanent_user_data;
+mod persistence;
 mod publisher;
+mod snapshot;
+mod sticky_index;
 mod store;
+mod transaction;
 mod types;
+mod undo;
 mod utils;
+mod verify;
 
 pub use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
-pub use awareness::{Awareness, AwarenessEvent};
+pub use awareness::{Awareness, AwarenessEvent, AWARENESS_OUTDATED_TIMEOUT};
 pub use codec::*;
 pub use common::*;
-pub use document::{Doc, DocOptions};
+pub use document::{Doc, DocOptions, DocStoreStatus, GcOptions, GcStats};
+pub use event::{
+  ArrayDelta, ArrayEvent, ClientIdEvent, EntryChange, Event, MapEvent, SubdocsEvent, TextEvent,
+};
+pub use filter::UpdateFilter;
 pub use hasher::ClientMap;
 pub use history::{History, HistoryOptions, StoreHistory};
+pub use inspector::{
+  inspect_structs_v1, inspect_update_v1, ContentInspection, ParentInspection, PendingInspection,
+  StructInspection, StructKind, UpdateInspection,
+};
+pub use permanent_user_data::{Attribution, PermanentUserData};
+pub use persistence::{DirectoryPersistence, DocPersistence, MemoryPersistence, PersistentDoc};
 use smol_str::SmolStr;
+pub use snapshot::Snapshot;
+pub use sticky_index::{Assoc, IndexScope, RelativePosition, StickyIndex};
 pub(crate) use store::DocStore;
+pub use transaction::{ObserverId, Transaction};
+pub(crate) use transaction::{Observers, TransactionLock, TransactionState};
 pub use types::*;
+pub use undo::{StackItem, UndoManager, UndoManagerOptions};
 pub use utils::*;
+pub use verify::{DroppedStruct, RepairReport, StoreIssue};
 
 use super::*;
 
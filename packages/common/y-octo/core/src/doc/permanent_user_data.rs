@@ -0,0 +1,410 @@
+use super::{store::ChangeRecorder, *};
+
+const DEFAULT_USERS_ROOT: &str = "users";
+
+/// The mapping between client ids and the users they belong to, along with
+/// the deletions made by each user, a.k.a `PermanentUserData` in yjs.
+///
+/// The mapping is stored in a root map of the doc, so it is synced with the
+/// doc content and survives the clients rotating their ids:
+///
+/// ```text
+/// users: {
+///   [user description]: {
+///     ids: [client id, ...],
+///     ds: [encoded delete set, ...],
+///   }
+/// }
+/// ```
+///
+/// The deletions made locally are appended to the user mapped to the client
+/// of the doc as long as the [PermanentUserData] is alive, so as the new
+/// client id once the doc switches to it, see [Doc::observe_client_id].
+///
+/// ```
+/// use y_octo::{DocOptions, PermanentUserData};
+///
+/// let doc = DocOptions::new().with_client_id(1).auto_gc(false).build();
+/// let mut users = PermanentUserData::new(&doc).unwrap();
+/// users.set_user_mapping(1, "alice").unwrap();
+///
+/// let mut text = doc.get_or_create_text("text").unwrap();
+/// text.insert(0, "hello").unwrap();
+/// text.remove(0, 1).unwrap();
+///
+/// let attributions = text.attributions(&users);
+/// assert_eq!(attributions[0].content, "h");
+/// assert_eq!(attributions[0].deleted_by.as_deref(), Some("alice"));
+/// assert_eq!(attributions[1].content, "ello");
+/// assert_eq!(attributions[1].inserted_by.as_deref(), Some("alice"));
+/// ```
+pub struct PermanentUserData {
+  doc: Doc,
+  users: Map,
+  changes: ChangeRecorder,
+  observers: Vec<ObserverId>,
+}
+
+unsafe impl Send for PermanentUserData {}
+unsafe impl Sync for PermanentUserData {}
+
+impl PermanentUserData {
+  pub fn new(doc: &Doc) -> JwstCodecResult<Self> {
+    Self::with_root_name(doc, DEFAULT_USERS_ROOT)
+  }
+
+  /// Store the mapping in the root map with the given name instead of
+  /// `users`.
+  pub fn with_root_name<S: AsRef<str>>(doc: &Doc, name: S) -> JwstCodecResult<Self> {
+    Ok(Self {
+      doc: doc.clone(),
+      users: doc.get_or_create_map(name)?,
+      changes: ChangeRecorder::default(),
+      observers: Vec::new(),
+    })
+  }
+
+  /// Map the client id to the user, if the client is the one of the doc, the
+  /// following local deletions are recorded for the user.
+  pub fn set_user_mapping<S: ToString>(&mut self, client: Client, user: S) -> JwstCodecResult {
+    let user = user.to_string();
+    let entry = self.user_entry(&user)?;
+
+    let mut ids = entry
+      .get("ids")
+      .and_then(|ids| ids.to_array())
+      .ok_or(JwstCodecError::UnexpectedType("Array"))?;
+    if !ids.iter().any(|id| read_client(&id) == Some(client)) {
+      ids.push(Any::from(client))?;
+    }
+
+    if client == self.doc.client() {
+      self.record_deletions(user);
+    }
+
+    Ok(())
+  }
+
+  pub fn get_user_by_client_id(&self, client: Client) -> Option<String> {
+    self.index().clients.remove(&client)
+  }
+
+  pub fn get_user_by_deleted_id(&self, id: Id) -> Option<String> {
+    self.index().deleted_by(&id).map(ToString::to_string)
+  }
+
+  pub(crate) fn index(&self) -> UserIndex {
+    let mut index = UserIndex::default();
+
+    for (user, value) in self.users.iter() {
+      let Some(entry) = value.to_map() else {
+        continue;
+      };
+
+      if let Some(ids) = entry.get("ids").and_then(|ids| ids.to_array()) {
+        for client in ids.iter().filter_map(|id| read_client(&id)) {
+          index.clients.insert(client, user.to_string());
+        }
+      }
+
+      if let Some(ds) = entry.get("ds").and_then(|ds| ds.to_array()) {
+        for value in ds.iter() {
+          let Some(Any::Binary(buffer)) = value.to_any() else {
+            continue;
+          };
+          match DeleteSet::read(&mut RawDecoder::new(&buffer)) {
+            Ok(deletions) => index.deletions.push((user.to_string(), deletions)),
+            Err(e) => warn!("failed to decode deletions of user {user}: {e}"),
+          }
+        }
+      }
+    }
+
+    index
+  }
+
+  /// get the map of the user, creating it along with the nested arrays if
+  /// not exists
+  fn user_entry(&mut self, user: &str) -> JwstCodecResult<Map> {
+    if let Some(entry) = self.users.get(user).and_then(|entry| entry.to_map()) {
+      return Ok(entry);
+    }
+
+    let entry = self.users.0.create_type::<Map>(YTypeKind::Map)?;
+    self.users.insert(user.to_string(), entry)?;
+    let mut entry = self
+      .users
+      .get(user)
+      .and_then(|entry| entry.to_map())
+      .ok_or(JwstCodecError::Unexpected)?;
+
+    for key in ["ids", "ds"] {
+      let array = self.users.0.create_type::<Array>(YTypeKind::Array)?;
+      entry.insert(key.to_string(), array)?;
+    }
+
+    Ok(entry)
+  }
+
+  fn record_deletions(&mut self, user: String) {
+    for observer in self.observers.drain(..) {
+      self.doc.unobserve(observer);
+    }
+
+    // start with a fresh recorder, the deletions made before belong to no one
+    self.changes = ChangeRecorder::default();
+    self
+      .doc
+      .store
+      .write()
+      .unwrap()
+      .add_change_recorder(&self.changes);
+
+    let users = self.users.clone();
+    let client_user = user.clone();
+    self
+      .observers
+      .push(self.doc.observe_client_id(move |event| {
+        let Some(mut ids) = users
+          .get(&client_user)
+          .and_then(|entry| entry.to_map())
+          .and_then(|entry| entry.get("ids"))
+          .and_then(|ids| ids.to_array())
+        else {
+          return;
+        };
+
+        if let Err(e) = ids.push(Any::from(event.new)) {
+          warn!(
+            "failed to map client {} to user {client_user}: {e}",
+            event.new
+          );
+        }
+      }));
+
+    let changes = self.changes.clone();
+    let users = self.users.clone();
+    self.observers.push(self.doc.observe_update(move |_, _| {
+      let mut deletions = DeleteSet::default();
+      for change in changes.lock().unwrap().drain(..) {
+        deletions.merge(&change.deletions);
+      }
+
+      if deletions.is_empty() {
+        return;
+      }
+
+      let Some(mut ds) = users
+        .get(&user)
+        .and_then(|entry| entry.to_map())
+        .and_then(|entry| entry.get("ds"))
+        .and_then(|ds| ds.to_array())
+      else {
+        return;
+      };
+
+      let mut encoder = RawEncoder::default();
+      if let Err(e) = deletions
+        .write(&mut encoder)
+        .and_then(|_| ds.push(Any::Binary(encoder.into_inner())))
+      {
+        warn!("failed to record deletions of user {user}: {e}");
+      }
+    }));
+  }
+}
+
+impl Drop for PermanentUserData {
+  fn drop(&mut self) {
+    for observer in self.observers.drain(..) {
+      self.doc.unobserve(observer);
+    }
+  }
+}
+
+fn read_client(value: &Value) -> Option<Client> {
+  match value.to_any()? {
+    Any::Integer(id) => Some(id as Client),
+    Any::Float32(id) => Some(id.0 as Client),
+    Any::Float64(id) => Some(id.0 as Client),
+    Any::BigInt64(id) => Some(id as Client),
+    _ => None,
+  }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UserIndex {
+  clients: HashMap<Client, String>,
+  deletions: Vec<(String, DeleteSet)>,
+}
+
+impl UserIndex {
+  fn deleted_by(&self, id: &Id) -> Option<&str> {
+    self
+      .deletions
+      .iter()
+      .find(|(_, deletions)| deletions.contains(id))
+      .map(|(user, _)| user.as_str())
+  }
+
+  /// attribute the content of the item, the deletion is attributed by the
+  /// first id of the item
+  pub fn attribute<T>(&self, item: &Item, content: T) -> Attribution<T> {
+    let deleted = item.deleted();
+
+    Attribution {
+      content,
+      inserted_by: self.clients.get(&item.id.client).cloned(),
+      deleted,
+      deleted_by: if deleted {
+        self.deleted_by(&item.id).map(ToString::to_string)
+      } else {
+        None
+      },
+    }
+  }
+}
+
+/// A range of content along with the users inserted and deleted it, see
+/// [PermanentUserData].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribution<T> {
+  pub content: T,
+  pub inserted_by: Option<String>,
+  pub deleted: bool,
+  pub deleted_by: Option<String>,
+}
+
+impl<T> Attribution<T> {
+  /// push the attribution to the list, merging the content into the last one
+  /// if they are made by the same users
+  pub(crate) fn push_to(self, list: &mut Vec<Self>, merge: impl FnOnce(&mut T, T)) {
+    if let Some(last) = list.last_mut() {
+      if last.inserted_by == self.inserted_by
+        && last.deleted == self.deleted
+        && last.deleted_by == self.deleted_by
+      {
+        merge(&mut last.content, self.content);
+        return;
+      }
+    }
+
+    list.push(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loom_model;
+
+  fn sync(from: &Doc, to: &mut Doc) {
+    let update = from
+      .encode_state_as_update_v1(&to.get_state_vector())
+      .unwrap();
+    to.apply_update_from_binary_v1(update).unwrap();
+  }
+
+  #[test]
+  fn test_permanent_user_data() {
+    loom_model!({
+      let mut alice = DocOptions::new().with_client_id(1).auto_gc(false).build();
+      let mut bob = DocOptions::new().with_client_id(2).auto_gc(false).build();
+
+      let mut alice_users = PermanentUserData::new(&alice).unwrap();
+      alice_users.set_user_mapping(1, "alice").unwrap();
+      // the clock the text content starts at, after the user mapping
+      let clock = alice.get_state_vector().get(&1);
+      let mut text = alice.get_or_create_text("text").unwrap();
+      let mut array = alice.get_or_create_array("array").unwrap();
+      text.insert(0, "hello world").unwrap();
+      array.push(1).unwrap();
+      array.push(2).unwrap();
+      sync(&alice, &mut bob);
+
+      let mut bob_users = PermanentUserData::new(&bob).unwrap();
+      bob_users.set_user_mapping(2, "bob").unwrap();
+      let mut bob_text = bob.get_or_create_text("text").unwrap();
+      let mut bob_array = bob.get_or_create_array("array").unwrap();
+      bob_text.remove(5, 6).unwrap();
+      bob_text.insert(5, "!").unwrap();
+      bob_array.remove(0, 1).unwrap();
+      bob_array.push(3).unwrap();
+      sync(&bob, &mut alice);
+
+      assert_eq!(text.to_string(), "hello!");
+      assert_eq!(
+        alice_users.get_user_by_client_id(1).as_deref(),
+        Some("alice")
+      );
+      assert_eq!(alice_users.get_user_by_client_id(2).as_deref(), Some("bob"));
+      assert_eq!(alice_users.get_user_by_client_id(3), None);
+      assert_eq!(
+        alice_users
+          .get_user_by_deleted_id(Id::new(1, clock + 5))
+          .as_deref(),
+        Some("bob")
+      );
+      assert_eq!(alice_users.get_user_by_deleted_id(Id::new(1, clock)), None);
+
+      let attributions = text.attributions(&alice_users);
+      assert_eq!(
+        attributions,
+        vec![
+          Attribution {
+            content: "hello".to_string(),
+            inserted_by: Some("alice".into()),
+            deleted: false,
+            deleted_by: None,
+          },
+          Attribution {
+            content: "!".to_string(),
+            inserted_by: Some("bob".into()),
+            deleted: false,
+            deleted_by: None,
+          },
+          Attribution {
+            content: " world".to_string(),
+            inserted_by: Some("alice".into()),
+            deleted: true,
+            deleted_by: Some("bob".into()),
+          },
+        ]
+      );
+
+      let attributions = array
+        .attributions(&alice_users)
+        .into_iter()
+        .map(|attribution| {
+          (
+            attribution
+              .content
+              .into_iter()
+              .filter_map(|value| value.to_any())
+              .collect::<Vec<_>>(),
+            attribution.inserted_by,
+            attribution.deleted_by,
+          )
+        })
+        .collect::<Vec<_>>();
+      assert_eq!(
+        attributions,
+        vec![
+          (
+            vec![Any::Integer(1)],
+            Some("alice".into()),
+            Some("bob".into())
+          ),
+          (vec![Any::Integer(2)], Some("alice".into()), None),
+          (vec![Any::Integer(3)], Some("bob".into()), None),
+        ]
+      );
+
+      // deletions made before mapping the client are not attributed
+      drop(alice_users);
+      text.remove(0, 1).unwrap();
+      let alice_users = PermanentUserData::new(&alice).unwrap();
+      assert_eq!(alice_users.get_user_by_deleted_id(Id::new(1, clock)), None);
+    });
+  }
+}
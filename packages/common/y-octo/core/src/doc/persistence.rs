@@ -0,0 +1,550 @@
+use std::{
+  collections::hash_map::Entry,
+  fs::{self, OpenOptions},
+  io::ErrorKind,
+  ops::Deref,
+  path::{Path, PathBuf},
+};
+
+use super::*;
+use crate::sync::{Arc, Mutex};
+
+const DEFAULT_COMPACT_THRESHOLD: usize = 100;
+
+/// Where the updates of docs are stored, keyed by the guid of the doc.
+///
+/// A doc is expected to be written by a single writer, e.g. one
+/// [PersistentDoc], as the compaction replaces all the updates stored.
+pub trait DocPersistence: Send + Sync {
+  /// Load the updates stored for the doc, in the order they are appended.
+  fn load(&self, guid: &str) -> JwstCodecResult<Vec<Vec<u8>>>;
+
+  /// Append an update of the doc.
+  fn append(&self, guid: &str, update: &[u8]) -> JwstCodecResult;
+
+  /// Replace all the updates stored for the doc with the merged one.
+  fn compact(&self, guid: &str, merged: &[u8]) -> JwstCodecResult;
+}
+
+impl<P: DocPersistence + ?Sized> DocPersistence for Arc<P> {
+  fn load(&self, guid: &str) -> JwstCodecResult<Vec<Vec<u8>>> {
+    self.deref().load(guid)
+  }
+
+  fn append(&self, guid: &str, update: &[u8]) -> JwstCodecResult {
+    self.deref().append(guid, update)
+  }
+
+  fn compact(&self, guid: &str, merged: &[u8]) -> JwstCodecResult {
+    self.deref().compact(guid, merged)
+  }
+}
+
+/// Keep the updates in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemoryPersistence {
+  docs: Mutex<HashMap<String, Vec<Vec<u8>>>>,
+}
+
+impl MemoryPersistence {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl DocPersistence for MemoryPersistence {
+  fn load(&self, guid: &str) -> JwstCodecResult<Vec<Vec<u8>>> {
+    Ok(
+      self
+        .docs
+        .lock()
+        .unwrap()
+        .get(guid)
+        .cloned()
+        .unwrap_or_default(),
+    )
+  }
+
+  fn append(&self, guid: &str, update: &[u8]) -> JwstCodecResult {
+    self
+      .docs
+      .lock()
+      .unwrap()
+      .entry(guid.to_string())
+      .or_default()
+      .push(update.to_vec());
+    Ok(())
+  }
+
+  fn compact(&self, guid: &str, merged: &[u8]) -> JwstCodecResult {
+    self
+      .docs
+      .lock()
+      .unwrap()
+      .insert(guid.to_string(), vec![merged.to_vec()]);
+    Ok(())
+  }
+}
+
+/// Keep the updates of each doc as files in a directory named by its guid:
+///
+/// ```text
+/// [root]/[guid]/snapshot
+/// [root]/[guid]/update-0000000000000001
+/// [root]/[guid]/update-0000000000000002
+/// [root]/[guid]/writer.lock
+/// ```
+///
+/// The updates are removed after the snapshot is written by compaction, the
+/// ones left by an interrupted compaction are merged again without harm.
+///
+/// The lock file is created the first time a doc is written and removed once
+/// the persistence is dropped, so that other writers fail instead of losing
+/// the updates appended during a compaction. A lock file left by a crashed
+/// process has to be removed before the doc can be written again.
+#[derive(Debug)]
+pub struct DirectoryPersistence {
+  root: PathBuf,
+  /// the next sequence number of the docs locked for writing
+  writing: Mutex<HashMap<String, u64>>,
+}
+
+const WRITER_LOCK: &str = "writer.lock";
+
+impl DirectoryPersistence {
+  pub fn new<P: AsRef<Path>>(root: P) -> Self {
+    Self {
+      root: root.as_ref().to_path_buf(),
+      writing: Mutex::default(),
+    }
+  }
+
+  fn doc_dir(&self, guid: &str) -> JwstCodecResult<PathBuf> {
+    if guid.is_empty() || guid.starts_with('.') || guid.contains(['/', '\\']) {
+      return Err(JwstCodecError::Persistence(format!(
+        "invalid guid for path: {guid}"
+      )));
+    }
+
+    Ok(self.root.join(guid))
+  }
+
+  /// the update files of the doc along with their sequence numbers, in order
+  fn updates(dir: &Path) -> JwstCodecResult<Vec<(u64, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+      Ok(entries) => entries,
+      Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(e.into()),
+    };
+
+    let mut updates = Vec::new();
+    for entry in entries {
+      let path = entry?.path();
+      let seq = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("update-"))
+        .and_then(|seq| seq.parse::<u64>().ok());
+      if let Some(seq) = seq {
+        updates.push((seq, path));
+      }
+    }
+    updates.sort();
+
+    Ok(updates)
+  }
+
+  /// Run `f` with the next sequence number of the doc, which is locked for
+  /// writing by this persistence on the first call.
+  fn write<T>(
+    &self,
+    guid: &str,
+    f: impl FnOnce(&Path, &mut u64) -> JwstCodecResult<T>,
+  ) -> JwstCodecResult<T> {
+    let dir = self.doc_dir(guid)?;
+    let mut writing = self.writing.lock().unwrap();
+
+    let seq = match writing.entry(guid.to_string()) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => {
+        fs::create_dir_all(&dir)?;
+        match OpenOptions::new()
+          .write(true)
+          .create_new(true)
+          .open(dir.join(WRITER_LOCK))
+        {
+          Ok(_) => {}
+          Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(JwstCodecError::Persistence(format!(
+              "doc {guid} is locked by another writer"
+            )));
+          }
+          Err(e) => return Err(e.into()),
+        }
+        let next = Self::updates(&dir)?.last().map_or(1, |(seq, _)| seq + 1);
+        entry.insert(next)
+      }
+    };
+
+    f(&dir, seq)
+  }
+}
+
+impl DocPersistence for DirectoryPersistence {
+  fn load(&self, guid: &str) -> JwstCodecResult<Vec<Vec<u8>>> {
+    let dir = self.doc_dir(guid)?;
+
+    let mut updates = Vec::new();
+    match fs::read(dir.join("snapshot")) {
+      Ok(snapshot) => updates.push(snapshot),
+      Err(e) if e.kind() == ErrorKind::NotFound => {}
+      Err(e) => return Err(e.into()),
+    }
+    for (_, path) in Self::updates(&dir)? {
+      updates.push(fs::read(path)?);
+    }
+
+    Ok(updates)
+  }
+
+  fn append(&self, guid: &str, update: &[u8]) -> JwstCodecResult {
+    self.write(guid, |dir, seq| {
+      fs::write(dir.join(format!("update-{seq:016}")), update)?;
+      *seq += 1;
+
+      Ok(())
+    })
+  }
+
+  fn compact(&self, guid: &str, merged: &[u8]) -> JwstCodecResult {
+    self.write(guid, |dir, _| {
+      let updates = Self::updates(dir)?;
+      // replace the snapshot atomically, the updates are merged into it
+      let tmp = dir.join("snapshot.tmp");
+      fs::write(&tmp, merged)?;
+      fs::rename(&tmp, dir.join("snapshot"))?;
+      for (_, path) in updates {
+        fs::remove_file(path)?;
+      }
+
+      Ok(())
+    })
+  }
+}
+
+impl Drop for DirectoryPersistence {
+  fn drop(&mut self) {
+    for guid in self.writing.lock().unwrap().keys() {
+      if let Err(e) = fs::remove_file(self.root.join(guid).join(WRITER_LOCK)) {
+        warn!("failed to unlock doc {guid}: {e}");
+      }
+    }
+  }
+}
+
+impl From<std::io::Error> for JwstCodecError {
+  fn from(e: std::io::Error) -> Self {
+    JwstCodecError::Persistence(e.to_string())
+  }
+}
+
+/// A [Doc] loaded from a [DocPersistence], whose updates are appended to it
+/// once the transactions are committed.
+///
+/// The stored updates are merged into one once more than the compact
+/// threshold of them are appended. The updates failed to be appended are kept
+/// and appended again on the next commit, call [PersistentDoc::flush] to make
+/// sure all of them are stored.
+///
+/// ```
+/// use y_octo::{DocOptions, MemoryPersistence, PersistentDoc};
+/// use std::sync::Arc;
+///
+/// let persistence = Arc::new(MemoryPersistence::new());
+/// let options = DocOptions::new().with_guid("doc".into());
+///
+/// let doc = PersistentDoc::open(persistence.clone(), options.clone()).unwrap();
+/// doc.get_or_create_text("text").unwrap().insert(0, "hello").unwrap();
+/// doc.flush().unwrap();
+/// drop(doc);
+///
+/// let doc = PersistentDoc::open(persistence, options).unwrap();
+/// assert_eq!(doc.get_or_create_text("text").unwrap().to_string(), "hello");
+/// ```
+pub struct PersistentDoc<P: DocPersistence + 'static> {
+  doc: Doc,
+  persistence: Arc<P>,
+  stored: Arc<Mutex<StoredUpdates>>,
+  observer: ObserverId,
+}
+
+#[derive(Default)]
+struct StoredUpdates {
+  /// the number of updates stored since the last compaction
+  count: usize,
+  /// the updates failed to be appended, in order
+  unsaved: Vec<Vec<u8>>,
+}
+
+impl StoredUpdates {
+  /// append the unsaved updates, stops at the first one failed
+  fn save<P: DocPersistence>(&mut self, persistence: &P, guid: &str) -> JwstCodecResult {
+    let mut saved = 0;
+    let result = self.unsaved.iter().try_for_each(|update| {
+      persistence.append(guid, update)?;
+      saved += 1;
+      Ok(())
+    });
+    self.unsaved.drain(..saved);
+    self.count += saved;
+
+    result
+  }
+}
+
+impl<P: DocPersistence + 'static> PersistentDoc<P> {
+  pub fn open(persistence: P, options: DocOptions) -> JwstCodecResult<Self> {
+    Self::open_with_threshold(persistence, options, DEFAULT_COMPACT_THRESHOLD)
+  }
+
+  /// Open the doc, compacting the stored updates once more than
+  /// `compact_threshold` of them are stored.
+  pub fn open_with_threshold(
+    persistence: P,
+    options: DocOptions,
+    compact_threshold: usize,
+  ) -> JwstCodecResult<Self> {
+    let persistence = Arc::new(persistence);
+    let guid = options.guid.clone();
+
+    let mut doc = Doc::with_options(options);
+    let loaded = persistence.load(&guid)?;
+    if !loaded.is_empty() {
+      doc.apply_update(merge_updates_v1(&loaded)?)?;
+    }
+
+    let stored = Arc::new(Mutex::new(StoredUpdates {
+      count: loaded.len(),
+      unsaved: Vec::new(),
+    }));
+    let observer = {
+      let persistence = persistence.clone();
+      let stored = stored.clone();
+      doc.observe_update(move |update, _| {
+        let mut stored = stored.lock().unwrap();
+        stored.unsaved.push(update.to_vec());
+        if let Err(e) = stored.save(&persistence, &guid) {
+          warn!(
+            "failed to append update of doc {guid}, {} updates unsaved: {e}",
+            stored.unsaved.len()
+          );
+          return;
+        }
+
+        if stored.count > compact_threshold {
+          match Self::merge_stored(&persistence, &guid) {
+            Ok(()) => stored.count = 1,
+            Err(e) => warn!("failed to compact updates of doc {guid}: {e}"),
+          }
+        }
+      })
+    };
+
+    Ok(Self {
+      doc,
+      persistence,
+      stored,
+      observer,
+    })
+  }
+
+  fn merge_stored(persistence: &P, guid: &str) -> JwstCodecResult {
+    let merged = merge_updates_v1(persistence.load(guid)?)?.encode_v1()?;
+    persistence.compact(guid, &merged)
+  }
+
+  /// The number of updates stored since the last compaction, the snapshot
+  /// included.
+  pub fn stored_updates(&self) -> usize {
+    self.stored.lock().unwrap().count
+  }
+
+  /// The number of updates failed to be appended, which are appended again
+  /// on the next commit or [PersistentDoc::flush].
+  pub fn unsaved_updates(&self) -> usize {
+    self.stored.lock().unwrap().unsaved.len()
+  }
+
+  /// Append the updates failed to be appended before, returns the error if
+  /// any of them still fails.
+  pub fn flush(&self) -> JwstCodecResult {
+    self
+      .stored
+      .lock()
+      .unwrap()
+      .save(&self.persistence, self.doc.guid())
+  }
+
+  /// Replace the stored updates with the current state of the doc, the
+  /// unsaved updates included.
+  pub fn compact(&self) -> JwstCodecResult {
+    let mut stored = self.stored.lock().unwrap();
+    self
+      .persistence
+      .compact(self.doc.guid(), &self.doc.encode_update_v1()?)?;
+    stored.count = 1;
+    stored.unsaved.clear();
+
+    Ok(())
+  }
+
+  pub fn doc(&self) -> &Doc {
+    &self.doc
+  }
+}
+
+impl<P: DocPersistence + 'static> Deref for PersistentDoc<P> {
+  type Target = Doc;
+
+  fn deref(&self) -> &Self::Target {
+    &self.doc
+  }
+}
+
+impl<P: DocPersistence + 'static> Drop for PersistentDoc<P> {
+  fn drop(&mut self) {
+    self.doc.unobserve(self.observer);
+    if let Err(e) = self.flush() {
+      warn!(
+        "{} updates of doc {} are lost: {e}",
+        self.unsaved_updates(),
+        self.doc.guid()
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    loom_model,
+    sync::{AtomicBool, Ordering},
+  };
+
+  fn test_persistence<P: DocPersistence + 'static>(persistence: Arc<P>) {
+    let options = DocOptions::new().with_guid("doc".into());
+    let doc = PersistentDoc::open_with_threshold(persistence.clone(), options.clone(), 3).unwrap();
+    let mut text = doc.get_or_create_text("text").unwrap();
+    text.insert(0, "hello").unwrap();
+    text.insert(5, " world").unwrap();
+    assert_eq!(persistence.load("doc").unwrap().len(), 2);
+
+    // compacted once exceeding the threshold
+    text.insert(0, "> ").unwrap();
+    text.remove(0, 2).unwrap();
+    assert_eq!(doc.stored_updates(), 1);
+    assert_eq!(persistence.load("doc").unwrap().len(), 1);
+    text.insert(11, "!").unwrap();
+    assert_eq!(doc.stored_updates(), 2);
+    drop(doc);
+
+    let doc = PersistentDoc::open(persistence.clone(), options.clone()).unwrap();
+    assert_eq!(
+      doc.get_or_create_text("text").unwrap().to_string(),
+      "hello world!"
+    );
+    doc.compact().unwrap();
+    assert_eq!(doc.stored_updates(), 1);
+    assert_eq!(persistence.load("doc").unwrap().len(), 1);
+
+    assert!(persistence.load("other").unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_memory_persistence() {
+    loom_model!({
+      test_persistence(Arc::new(MemoryPersistence::new()));
+    });
+  }
+
+  #[test]
+  fn test_directory_persistence() {
+    loom_model!({
+      let root = std::env::temp_dir().join(format!("y-octo-{}", nanoid::nanoid!()));
+      let persistence = Arc::new(DirectoryPersistence::new(&root));
+      test_persistence(persistence.clone());
+      assert!(persistence.append("../doc", &[]).is_err());
+
+      // the doc is locked by the first writer until it's dropped
+      let other = DirectoryPersistence::new(&root);
+      assert!(other.append("doc", &[]).is_err());
+      assert!(other.append("other", &[]).is_ok());
+      drop(persistence);
+      assert!(other.append("doc", &[]).is_ok());
+      drop(other);
+      assert!(!root.join("doc").join(WRITER_LOCK).exists());
+
+      fs::remove_dir_all(root).unwrap();
+    });
+  }
+
+  #[derive(Default)]
+  struct FlakyPersistence {
+    inner: MemoryPersistence,
+    failing: AtomicBool,
+  }
+
+  impl DocPersistence for FlakyPersistence {
+    fn load(&self, guid: &str) -> JwstCodecResult<Vec<Vec<u8>>> {
+      self.inner.load(guid)
+    }
+
+    fn append(&self, guid: &str, update: &[u8]) -> JwstCodecResult {
+      if self.failing.load(Ordering::SeqCst) {
+        return Err(JwstCodecError::Persistence("unavailable".into()));
+      }
+      self.inner.append(guid, update)
+    }
+
+    fn compact(&self, guid: &str, merged: &[u8]) -> JwstCodecResult {
+      self.inner.compact(guid, merged)
+    }
+  }
+
+  #[test]
+  fn test_unsaved_updates() {
+    loom_model!({
+      let persistence = Arc::new(FlakyPersistence::default());
+      let options = DocOptions::new().with_guid("doc".into());
+      let doc = PersistentDoc::open(persistence.clone(), options.clone()).unwrap();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello").unwrap();
+
+      persistence.failing.store(true, Ordering::SeqCst);
+      text.insert(5, " world").unwrap();
+      text.insert(11, "!").unwrap();
+      assert_eq!(doc.unsaved_updates(), 2);
+      assert!(doc.flush().is_err());
+
+      // appended again in order once the persistence recovers
+      persistence.failing.store(false, Ordering::SeqCst);
+      doc.flush().unwrap();
+      assert_eq!(doc.unsaved_updates(), 0);
+      assert_eq!(doc.stored_updates(), 3);
+
+      persistence.failing.store(true, Ordering::SeqCst);
+      text.insert(0, "> ").unwrap();
+      persistence.failing.store(false, Ordering::SeqCst);
+      text.insert(14, "!").unwrap();
+      assert_eq!(doc.unsaved_updates(), 0);
+      drop(doc);
+
+      let doc = PersistentDoc::open(persistence.clone(), options).unwrap();
+      assert_eq!(
+        doc.get_or_create_text("text").unwrap().to_string(),
+        "> hello world!!"
+      );
+    });
+  }
+}
@@ -1,141 +1,185 @@
 use std::{
-  thread::{current, sleep, spawn},
-  time::Duration,
+  collections::{BTreeMap, VecDeque},
+  sync::{
+    mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    OnceLock,
+  },
+  time::{Duration, Instant},
 };
 
-use log::{debug, trace};
+use log::{debug, warn};
 
-use super::{history::StoreHistory, store::StoreRef, *};
-use crate::sync::{Arc, AtomicBool, Mutex, Ordering, RwLock};
+use super::{
+  history::StoreHistory,
+  store::{StoreRef, WeakStoreRef},
+  *,
+};
+use crate::sync::{Arc, Mutex, RwLock};
 
 pub type DocSubscriber = Box<dyn Fn(&[u8], &[History]) + Sync + Send + 'static>;
 
-const OBSERVE_INTERVAL: u64 = 100;
+#[derive(Default)]
+struct PublisherState {
+  observing: bool,
+  /// the update observer registered in the store, only exists when there are
+  /// subscribers to avoid encoding updates nobody listens to
+  observer: Option<ObserverId>,
+  debounce: Option<Duration>,
+  /// the updates waiting for the debounce interval to elapse
+  buffer: Vec<Vec<u8>>,
+  buffered_at: Option<Instant>,
+  /// bumped whenever the buffer is taken, so the timer of a burst that has
+  /// already been published does nothing
+  burst: u64,
+}
 
+impl PublisherState {
+  fn take_buffer(&mut self) -> Option<Vec<u8>> {
+    self.buffered_at = None;
+    self.burst += 1;
+    match self.buffer.len() {
+      0 => None,
+      1 => self.buffer.pop(),
+      _ => match merge_updates_v1(self.buffer.drain(..)).and_then(|update| update.encode_v1()) {
+        Ok(update) => Some(update),
+        Err(e) => {
+          warn!("Failed to merge updates: {}", e);
+          None
+        }
+      },
+    }
+  }
+}
+
+/// Publish the update of each committed transaction to the subscribers,
+/// along with the [History] of the changes in it.
+///
+/// The subscribers are called synchronously once the transaction is
+/// committed, or with the updates merged if debounced, see
+/// [DocPublisher::set_debounce].
 pub struct DocPublisher {
   store: StoreRef,
-  history: StoreHistory,
   subscribers: Arc<RwLock<Vec<DocSubscriber>>>,
-  observer: Arc<Mutex<Option<std::thread::JoinHandle<()>>>>,
-  observing: Arc<AtomicBool>,
+  state: Arc<Mutex<PublisherState>>,
 }
 
 impl DocPublisher {
   pub(crate) fn new(store: StoreRef) -> Self {
-    let subscribers = Arc::new(RwLock::new(Vec::<DocSubscriber>::new()));
-    let history = StoreHistory::new(&store);
-    history.resolve();
-
     let publisher = Self {
       store,
-      history,
-      subscribers,
-      observer: Arc::default(),
-      observing: Arc::new(AtomicBool::new(false)),
+      subscribers: Arc::default(),
+      state: Arc::default(),
     };
 
-    if cfg!(not(any(feature = "bench", fuzzing, loom, miri))) {
-      publisher.start();
-    }
+    publisher.start();
 
     publisher
   }
 
   pub fn start(&self) {
-    let mut observer = self.observer.lock().unwrap();
-    let observing = self.observing.clone();
-    let store = self.store.clone();
-    let history = self.history.clone();
-    if observer.is_none() {
-      let thread_subscribers = self.subscribers.clone();
-      observing.store(true, Ordering::Release);
-      debug!("start observing");
-      let thread = spawn(move || {
-        let mut last_update = store.read().unwrap().get_state_vector();
-        let mut last_deletes = store.read().unwrap().delete_set.clone();
-        loop {
-          sleep(Duration::from_millis(OBSERVE_INTERVAL));
-          if !observing.load(Ordering::Acquire) {
-            debug!("stop observing");
-            break;
-          }
+    let mut state = self.state.lock().unwrap();
+    if state.observing {
+      debug!("already observing");
+      return;
+    }
 
-          let subscribers = thread_subscribers.read().unwrap();
-          if subscribers.is_empty() {
-            continue;
-          }
+    debug!("start observing");
+    state.observing = true;
+    self.observe(&mut state);
+  }
+
+  /// Stop publishing, the updates buffered by debounce are published at once.
+  pub fn stop(&self) {
+    let mut state = self.state.lock().unwrap();
+    if !state.observing {
+      return;
+    }
+
+    debug!("stop observing");
+    state.observing = false;
+    self.unobserve(&mut state);
+    drop(state);
+
+    self.flush();
+  }
+
+  /// Merge the updates committed within the interval and publish them once
+  /// the interval elapsed since the first of them, or when
+  /// [DocPublisher::flush] is called. Pass `None` to publish every update at
+  /// once. The buffered updates are also published when the publisher is
+  /// stopped or dropped.
+  ///
+  /// The intervals of all the publishers are timed by a single shared thread,
+  /// which is not used under loom and miri, call [DocPublisher::flush] to
+  /// publish the buffered updates there.
+  pub fn set_debounce(&self, interval: Option<Duration>) {
+    self.state.lock().unwrap().debounce = interval;
+    if interval.is_none() {
+      self.flush();
+    }
+  }
+
+  /// Publish the updates buffered by debounce immediately.
+  pub fn flush(&self) {
+    let update = self.state.lock().unwrap().take_buffer();
+    if let Some(update) = update {
+      publish(&self.store, &self.subscribers, update);
+    }
+  }
+
+  fn observe(&self, state: &mut PublisherState) {
+    if !state.observing || state.observer.is_some() || self.count() == 0 {
+      return;
+    }
 
-          let store = store.read().unwrap();
-
-          let update = store.get_state_vector();
-          let deletes = store.delete_set.clone();
-          if update != last_update || deletes != last_deletes {
-            trace!(
-              "update: {:?}, last_update: {:?}, {:?}",
-              update,
-              last_update,
-              current().id(),
-            );
-            trace!(
-              "deletes: {:?}, last_deletes: {:?}, {:?}",
-              deletes,
-              last_deletes,
-              current().id(),
-            );
-
-            history.resolve_with_store(&store);
-            let (binary, history) = match store.diff_state_vector(&last_update, false) {
-              Ok(update) => {
-                drop(store);
-
-                let history = history
-                  .parse_update(&update)
-                  .into_iter()
-                  .chain(history.parse_delete_sets(&last_deletes, &deletes))
-                  .collect::<Vec<_>>();
-
-                let mut encoder = RawEncoder::default();
-                if let Err(e) = update.write(&mut encoder) {
-                  warn!("Failed to encode document: {}", e);
-                  continue;
-                }
-                (encoder.into_inner(), history)
+    let weak_store = Arc::downgrade(&self.store);
+    let subscribers = self.subscribers.clone();
+    let publisher_state = self.state.clone();
+    let id = self
+      .store
+      .write()
+      .unwrap()
+      .observers
+      .add_update(Arc::new(move |update, _| {
+        let Some(store) = weak_store.upgrade() else {
+          return;
+        };
+
+        let update = {
+          let mut state = publisher_state.lock().unwrap();
+          match state.debounce {
+            Some(interval) => {
+              if state.buffer.is_empty() {
+                schedule_flush(
+                  weak_store.clone(),
+                  subscribers.clone(),
+                  publisher_state.clone(),
+                  state.burst,
+                  interval,
+                );
               }
-              Err(e) => {
-                warn!("Failed to diff document: {}", e);
-                continue;
+              state.buffer.push(update.to_vec());
+              let buffered_at = *state.buffered_at.get_or_insert_with(Instant::now);
+              if buffered_at.elapsed() < interval {
+                return;
+              }
+              match state.take_buffer() {
+                Some(update) => update,
+                None => return,
               }
-            };
-
-            last_update = update;
-            last_deletes = deletes;
-
-            for cb in subscribers.iter() {
-              use std::panic::{catch_unwind, AssertUnwindSafe};
-              // catch panic if callback throw
-              catch_unwind(AssertUnwindSafe(|| {
-                cb(&binary, &history);
-              }))
-              .unwrap_or_else(|e| {
-                warn!("Failed to call subscriber: {:?}", e);
-              });
             }
-          } else {
-            drop(store);
+            None => update.to_vec(),
           }
-        }
-      });
-      observer.replace(thread);
-    } else {
-      debug!("already observing");
-    }
+        };
+
+        publish(&store, &subscribers, update);
+      }));
+    state.observer.replace(id);
   }
 
-  pub fn stop(&self) {
-    let mut observer = self.observer.lock().unwrap();
-    if let Some(observer) = observer.take() {
-      self.observing.store(false, Ordering::Release);
-      observer.join().unwrap();
+  fn unobserve(&self, state: &mut PublisherState) {
+    if let Some(id) = state.observer.take() {
+      self.store.write().unwrap().observers.remove(id);
     }
   }
 
@@ -145,10 +189,167 @@ impl DocPublisher {
 
   pub(crate) fn subscribe(&self, subscriber: impl Fn(&[u8], &[History]) + Send + Sync + 'static) {
     self.subscribers.write().unwrap().push(Box::new(subscriber));
+    self.observe(&mut self.state.lock().unwrap());
   }
 
   pub(crate) fn unsubscribe_all(&self) {
     self.subscribers.write().unwrap().clear();
+    let mut state = self.state.lock().unwrap();
+    self.unobserve(&mut state);
+    state.take_buffer();
+  }
+}
+
+/// publish the buffered updates once the debounce interval elapsed, even if no
+/// more transaction is committed in the meantime
+fn schedule_flush(
+  store: WeakStoreRef,
+  subscribers: Arc<RwLock<Vec<DocSubscriber>>>,
+  state: Arc<Mutex<PublisherState>>,
+  burst: u64,
+  interval: Duration,
+) {
+  if cfg!(any(loom, miri)) {
+    return;
+  }
+
+  schedule(
+    Instant::now() + interval,
+    Box::new(move || {
+      let update = {
+        let mut state = state.lock().unwrap();
+        if state.burst != burst {
+          return;
+        }
+        state.take_buffer()
+      };
+
+      if let (Some(store), Some(update)) = (store.upgrade(), update) {
+        publish(&store, &subscribers, update);
+      }
+    }),
+  );
+}
+
+type TimerTask = Box<dyn FnOnce() + Send>;
+
+/// run the task once the deadline is reached, the tasks of all the publishers
+/// are run by a single timer thread
+fn schedule(deadline: Instant, task: TimerTask) {
+  static TIMER: OnceLock<Option<Sender<(Instant, TimerTask)>>> = OnceLock::new();
+
+  let timer = TIMER.get_or_init(|| {
+    let (sender, receiver) = channel::<(Instant, TimerTask)>();
+    std::thread::Builder::new()
+      .name("y-octo-publisher-timer".into())
+      .spawn(move || run_timer(receiver))
+      .inspect_err(|e| warn!("Failed to spawn publisher timer: {}", e))
+      .ok()
+      .map(|_| sender)
+  });
+
+  // without the timer, the buffer is published by the next commit
+  if let Some(timer) = timer {
+    timer.send((deadline, task)).ok();
+  }
+}
+
+fn run_timer(receiver: Receiver<(Instant, TimerTask)>) {
+  // ordered by the deadline, then the order of scheduling
+  let mut tasks = BTreeMap::<(Instant, u64), TimerTask>::new();
+  let mut seq = 0;
+
+  loop {
+    let received = match tasks.first_key_value() {
+      Some(((deadline, _), _)) => {
+        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+      }
+      None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match received {
+      Ok((deadline, task)) => {
+        seq += 1;
+        tasks.insert((deadline, seq), task);
+      }
+      Err(RecvTimeoutError::Timeout) => {}
+      Err(RecvTimeoutError::Disconnected) => return,
+    }
+
+    let now = Instant::now();
+    while let Some(entry) = tasks.first_entry() {
+      if entry.key().0 > now {
+        break;
+      }
+      entry.remove()();
+    }
+  }
+}
+
+/// call the subscribers with the update and the history of the integrated
+/// items in it
+fn publish(store: &StoreRef, subscribers: &RwLock<Vec<DocSubscriber>>, binary: Vec<u8>) {
+  let subscribers = subscribers.read().unwrap();
+  if subscribers.is_empty() {
+    return;
+  }
+
+  let update = match Update::decode_v1(&binary) {
+    Ok(update) => update,
+    Err(e) => {
+      warn!("Failed to decode update: {}", e);
+      return;
+    }
+  };
+
+  let history = {
+    let history = StoreHistory::new(store);
+    let store = store.read().unwrap();
+
+    // the decoded items are not linked, read the integrated ones instead
+    let mut structs = ClientMap::<VecDeque<Node>>::new();
+    for (client, nodes) in update.structs.iter() {
+      let integrated = structs.entry(*client).or_default();
+      for node in nodes {
+        if let Some(node) = store.get_node(node.id()) {
+          if integrated.back().map(|n| n.id()) != Some(node.id()) {
+            integrated.push_back(node);
+          }
+        }
+      }
+    }
+    let deleted = update
+      .delete_set
+      .iter()
+      .filter_map(|(client, range)| {
+        store
+          .items
+          .get(client)
+          .map(|items| items.iter().filter(move |i| range.contains(i.clock())))
+      })
+      .flatten();
+    // only the items in the update need their parents resolved
+    history.resolve_nodes(&store, structs.values().flatten().chain(deleted));
+    drop(store);
+
+    history
+      .parse_update(&Update {
+        structs,
+        ..Update::default()
+      })
+      .into_iter()
+      .chain(history.parse_delete_sets(&ClientMap::default(), &update.delete_set))
+      .collect::<Vec<_>>()
+  };
+
+  for cb in subscribers.iter() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    // catch panic if callback throw
+    catch_unwind(AssertUnwindSafe(|| {
+      cb(&binary, &history);
+    }))
+    .unwrap_or_else(|e| {
+      warn!("Failed to call subscriber: {:?}", e);
+    });
   }
 }
 
@@ -168,7 +369,7 @@ impl Drop for DocPublisher {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::sync::AtomicUsize;
+  use crate::sync::{AtomicUsize, Ordering};
 
   #[test]
   fn test_parse_update_history() {
@@ -186,59 +387,136 @@ mod tests {
           vec!["(1, 4)", "array.1", "val2"],
           vec!["(1, 5)", "array.2", "val3"],
         ],
+        vec![vec!["(1, 1)", "test.key2", "val2"]],
       ];
 
       let cycle = Arc::new(AtomicUsize::new(0));
+      let cycle_clone = cycle.clone();
 
-      // update: 24
-      // history change by (1, 0) at test.key1: val1
-      // update: 43
-      // history change by (1, 1) at test.key2: val2
-      // history change by (1, 2) at test.key3: val3
-      // update: 40
-      // history change by (1, 3) at array.0: val1
-      // history change by (1, 4) at array.1: val2
-      // history change by (1, 5) at array.2: val3
       doc.subscribe(move |u, history| {
         println!("update: {}", u.len());
-        let cycle = cycle.fetch_add(1, Ordering::SeqCst);
+        let cycle = cycle_clone.fetch_add(1, Ordering::SeqCst);
 
-        let ret = ret[cycle].clone();
-        for (i, h) in history.iter().enumerate() {
+        let ret = &ret[cycle];
+        assert_eq!(history.len(), ret.len());
+        for (h, ret) in history.iter().zip(ret) {
           println!(
             "history change by {} at {}: {}",
             h.id,
             h.parent.join("."),
             h.content
           );
-          // lost first update by unknown reason in asan test, skip it if asan enabled
-          if option_env!("ASAN_OPTIONS").is_none() {
-            let ret = &ret[i];
-            assert_eq!(h.id, ret[0]);
-            assert_eq!(h.parent.join("."), ret[1]);
-            assert_eq!(h.content, ret[2]);
-          }
+          assert_eq!(h.id, ret[0]);
+          assert_eq!(h.parent.join("."), ret[1]);
+          assert_eq!(h.content, ret[2]);
         }
       });
-      sleep(Duration::from_millis(500));
 
       let mut map = doc.get_or_create_map("test").unwrap();
       map.insert("key1".to_string(), "val1").unwrap();
+      assert_eq!(cycle.load(Ordering::SeqCst), 1);
 
-      sleep(Duration::from_millis(500));
-
-      map.insert("key2".to_string(), "val2").unwrap();
-      map.insert("key3".to_string(), "val3").unwrap();
-      sleep(Duration::from_millis(500));
+      doc.transact("batch", |_| {
+        map.insert("key2".to_string(), "val2").unwrap();
+        map.insert("key3".to_string(), "val3").unwrap();
+      });
+      assert_eq!(cycle.load(Ordering::SeqCst), 2);
 
       let mut array = doc.get_or_create_array("array").unwrap();
-      array.push("val1").unwrap();
-      array.push("val2").unwrap();
-      array.push("val3").unwrap();
+      doc.transact("batch", |_| {
+        array.push("val1").unwrap();
+        array.push("val2").unwrap();
+        array.push("val3").unwrap();
+      });
+      assert_eq!(cycle.load(Ordering::SeqCst), 3);
 
-      sleep(Duration::from_millis(500));
+      map.remove("key2");
+      assert_eq!(cycle.load(Ordering::SeqCst), 4);
 
       doc.publisher.stop();
+      map.insert("key4".to_string(), "val4").unwrap();
+      assert_eq!(cycle.load(Ordering::SeqCst), 4);
+    });
+  }
+
+  #[test]
+  fn test_debounce() {
+    loom_model!({
+      let doc = Doc::default();
+      let updates = Arc::new(Mutex::new(Vec::new()));
+      let updates_clone = updates.clone();
+      doc.subscribe(move |u, _| {
+        updates_clone.lock().unwrap().push(u.to_vec());
+      });
+
+      doc.publisher.set_debounce(Some(Duration::from_secs(3600)));
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello").unwrap();
+      text.insert(5, " world").unwrap();
+      assert!(updates.lock().unwrap().is_empty());
+
+      // the buffered updates are merged into one
+      doc.publisher.flush();
+      assert_eq!(updates.lock().unwrap().len(), 1);
+
+      doc.publisher.set_debounce(Some(Duration::ZERO));
+      text.insert(0, "!").unwrap();
+      assert_eq!(updates.lock().unwrap().len(), 2);
+
+      let mut new_doc = Doc::default();
+      for update in updates.lock().unwrap().iter() {
+        new_doc.apply_update_from_binary_v1(update.clone()).unwrap();
+      }
+      assert_eq!(
+        new_doc.get_or_create_text("text").unwrap().to_string(),
+        "!hello world"
+      );
+    });
+  }
+
+  #[test]
+  #[cfg(not(loom))]
+  #[cfg_attr(miri, ignore)]
+  fn test_debounce_trailing() {
+    let doc = Doc::default();
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let updates_clone = updates.clone();
+    doc.subscribe(move |u, _| {
+      updates_clone.lock().unwrap().push(u.to_vec());
+    });
+
+    // the tail of a burst is published without any later commit
+    doc.publisher.set_debounce(Some(Duration::from_millis(10)));
+    let mut text = doc.get_or_create_text("text").unwrap();
+    text.insert(0, "hello").unwrap();
+    text.insert(5, " world").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while updates.lock().unwrap().is_empty() && Instant::now() < deadline {
+      std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(updates.lock().unwrap().len(), 1);
+
+    // dropping the doc publishes the buffered updates
+    doc.publisher.set_debounce(Some(Duration::from_secs(3600)));
+    text.insert(0, "!").unwrap();
+    assert_eq!(updates.lock().unwrap().len(), 1);
+    drop(text);
+    drop(doc);
+    assert_eq!(updates.lock().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn test_no_observer_without_subscribers() {
+    loom_model!({
+      let doc = Doc::default();
+      assert!(doc.store.read().unwrap().observers.is_empty());
+
+      doc.subscribe(|_, _| {});
+      assert!(!doc.store.read().unwrap().observers.is_empty());
+
+      doc.unsubscribe_all();
+      assert!(doc.store.read().unwrap().observers.is_empty());
     });
   }
 }
@@ -0,0 +1,348 @@
+use std::{collections::VecDeque, ops::Range};
+
+use super::*;
+
+/// The state of a [Doc] at a point in time, made of the state vector and the
+/// delete set of the doc, a.k.a `Snapshot` in yjs.
+///
+/// The content of the doc at the snapshot can only be read back if the doc
+/// keeps the deleted content, which requires gc disabled by
+/// [DocOptions::auto_gc].
+///
+/// ```
+/// use y_octo::DocOptions;
+///
+/// let doc = DocOptions::new().auto_gc(false).build();
+/// let mut text = doc.get_or_create_text("text").unwrap();
+/// text.insert(0, "hello").unwrap();
+///
+/// let snapshot = doc.snapshot();
+/// text.insert(5, " world").unwrap();
+///
+/// assert_eq!(text.to_string_at(&snapshot), "hello");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Snapshot {
+  pub state_vector: StateVector,
+  pub delete_set: DeleteSet,
+}
+
+impl Snapshot {
+  pub fn new(state_vector: StateVector, delete_set: DeleteSet) -> Self {
+    Self {
+      state_vector,
+      delete_set,
+    }
+  }
+
+  pub fn decode_v1<T: AsRef<[u8]>>(buffer: T) -> JwstCodecResult<Self> {
+    Self::read(&mut RawDecoder::new(buffer.as_ref()))
+  }
+
+  pub fn encode_v1(&self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoder::default();
+    self.write(&mut encoder)?;
+    Ok(encoder.into_inner())
+  }
+
+  /// whether the struct with given id exists and not deleted in the snapshot
+  pub fn contains(&self, id: &Id) -> bool {
+    self.state_vector.get(&id.client) > id.clock && !self.delete_set.contains(id)
+  }
+
+  /// the ranges of the item visible in the snapshot, relative to the start of
+  /// the item
+  pub(crate) fn visible_ranges(&self, item: &Item) -> Vec<Range<u64>> {
+    let Id { client, clock } = item.id;
+    let end = (clock + item.len()).min(self.state_vector.get(&client));
+    if end <= clock {
+      return vec![];
+    }
+
+    let mut ranges = std::iter::once(clock..end).collect::<Vec<_>>();
+    if let Some(deleted) = self.delete_set.get(&client) {
+      for deleted in deleted {
+        if deleted.start >= end {
+          break;
+        }
+
+        ranges = ranges
+          .into_iter()
+          .flat_map(|range| {
+            [
+              range.start..range.end.min(deleted.start),
+              range.start.max(deleted.end)..range.end,
+            ]
+          })
+          .filter(|range| !range.is_empty())
+          .collect();
+      }
+    }
+
+    ranges
+      .into_iter()
+      .map(|range| range.start - clock..range.end - clock)
+      .collect()
+  }
+
+  /// the content of the item visible in the snapshot
+  pub(crate) fn visible_contents(&self, item: &Item) -> Vec<Content> {
+    let len = item.len();
+
+    self
+      .visible_ranges(item)
+      .into_iter()
+      .filter_map(|range| {
+        if range.start == 0 && range.end == len {
+          return Some(item.content.clone());
+        }
+
+        let content = if range.start > 0 {
+          item.content.split(range.start).ok()?.1
+        } else {
+          item.content.clone()
+        };
+
+        if range.end < len {
+          content
+            .split(range.end - range.start)
+            .ok()
+            .map(|(left, _)| left)
+        } else {
+          Some(content)
+        }
+      })
+      .collect()
+  }
+}
+
+impl<R: CrdtReader> CrdtRead<R> for Snapshot {
+  fn read(decoder: &mut R) -> JwstCodecResult<Self> {
+    let delete_set = DeleteSet::read(decoder)?;
+    let state_vector = StateVector::read(decoder)?;
+
+    Ok(Self {
+      state_vector,
+      delete_set,
+    })
+  }
+}
+
+impl<W: CrdtWriter> CrdtWrite<W> for Snapshot {
+  fn write(&self, encoder: &mut W) -> JwstCodecResult {
+    self.delete_set.write(encoder)?;
+    self.state_vector.write(encoder)
+  }
+}
+
+impl Doc {
+  /// Take a [Snapshot] of the current state of the doc.
+  pub fn snapshot(&self) -> Snapshot {
+    let store = self.store.read().unwrap();
+
+    Snapshot::new(store.get_state_vector(), store.generate_delete_set())
+  }
+
+  /// Encode the state of the doc at the given snapshot as a v1 update, which
+  /// can be applied to an empty doc to restore the content of the snapshot.
+  ///
+  /// The doc must be created with gc disabled, otherwise the deleted content
+  /// may have been dropped.
+  pub fn encode_state_from_snapshot(&self, snapshot: &Snapshot) -> JwstCodecResult<Vec<u8>> {
+    if self.options().gc {
+      return Err(JwstCodecError::GarbageCollectionEnabled);
+    }
+
+    let store = self.store.read().unwrap();
+    let mut structs = ClientMap::new();
+
+    for (&client, &clock) in snapshot.state_vector.iter() {
+      if clock == 0 {
+        continue;
+      }
+
+      let invalid = || JwstCodecError::StructSequenceInvalid {
+        client_id: client,
+        clock,
+      };
+      if store.get_state(client) < clock {
+        return Err(invalid());
+      }
+
+      let nodes = store.items.get(&client).ok_or_else(invalid)?;
+      let last = DocStore::get_node_index(nodes, clock - 1).ok_or_else(invalid)?;
+
+      let mut snapshot_nodes = nodes.range(..last).cloned().collect::<VecDeque<_>>();
+      let node = &nodes[last];
+      let offset = clock - node.clock();
+      snapshot_nodes.push_back(if offset == node.len() {
+        node.clone()
+      } else {
+        node.split_at(offset)?.0
+      });
+
+      structs.insert(client, snapshot_nodes);
+    }
+
+    let update = Update {
+      structs,
+      delete_set: snapshot.delete_set.clone(),
+      ..Update::default()
+    };
+
+    let mut encoder = RawEncoder::default();
+    update.write(&mut encoder)?;
+    Ok(encoder.into_inner())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    GetString, Options, ReadTxn, Text as _, Transact,
+  };
+
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_snapshot_views() {
+    loom_model!({
+      let doc = DocOptions::new().auto_gc(false).build();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      let mut map = doc.get_or_create_map("map").unwrap();
+
+      text.insert(0, "hello world").unwrap();
+      array.push(1).unwrap();
+      array.push(2).unwrap();
+      array.push(3).unwrap();
+      map.insert("a".into(), "a").unwrap();
+      map.insert("b".into(), "b").unwrap();
+      let snapshot = doc.snapshot();
+
+      text.remove(2, 6).unwrap();
+      text.insert(2, "!").unwrap();
+      array.remove(1, 1).unwrap();
+      array.push(4).unwrap();
+      map.insert("a".into(), "c").unwrap();
+      map.remove("b");
+      map.insert("d".into(), "d").unwrap();
+
+      assert_eq!(text.to_string(), "he!rld");
+      assert_eq!(text.to_string_at(&snapshot), "hello world");
+      assert_eq!(text.to_string_at(&doc.snapshot()), "he!rld");
+      assert_eq!(text.to_string_at(&Snapshot::default()), "");
+      assert_eq!(
+        text.to_delta_at(&snapshot),
+        vec![TextDelta::Insert {
+          insert: TextInsert::Text("hello world".into()),
+          attributes: None,
+        }]
+      );
+
+      assert_eq!(
+        array
+          .to_vec_at(&snapshot)
+          .into_iter()
+          .map(|v| v.to_any().unwrap())
+          .collect::<Vec<_>>(),
+        vec![Any::Integer(1), Any::Integer(2), Any::Integer(3)]
+      );
+
+      assert_eq!(
+        map.get_at("a", &snapshot).and_then(|v| v.to_any()),
+        Some(Any::String("a".into()))
+      );
+      assert_eq!(
+        map.get_at("b", &snapshot).and_then(|v| v.to_any()),
+        Some(Any::String("b".into()))
+      );
+      assert!(map.get_at("d", &snapshot).is_none());
+      let mut keys = map.to_map_at(&snapshot).into_keys().collect::<Vec<_>>();
+      keys.sort();
+      assert_eq!(keys, vec!["a", "b"]);
+    });
+  }
+
+  #[test]
+  fn test_encode_state_from_snapshot() {
+    loom_model!({
+      let doc = DocOptions::new().auto_gc(false).build();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello world").unwrap();
+      text.remove(0, 1).unwrap();
+      let snapshot = doc.snapshot();
+      text.insert(0, "H").unwrap();
+      text.insert(1, "ey, h").unwrap();
+      text.remove(3, 8).unwrap();
+
+      let update = doc.encode_state_from_snapshot(&snapshot).unwrap();
+      let mut restored = Doc::new();
+      restored.apply_update_from_binary_v1(update).unwrap();
+      assert_eq!(
+        restored.get_or_create_text("text").unwrap().to_string(),
+        "ello world"
+      );
+
+      // the deleted content may be gone if gc is enabled
+      let doc = Doc::new();
+      assert_eq!(
+        doc.encode_state_from_snapshot(&doc.snapshot()),
+        Err(JwstCodecError::GarbageCollectionEnabled)
+      );
+    });
+  }
+
+  #[test]
+  fn test_snapshot_yrs_compatibility() {
+    let options = Options {
+      client_id: 1,
+      skip_gc: true,
+      ..Default::default()
+    };
+    let yrs_doc = yrs::Doc::with_options(options);
+    let yrs_text = yrs_doc.get_or_insert_text("text");
+    yrs_text.insert(&mut yrs_doc.transact_mut(), 0, "hello world");
+    yrs_text.remove_range(&mut yrs_doc.transact_mut(), 2, 3);
+    let yrs_snapshot = yrs_doc.transact().snapshot();
+    yrs_text.insert(&mut yrs_doc.transact_mut(), 0, "say: ");
+
+    let mut doc = DocOptions::new().auto_gc(false).build();
+    doc
+      .apply_update_from_binary_v1(
+        yrs_doc
+          .transact()
+          .encode_state_as_update_v1(&Default::default()),
+      )
+      .unwrap();
+    let text = doc.get_or_create_text("text").unwrap();
+
+    let snapshot = Snapshot::decode_v1(yrs_snapshot.encode_v1()).unwrap();
+    assert_eq!(text.to_string_at(&snapshot), "he world");
+    assert_eq!(
+      yrs::Snapshot::decode_v1(&snapshot.encode_v1().unwrap()).unwrap(),
+      yrs_snapshot
+    );
+
+    let yrs_restored = yrs::Doc::new();
+    yrs_restored
+      .transact_mut()
+      .apply_update(
+        yrs::Update::decode_v1(&doc.encode_state_from_snapshot(&snapshot).unwrap()).unwrap(),
+      )
+      .unwrap();
+    assert_eq!(
+      yrs_restored
+        .get_or_insert_text("text")
+        .get_string(&yrs_restored.transact()),
+      "he world"
+    );
+
+    assert_eq!(
+      doc.snapshot(),
+      Snapshot::decode_v1(yrs_doc.transact().snapshot().encode_v1()).unwrap()
+    );
+  }
+}
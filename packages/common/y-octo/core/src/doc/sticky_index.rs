@@ -0,0 +1,355 @@
+use super::*;
+
+/// Which side of the position the [StickyIndex] sticks to when content is
+/// inserted at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Assoc {
+  /// stick to the item after the position
+  #[default]
+  After,
+  /// stick to the item before the position
+  Before,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexScope {
+  /// the position is relative to an item in the list
+  Relative(Id),
+  /// the start or the end of a nested type, referenced by the id of the type
+  /// item
+  Nested(Id),
+  /// the start or the end of a root type
+  Root(String),
+}
+
+/// A position in a [Text] or an [Array] that survives concurrent edits, a.k.a
+/// `RelativePosition` in yjs.
+///
+/// ```
+/// use y_octo::{Assoc, Doc};
+///
+/// let doc = Doc::new();
+/// let mut text = doc.get_or_create_text("text").unwrap();
+/// text.insert(0, "hello world").unwrap();
+///
+/// let cursor = text.sticky_index(6, Assoc::After).unwrap();
+/// text.insert(0, "say: ").unwrap();
+/// assert_eq!(cursor.resolve(&doc), Some(11));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StickyIndex {
+  scope: IndexScope,
+  assoc: Assoc,
+}
+
+pub type RelativePosition = StickyIndex;
+
+impl StickyIndex {
+  pub fn new(scope: IndexScope, assoc: Assoc) -> Self {
+    Self { scope, assoc }
+  }
+
+  pub fn scope(&self) -> &IndexScope {
+    &self.scope
+  }
+
+  pub fn assoc(&self) -> Assoc {
+    self.assoc
+  }
+
+  pub(crate) fn from_type(ty: &YTypeRef, mut index: u64, assoc: Assoc) -> Option<Self> {
+    let inner = ty.ty()?;
+    if index > inner.len {
+      return None;
+    }
+
+    let type_scope = || match (&inner.root_name, inner.item.get()) {
+      (Some(name), _) => Some(IndexScope::Root(name.clone())),
+      (None, Some(item)) => Some(IndexScope::Nested(item.id)),
+      // the type is not integrated into any doc yet
+      (None, None) => None,
+    };
+
+    if assoc == Assoc::Before {
+      if index == 0 {
+        return type_scope().map(|scope| Self::new(scope, assoc));
+      }
+      index -= 1;
+    }
+
+    let mut item_ref = inner.start.clone();
+    while let Some(item) = item_ref.get() {
+      if item.indexable() {
+        if item.len() > index {
+          let id = Id::new(item.id.client, item.id.clock + index);
+          return Some(Self::new(IndexScope::Relative(id), assoc));
+        }
+        index -= item.len();
+      }
+
+      if item.right.is_none() && assoc == Assoc::Before {
+        return Some(Self::new(IndexScope::Relative(item.last_id()), assoc));
+      }
+
+      item_ref = item.right.clone();
+    }
+
+    type_scope().map(|scope| Self::new(scope, assoc))
+  }
+
+  /// Resolve the absolute index in the doc, returns [None] if the referenced
+  /// content doesn't exist in the doc or its type has been deleted.
+  ///
+  /// The index in a [Text] is in the unit of [Text::offset_kind].
+  pub fn resolve(&self, doc: &Doc) -> Option<u64> {
+    let store = doc.store.read().unwrap();
+    let kind = store.offset_kind;
+
+    match &self.scope {
+      IndexScope::Relative(id) => {
+        if store.get_state(id.client) <= id.clock {
+          return None;
+        }
+
+        let node = store.get_node(*id)?;
+        let item = node.as_item();
+        let item = item.get()?;
+        let Some(Parent::Type(parent)) = &item.parent else {
+          return None;
+        };
+        let parent = parent.ty()?;
+        if parent.item.get().is_some_and(|item| item.deleted()) {
+          return None;
+        }
+
+        let mut index = if item.deleted() || !item.countable() {
+          0
+        } else {
+          let offset = id.clock - item.id.clock + if self.assoc == Assoc::After { 0 } else { 1 };
+          match &item.content {
+            Content::String(str) => OffsetKind::Utf16.convert(str, offset, kind),
+            _ => offset,
+          }
+        };
+
+        let mut left = item.left.clone();
+        while let Some(item) = left.get() {
+          if item.indexable() {
+            index += kind.content_len(&item.content);
+          }
+          left = item.left.clone();
+        }
+
+        Some(index)
+      }
+      IndexScope::Nested(id) => {
+        if store.get_state(id.client) <= id.clock {
+          return None;
+        }
+
+        let node = store.get_node(*id)?;
+        let item = node.as_item();
+        let item = item.get()?;
+        match &item.content {
+          Content::Type(ty) if !item.deleted() => Some(match self.assoc {
+            Assoc::After => type_len(&*ty.ty()?, kind),
+            Assoc::Before => 0,
+          }),
+          _ => None,
+        }
+      }
+      IndexScope::Root(name) => Some(match self.assoc {
+        Assoc::After => store
+          .types
+          .get(name)
+          .and_then(|ty| ty.ty().map(|ty| type_len(&ty, kind)))
+          .unwrap_or_default(),
+        Assoc::Before => 0,
+      }),
+    }
+  }
+
+  pub fn decode_v1<T: AsRef<[u8]>>(buffer: T) -> JwstCodecResult<Self> {
+    Self::read(&mut RawDecoder::new(buffer.as_ref()))
+  }
+
+  pub fn encode_v1(&self) -> JwstCodecResult<Vec<u8>> {
+    let mut encoder = RawEncoder::default();
+    self.write(&mut encoder)?;
+    Ok(encoder.into_inner())
+  }
+}
+
+/// the length of the type in the unit of `kind`
+fn type_len(ty: &YType, kind: OffsetKind) -> u64 {
+  if kind == OffsetKind::Utf16 {
+    return ty.len;
+  }
+
+  let mut len = 0;
+  let mut item_ref = ty.start.clone();
+  while let Some(item) = item_ref.get() {
+    if item.indexable() {
+      len += kind.content_len(&item.content);
+    }
+    item_ref = item.right.clone();
+  }
+
+  len
+}
+
+impl<R: CrdtReader> CrdtRead<R> for StickyIndex {
+  fn read(decoder: &mut R) -> JwstCodecResult<Self> {
+    let scope = match decoder.read_var_u64()? {
+      0 => IndexScope::Relative(decoder.read_item_id()?),
+      1 => IndexScope::Root(decoder.read_var_string()?),
+      2 => IndexScope::Nested(decoder.read_item_id()?),
+      _ => return Err(JwstCodecError::UnexpectedType("sticky index scope")),
+    };
+
+    // the association is optional in the old versions of yjs
+    let assoc = if decoder.is_empty() || decoder.read_var_i32()? >= 0 {
+      Assoc::After
+    } else {
+      Assoc::Before
+    };
+
+    Ok(Self { scope, assoc })
+  }
+}
+
+impl<W: CrdtWriter> CrdtWrite<W> for StickyIndex {
+  fn write(&self, encoder: &mut W) -> JwstCodecResult {
+    match &self.scope {
+      IndexScope::Relative(id) => {
+        encoder.write_var_u64(0)?;
+        encoder.write_item_id(id)?;
+      }
+      IndexScope::Root(name) => {
+        encoder.write_var_u64(1)?;
+        encoder.write_var_string(name)?;
+      }
+      IndexScope::Nested(id) => {
+        encoder.write_var_u64(2)?;
+        encoder.write_item_id(id)?;
+      }
+    }
+
+    encoder.write_var_i32(match self.assoc {
+      Assoc::After => 0,
+      Assoc::Before => -1,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    IndexedSequence, Options, ReadTxn, Text as _, Transact,
+  };
+
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_sticky_index() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello world").unwrap();
+
+      let after = text.sticky_index(5, Assoc::After).unwrap();
+      let before = text.sticky_index(5, Assoc::Before).unwrap();
+      let start = text.sticky_index(0, Assoc::Before).unwrap();
+      let end = text.sticky_index(11, Assoc::After).unwrap();
+      assert_eq!(start.scope(), &IndexScope::Root("text".into()));
+      assert_eq!(end.scope(), &IndexScope::Root("text".into()));
+      assert!(text.sticky_index(12, Assoc::After).is_none());
+
+      // insertions at the position move the positions stick to the right side
+      text.insert(5, ",").unwrap();
+      assert_eq!(after.resolve(&doc), Some(6));
+      assert_eq!(before.resolve(&doc), Some(5));
+
+      text.insert(0, ">> ").unwrap();
+      assert_eq!(after.resolve(&doc), Some(9));
+      assert_eq!(before.resolve(&doc), Some(8));
+      assert_eq!(start.resolve(&doc), Some(0));
+      assert_eq!(end.resolve(&doc), Some(15));
+
+      // the referenced item is deleted
+      text.remove(3, 6).unwrap();
+      assert_eq!(after.resolve(&doc), Some(3));
+    });
+  }
+
+  #[test]
+  fn test_sticky_index_nested() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      array.push(doc.create_array().unwrap()).unwrap();
+      let mut nested = array.get(0).unwrap().to_array().unwrap();
+      nested.push(1).unwrap();
+
+      let end = nested.sticky_index(1, Assoc::After).unwrap();
+      assert!(matches!(end.scope(), IndexScope::Nested(_)));
+      nested.push(2).unwrap();
+      assert_eq!(end.resolve(&doc), Some(2));
+
+      array.remove(0, 1).unwrap();
+      assert_eq!(end.resolve(&doc), None);
+    });
+  }
+
+  #[test]
+  fn test_sticky_index_yrs_compatibility() {
+    let options = Options {
+      client_id: 1,
+      ..Default::default()
+    };
+    let yrs_doc = yrs::Doc::with_options(options);
+    let yrs_text = yrs_doc.get_or_insert_text("text");
+    yrs_text.insert(&mut yrs_doc.transact_mut(), 0, "hello world");
+
+    let mut doc = Doc::default();
+    doc
+      .apply_update_from_binary_v1(
+        yrs_doc
+          .transact()
+          .encode_state_as_update_v1(&Default::default()),
+      )
+      .unwrap();
+    let text = doc.get_or_create_text("text").unwrap();
+
+    for (index, assoc, yrs_assoc) in [
+      (0, Assoc::Before, yrs::Assoc::Before),
+      (0, Assoc::After, yrs::Assoc::After),
+      (5, Assoc::Before, yrs::Assoc::Before),
+      (5, Assoc::After, yrs::Assoc::After),
+      // yrs doesn't create the position at the end of the type with
+      // `Assoc::After`, which is supported by yjs
+      (11, Assoc::Before, yrs::Assoc::Before),
+    ] {
+      let sticky = text.sticky_index(index, assoc).unwrap();
+      let yrs_sticky = yrs_text
+        .sticky_index(&mut yrs_doc.transact_mut(), index as u32, yrs_assoc)
+        .unwrap();
+
+      assert_eq!(sticky.encode_v1().unwrap(), yrs_sticky.encode_v1());
+      assert_eq!(
+        StickyIndex::decode_v1(yrs_sticky.encode_v1()).unwrap(),
+        sticky
+      );
+
+      let yrs_sticky = yrs::StickyIndex::decode_v1(&sticky.encode_v1().unwrap()).unwrap();
+      assert_eq!(
+        yrs_sticky
+          .get_offset(&yrs_doc.transact())
+          .map(|o| o.index as u64),
+        sticky.resolve(&doc)
+      );
+    }
+  }
+}
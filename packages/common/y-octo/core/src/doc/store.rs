@@ -2,14 +2,30 @@ use std::{
   collections::{hash_map::Entry, VecDeque},
   mem,
   ops::{Deref, Range},
+  time::Instant,
 };
 
-use super::*;
+use super::{document::prefer_small_random, *};
 use crate::{
   doc::StateVector,
-  sync::{Arc, RwLock, RwLockWriteGuard, Weak},
+  sync::{Arc, Mutex, RwLock, RwLockWriteGuard, Weak},
 };
 
+/// A batch of changes made by local operations, delivered to the recorders
+/// registered in [DocStore::change_recorders]. Changes from remote updates
+/// are not recorded.
+#[derive(Debug, Clone)]
+pub(crate) struct LocalChange {
+  pub origin: Option<Origin>,
+  /// changes made in the same transaction share the same batch
+  pub batch: u64,
+  pub time: Instant,
+  pub insertions: DeleteSet,
+  pub deletions: DeleteSet,
+}
+
+pub(crate) type ChangeRecorder = Arc<Mutex<Vec<LocalChange>>>;
+
 unsafe impl Send for DocStore {}
 unsafe impl Sync for DocStore {}
 
@@ -26,6 +42,25 @@ pub(crate) struct DocStore {
   pub dangling_types: HashMap<usize, YTypeRef>,
   pub pending: Option<Update>,
   pub last_optimized_state: StateVector,
+  pub change_recorders: Vec<Weak<Mutex<Vec<LocalChange>>>>,
+  pub change_batch: u64,
+  // the transaction in progress, see [Doc::transact]
+  pub txn: Option<TransactionState>,
+  pub txn_lock: Arc<TransactionLock>,
+  pub observers: Observers,
+  // the subdocs integrated into this store, by guid
+  pub subdocs: HashMap<String, Doc>,
+  // the store of the doc this doc belongs to as a subdoc
+  pub parent: Option<WeakStoreRef>,
+  // the item holding this doc in the parent doc
+  pub parent_item: Option<Id>,
+  pub should_load: bool,
+  // the unit of the indexes taken by [Text], see [DocOptions::offset_kind]
+  pub offset_kind: OffsetKind,
+  // the state of the doc this one is forked from at the time, see [Doc::fork]
+  pub fork_state: Option<StateVector>,
+  // the move items by the ids their ranges are anchored at, see [Move::start_anchor]
+  pub move_anchors: HashMap<Id, Vec<ItemRef>>,
 }
 
 pub(crate) type StoreRef = Arc<RwLock<DocStore>>;
@@ -49,16 +84,27 @@ impl DocStore {
     self.client
   }
 
+  /// replace the local client id with a random one not used by any struct
+  pub fn rotate_client(&mut self) -> Client {
+    let client = loop {
+      let client = prefer_small_random();
+      if client != self.client && !self.items.contains_key(&client) {
+        break client;
+      }
+    };
+    self.client = client;
+
+    client
+  }
+
   pub fn clients(&self) -> Vec<Client> {
     self.items.keys().cloned().collect()
   }
 
-  #[cfg(feature = "debug")]
   pub fn total_nodes(&self) -> usize {
     self.items.values().map(|v| v.len()).sum()
   }
 
-  #[cfg(feature = "debug")]
   pub fn total_delete_sets(&self) -> usize {
     self
       .delete_set
@@ -70,17 +116,14 @@ impl DocStore {
       .sum()
   }
 
-  #[cfg(feature = "debug")]
   pub fn total_types(&self) -> usize {
     self.types.len()
   }
 
-  #[cfg(feature = "debug")]
   pub fn total_dangling_types(&self) -> usize {
     self.dangling_types.len()
   }
 
-  #[cfg(feature = "debug")]
   pub fn total_pending_nodes(&self) -> usize {
     self.pending.as_ref().map(|p| p.structs.len()).unwrap_or(0)
   }
@@ -165,16 +208,23 @@ impl DocStore {
   }
 
   pub fn create_item(
-    &self,
+    &mut self,
     content: Content,
     left: Somr<Item>,
     right: Somr<Item>,
     parent: Option<Parent>,
     parent_sub: Option<SmolStr>,
   ) -> ItemRef {
-    let id = (self.client(), self.get_state(self.client())).into();
+    let id: Id = (self.client(), self.get_state(self.client())).into();
     let item = Somr::new(Item::new(id, content, left, right, parent, parent_sub));
 
+    if !self.change_recorders.is_empty() {
+      let len = item.get().unwrap().len();
+      let mut insertions = DeleteSet::default();
+      insertions.add(id.client, id.clock, len);
+      self.record_change(insertions, DeleteSet::default());
+    }
+
     if let Content::Type(ty) = &item.get().unwrap().content {
       if let Some(mut ty) = ty.ty_mut() {
         ty.item = item.clone();
@@ -250,6 +300,7 @@ impl DocStore {
         right_item.right = mem::replace(&mut left_item.right, right_ref.clone());
         right_item.origin_left_id = Some(left_item.last_id());
         right_item.origin_right_id = left_item.origin_right_id;
+        right_item.moved = left_item.moved;
       };
 
       let right = Node::Item(right_ref);
@@ -335,6 +386,136 @@ impl DocStore {
     }
   }
 
+  /// Deep clone the structs, types, delete set and pending update into the
+  /// empty store, with the references between them pointing to the cloned
+  /// ones instead of the original ones.
+  pub fn clone_into(&self, target: &StoreRef) {
+    let mut store = target.write().unwrap();
+    let store_ref = Arc::downgrade(target);
+    let addr = |ty: &YTypeRef| ty.inner.ptr().as_ptr() as usize;
+
+    // the original types and the cloned ones, by the address of the original
+    let mut types: HashMap<usize, (YTypeRef, YTypeRef)> = HashMap::new();
+    let mut clone_type = |ty: &YTypeRef| {
+      let inner = ty.ty()?;
+      let mut cloned = YType::new(inner.kind(), inner.name.clone());
+      cloned.root_name = inner.root_name.clone();
+      cloned.len = inner.len;
+      cloned.markers = inner.markers.as_ref().map(|_| MarkerList::new());
+
+      let cloned = YTypeRef {
+        store: store_ref.clone(),
+        inner: Somr::new(RwLock::new(cloned)),
+      };
+      types.insert(addr(ty), (ty.clone(), cloned.clone()));
+      Some(cloned)
+    };
+    for (name, ty) in &self.types {
+      if let Some(cloned) = clone_type(ty) {
+        store.types.insert(name.clone(), cloned);
+      }
+    }
+    for ty in self.dangling_types.values() {
+      if let Some(cloned) = clone_type(ty) {
+        store.dangling_types.insert(addr(&cloned), cloned);
+      }
+    }
+
+    // clone the items with the parents and contents pointing to the cloned
+    // types, the references between items are rebuilt once all are cloned
+    let mut items: HashMap<Id, ItemRef> = HashMap::new();
+    for (client, nodes) in &self.items {
+      let nodes = nodes
+        .iter()
+        .map(|node| {
+          let Node::Item(item_ref) = node else {
+            return node.clone();
+          };
+          let item = item_ref.get().unwrap();
+
+          let mut cloned = item.clone();
+          cloned.left = Somr::none();
+          cloned.right = Somr::none();
+          if let Some(Parent::Type(ty)) = &item.parent {
+            cloned.parent = types.get(&addr(ty)).map(|(_, ty)| Parent::Type(ty.clone()));
+          }
+          match &mut cloned.content {
+            Content::Type(ty) => {
+              if let Some((_, cloned_ty)) = types.get(&addr(ty)) {
+                *ty = cloned_ty.clone();
+              }
+            }
+            Content::Doc { doc, .. } => *doc = Default::default(),
+            _ => {}
+          }
+
+          let cloned = Somr::new(cloned);
+          items.insert(item.id, cloned.clone());
+          Node::Item(cloned)
+        })
+        .collect();
+      store.items.insert(*client, nodes);
+    }
+
+    let cloned_ref = |item_ref: &ItemRef| {
+      item_ref
+        .get()
+        .and_then(|item| items.get(&item.id))
+        .cloned()
+        .unwrap_or_default()
+    };
+    for nodes in self.items.values() {
+      for node in nodes {
+        let Node::Item(item_ref) = node else {
+          continue;
+        };
+        let item = item_ref.get().unwrap();
+        let cloned_item = &items[&item.id];
+        // SAFETY: the cloned items are not visible to others until the
+        // write lock of the target store is released
+        let mut cloned = unsafe { cloned_item.get_mut_unchecked() };
+        cloned.left = cloned_ref(&item.left);
+        cloned.right = cloned_ref(&item.right);
+        if let (Content::Move(cloned), Content::Move(mv)) = (&mut cloned.content, &item.content) {
+          cloned.start_anchor = cloned_ref(&mv.start_anchor);
+          cloned.end_anchor = cloned_ref(&mv.end_anchor);
+        }
+      }
+    }
+    for (ty, cloned) in types.values() {
+      let (Some(ty), Some(mut cloned)) = (ty.ty(), cloned.ty_mut()) else {
+        continue;
+      };
+      cloned.start = cloned_ref(&ty.start);
+      cloned.item = cloned_ref(&ty.item);
+      cloned.map = ty
+        .map
+        .iter()
+        .map(|(key, item)| (key.clone(), cloned_ref(item)))
+        .collect();
+    }
+    store.move_anchors = self
+      .move_anchors
+      .iter()
+      .map(|(id, moves)| (*id, moves.iter().map(cloned_ref).collect()))
+      .collect();
+
+    for item_ref in items.values() {
+      let Some(item) = item_ref.get() else {
+        continue;
+      };
+      if let Content::Doc { guid, .. } = &item.content {
+        if !item.deleted() && self.subdocs.contains_key(guid) {
+          store.add_subdoc(item);
+        }
+      }
+    }
+
+    store.delete_set = self.delete_set.clone();
+    store.pending = self.pending.as_ref().map(Update::deep_clone);
+    store.last_optimized_state = self.last_optimized_state.clone();
+  }
+
   /// A repair for an item do such things:
   ///   - split left if needed  (insert in between a splitable item)
   ///   - split right if needed (insert in between a splitable item)
@@ -436,6 +617,8 @@ impl DocStore {
     offset: u64,
     parent: Option<&mut YType>,
   ) -> JwstCodecResult {
+    let mut inserted = None;
+    let mut move_ref = None;
     match &mut node {
       Node::Item(item_owner_ref) => {
         assert!(
@@ -465,6 +648,11 @@ impl DocStore {
           let parent = if let Some(p) = parent {
             p
           } else if let Some(ty) = ty.ty_mut() {
+            // the search markers only follow the list operations made through the type,
+            // they are outdated by the items integrated from remote
+            if let Some(markers) = &ty.markers {
+              markers.borrow_mut().clear();
+            }
             parent_lock = Some(ty);
             parent_lock.as_deref_mut().unwrap()
           } else {
@@ -588,12 +776,29 @@ impl DocStore {
             .map(|item| item.deleted())
             .unwrap_or(false);
 
+          // search markers can't tell whether an insertion happens inside or outside a
+          // formatted range, disable them once the type has formats, same as yjs
+          if matches!(this.content, Content::Format { .. }) {
+            parent.markers = None;
+          }
+
+          if matches!(this.content, Content::Doc { .. }) {
+            self.add_subdoc(this);
+          }
+
+          if matches!(this.content, Content::Move(_)) {
+            // same as formats, the indexes of markers don't count the moved items
+            parent.markers = None;
+            move_ref = Some(item_owner_ref.clone());
+          }
+          inserted = Some(item_owner_ref.clone());
+
           // should delete
           if parent_deleted || this.parent_sub.is_some() && this.right.is_some() {
             self.delete_node(&Node::Item(item_owner_ref.clone()), Some(parent));
           } else {
             // adjust parent length
-            if this.parent_sub.is_none() {
+            if this.parent_sub.is_none() && this.countable() {
               parent.len += this.len();
             }
           }
@@ -616,19 +821,403 @@ impl DocStore {
         // skip ignored
       }
     }
-    self.add_node(node)
+    self.add_node(node)?;
+
+    if let Some(item_ref) = inserted {
+      self.inherit_move(&item_ref);
+    }
+    if let Some(move_ref) = move_ref {
+      self.integrate_move(&move_ref);
+    }
+
+    Ok(())
   }
 
   pub fn delete_item(&mut self, item: &Item, parent: Option<&mut YType>) {
     let mut pending_delete_sets = HashMap::new();
-    Self::delete_item_inner(&mut pending_delete_sets, item, parent);
+    let mut removed_subdocs = Vec::new();
+    let mut removed_moves = Vec::new();
+    let mut removed_types = Vec::new();
+    Self::delete_item_inner(
+      &mut pending_delete_sets,
+      &mut removed_subdocs,
+      &mut removed_moves,
+      &mut removed_types,
+      item,
+      parent,
+    );
+    self.commit_delete_sets(pending_delete_sets);
+    self.remove_subdocs(removed_subdocs);
+    self.remove_moves(removed_moves);
+    self.observers.remove_types(removed_types);
+  }
+
+  /// the anchor of a move range boundary, see [Move::start_anchor], split the
+  /// items so that the range starts and ends at item boundaries
+  fn get_move_anchor(&mut self, id: Id, assoc: Assoc) -> ItemRef {
+    if self.get_state(id.client) <= id.clock {
+      return Somr::none();
+    }
+
+    match assoc {
+      Assoc::After => self.split_at_and_get_right(id),
+      Assoc::Before => self.split_at_and_get_left(id),
+    }
+    .map(|node| node.as_item())
+    .unwrap_or_default()
+  }
+
+  /// an item inserted into a moved range is moved along, if its neighbors are
+  /// moved by different moves, let them take it again, same as yjs
+  fn inherit_move(&mut self, item_ref: &ItemRef) {
+    let Some(item) = item_ref.get() else {
+      return;
+    };
+    let left = item.left.get().and_then(|left| left.moved);
+    let right = item.right.get().and_then(|right| right.moved);
+
+    if left == right {
+      if left.is_some() {
+        // SAFETY: we hold the store write lock, items can only be mutated here
+        unsafe { item_ref.get_mut_unchecked().moved = left };
+      }
+      return;
+    }
+
+    for id in [left, right].into_iter().flatten() {
+      if let Some(move_ref) = self.get_move(id) {
+        if move_ref
+          .get()
+          .is_some_and(|item| matches!(&item.content, Content::Move(mv) if !mv.is_collapsed()))
+        {
+          self.take_move_range(&move_ref);
+        }
+      }
+    }
+  }
+
+  /// Resolve the range of a move item and take the items in it.
+  fn integrate_move(&mut self, item_ref: &ItemRef) {
+    let Some(mv) = item_ref.get().and_then(|item| match &item.content {
+      Content::Move(mv) if !item.deleted() => Some(mv.clone()),
+      _ => None,
+    }) else {
+      return;
+    };
+
+    let start_anchor = self.get_move_anchor(mv.start, mv.start_assoc);
+    let end_anchor = self.get_move_anchor(mv.end, mv.end_assoc);
+
+    // SAFETY: we hold the store write lock, items can only be mutated here
+    unsafe {
+      if let Content::Move(mv) = &mut item_ref.get_mut_unchecked().content {
+        mv.start_anchor = start_anchor;
+        mv.end_anchor = end_anchor;
+      }
+    }
+    for anchor in [mv.start, mv.end] {
+      let moves = self.move_anchors.entry(anchor).or_default();
+      if !moves.contains(item_ref) {
+        moves.push(item_ref.clone());
+      }
+    }
+
+    self.take_move_range(item_ref);
+  }
+
+  /// Mark the items in the range of the move item as moved by it, same as
+  /// yjs: an item is only taken from a move with a lower priority, or the
+  /// same priority but a lower id, and a local move gets a priority higher
+  /// than the moves of all the items in its range so that it always wins.
+  /// A move that ends up moving itself is deleted.
+  fn take_move_range(&mut self, move_ref: &ItemRef) {
+    let Some((id, mv)) = move_ref.get().and_then(|item| match &item.content {
+      Content::Move(mv) if !item.deleted() => Some((item.id, mv.clone())),
+      _ => None,
+    }) else {
+      return;
+    };
+
+    let adapt_priority = mv.priority < 0;
+    let mut max_priority = 0;
+    let mut overrides = Vec::new();
+
+    let end = mv.end_item();
+    let mut cur = mv.start_item();
+    while cur != end {
+      let Some(cur_item) = cur.get() else {
+        break;
+      };
+      let prev = cur_item.moved.filter(|prev| *prev != id);
+      let prev_priority = prev.map(|prev| self.get_move_priority(prev)).unwrap_or(-1);
+
+      if cur_item.moved == Some(id) {
+        // taken already
+      } else if adapt_priority
+        || prev_priority < mv.priority
+        || prev.is_some_and(|prev| {
+          prev_priority == mv.priority && (prev.client, prev.clock) < (id.client, id.clock)
+        })
+      {
+        if let Some(prev) = prev {
+          if let Some(prev_ref) = self.get_move(prev) {
+            // a collapsed move has nothing left to move
+            if let Some(prev_item) = prev_ref.get() {
+              if matches!(&prev_item.content, Content::Move(mv) if mv.is_collapsed()) {
+                self.delete_item(prev_item, None);
+              }
+            }
+          }
+          if !overrides.contains(&prev) {
+            overrides.push(prev);
+          }
+        }
+        max_priority = max_priority.max(prev_priority);
+
+        // SAFETY: we hold the store write lock, items can only be mutated here
+        unsafe { cur.get_mut_unchecked().moved = Some(id) };
+
+        if let Content::Move(cur_mv) = &cur_item.content {
+          if !cur_item.deleted()
+            && Self::find_move_loop(cur_mv, cur_item.id, &mut HashSet::from_iter([id]))
+          {
+            Self::push_move_overrides(move_ref, overrides);
+            if let Some(item) = move_ref.get() {
+              self.delete_item(item, None);
+            }
+            return;
+          }
+        }
+      } else if let Some(prev_ref) = prev.and_then(|prev| self.get_move(prev)) {
+        Self::push_move_overrides(&prev_ref, [id]);
+      }
+
+      cur = cur_item.right.clone();
+    }
+
+    Self::push_move_overrides(move_ref, overrides);
+    if adapt_priority {
+      // SAFETY: we hold the store write lock, items can only be mutated here
+      unsafe {
+        if let Content::Move(mv) = &mut move_ref.get_mut_unchecked().content {
+          mv.priority = max_priority + 1;
+        }
+      }
+    }
+  }
+
+  /// whether the move, which is the content of the item `moved`, moves the
+  /// moves already tracked, directly or along with the moves it moves
+  fn find_move_loop(mv: &Move, moved: Id, tracked: &mut HashSet<Id>) -> bool {
+    if !tracked.insert(moved) {
+      return true;
+    }
+
+    let end = mv.end_item();
+    let mut cur = mv.start_item();
+    while cur != end {
+      let Some(cur_item) = cur.get() else {
+        break;
+      };
+      if let Content::Move(cur_mv) = &cur_item.content {
+        if !cur_item.deleted()
+          && cur_item.moved == Some(moved)
+          && Self::find_move_loop(cur_mv, cur_item.id, tracked)
+        {
+          return true;
+        }
+      }
+      cur = cur_item.right.clone();
+    }
+
+    false
+  }
+
+  /// Release the items taken by the deleted moves and let the moves they
+  /// overrode take them again, same as yjs.
+  fn remove_moves(&mut self, moves: Vec<Id>) {
+    for id in moves {
+      let Some(move_ref) = self.get_move(id) else {
+        continue;
+      };
+      let Some(mv) = move_ref.get().and_then(|item| match &item.content {
+        Content::Move(mv) => Some(mv.clone()),
+        _ => None,
+      }) else {
+        continue;
+      };
+
+      let end = mv.end_item();
+      let mut cur = mv.start_item();
+      while cur != end {
+        let Some(cur_item) = cur.get() else {
+          break;
+        };
+        if cur_item.moved == Some(id) {
+          // SAFETY: we hold the store write lock, items can only be mutated here
+          unsafe { cur.get_mut_unchecked().moved = None };
+        }
+        cur = cur_item.right.clone();
+      }
+
+      for anchor in [mv.start, mv.end] {
+        if let Some(moves) = self.move_anchors.get_mut(&anchor) {
+          moves.retain(|mv| *mv != move_ref);
+          if moves.is_empty() {
+            self.move_anchors.remove(&anchor);
+          }
+        }
+      }
+
+      let mut visited = HashSet::from_iter([id]);
+      self.reintegrate_moves(mv.overrides, &mut visited);
+    }
+  }
+
+  fn reintegrate_moves(&mut self, moves: Vec<Id>, visited: &mut HashSet<Id>) {
+    for id in moves {
+      if !visited.insert(id) {
+        continue;
+      }
+      let Some(move_ref) = self.get_move(id) else {
+        continue;
+      };
+      let Some((deleted, overrides)) = move_ref.get().and_then(|item| match &item.content {
+        Content::Move(mv) => Some((item.deleted(), mv.overrides.clone())),
+        _ => None,
+      }) else {
+        continue;
+      };
+
+      if deleted {
+        // the items it overrode may be taken instead
+        self.reintegrate_moves(overrides, visited);
+      } else {
+        self.take_move_range(&move_ref);
+      }
+    }
+  }
+
+  fn push_move_overrides(move_ref: &ItemRef, ids: impl IntoIterator<Item = Id>) {
+    // SAFETY: we hold the store write lock, items can only be mutated here
+    unsafe {
+      if let Content::Move(mv) = &mut move_ref.get_mut_unchecked().content {
+        for id in ids {
+          if !mv.overrides.contains(&id) {
+            mv.overrides.push(id);
+          }
+        }
+      }
+    }
+  }
+
+  fn get_move(&self, id: Id) -> Option<ItemRef> {
+    let item_ref = self.get_node(id)?.as_item();
+    matches!(item_ref.get()?.content, Content::Move(_)).then_some(item_ref)
+  }
+
+  fn get_move_priority(&self, id: Id) -> i32 {
+    let item_ref = self.get_node(id).map(|n| n.as_item()).unwrap_or_default();
+    match item_ref.get().map(|item| &item.content) {
+      Some(Content::Move(mv)) => mv.priority,
+      _ => -1,
+    }
+  }
+
+  fn add_subdoc(&mut self, item: &Item) {
+    let (Some(doc), Some(Parent::Type(parent))) = (item.content.subdoc(), &item.parent) else {
+      return;
+    };
+
+    let should_load = {
+      let mut store = doc.store.write().unwrap();
+      store.parent = Some(parent.store.clone());
+      store.parent_item = Some(item.id);
+      store.should_load
+    };
+    self.subdocs.insert(doc.guid().to_owned(), doc.clone());
+
+    if let Some(txn) = &mut self.txn {
+      txn.subdocs_added.push(doc.clone());
+      if should_load {
+        txn.subdocs_loaded.push(doc.clone());
+      }
+    }
+  }
+
+  fn remove_subdocs(&mut self, docs: Vec<Doc>) {
+    for doc in docs {
+      self.subdocs.remove(doc.guid());
+
+      if let Some(txn) = &mut self.txn {
+        // the subdoc is added and removed in the same transaction
+        let added = txn.subdocs_added.len();
+        txn.subdocs_added.retain(|added| !added.ptr_eq(&doc));
+        if added == txn.subdocs_added.len() {
+          txn.subdocs_removed.push(doc);
+        } else {
+          txn.subdocs_loaded.retain(|loaded| !loaded.ptr_eq(&doc));
+        }
+      }
+    }
+  }
+
+  fn commit_delete_sets(&mut self, pending_delete_sets: HashMap<u64, Vec<Range<u64>>>) {
+    if pending_delete_sets.is_empty() {
+      return;
+    }
+
+    if !self.change_recorders.is_empty() || self.txn.is_some() {
+      let mut deletions = DeleteSet::default();
+      for (client, ranges) in &pending_delete_sets {
+        deletions.batch_add_ranges(*client, ranges.clone());
+      }
+      if let Some(txn) = &mut self.txn {
+        txn.deletions.merge(&deletions);
+      }
+      if !self.change_recorders.is_empty() {
+        self.record_change(DeleteSet::default(), deletions);
+      }
+    }
+
     for (client, ranges) in pending_delete_sets {
       self.delete_set.batch_add_ranges(client, ranges);
     }
   }
 
+  pub fn add_change_recorder(&mut self, recorder: &ChangeRecorder) {
+    self
+      .change_recorders
+      .retain(|recorder| recorder.strong_count() > 0);
+    self.change_recorders.push(Arc::downgrade(recorder));
+  }
+
+  fn record_change(&mut self, insertions: DeleteSet, deletions: DeleteSet) {
+    let change = LocalChange {
+      origin: self.txn.as_ref().and_then(|txn| txn.origin.clone()),
+      batch: self.change_batch,
+      time: Instant::now(),
+      insertions,
+      deletions,
+    };
+
+    // drop the recorders of dropped undo managers or user data along the way
+    self
+      .change_recorders
+      .retain(|recorder| match recorder.upgrade() {
+        Some(recorder) => {
+          recorder.lock().unwrap().push(change.clone());
+          true
+        }
+        None => false,
+      });
+  }
+
   fn delete_item_inner(
     delete_set: &mut HashMap<u64, Vec<Range<u64>>>,
+    removed_subdocs: &mut Vec<Doc>,
+    removed_moves: &mut Vec<Id>,
+    removed_types: &mut Vec<Id>,
     item: &Item,
     parent: Option<&mut YType>,
   ) {
@@ -651,19 +1240,32 @@ impl DocStore {
           parent.len -= item.len();
         }
       } else if let Some(Parent::Type(ty)) = &item.parent {
-        ty.ty_mut().unwrap().len -= item.len();
+        let mut ty = ty.ty_mut().unwrap();
+        ty.len -= item.len();
+        // same as the integration, not deleted through the type
+        if let Some(markers) = &ty.markers {
+          markers.borrow_mut().clear();
+        }
       }
     }
 
     match &item.content {
       Content::Type(ty) => {
+        removed_types.push(item.id);
         // 4. delete all children
         if let Some(mut ty) = ty.ty_mut() {
           // items in ty are all refs, not owned
           let mut item_ref = ty.start.clone();
           while let Some(item) = item_ref.get() {
             if !item.deleted() {
-              Self::delete_item_inner(delete_set, item, Some(&mut ty));
+              Self::delete_item_inner(
+                delete_set,
+                removed_subdocs,
+                removed_moves,
+                removed_types,
+                item,
+                Some(&mut ty),
+              );
             }
 
             item_ref = item.right.clone();
@@ -673,15 +1275,25 @@ impl DocStore {
           for item in map_values {
             if let Some(item) = item.get() {
               if !item.deleted() {
-                Self::delete_item_inner(delete_set, item, Some(&mut ty));
+                Self::delete_item_inner(
+                  delete_set,
+                  removed_subdocs,
+                  removed_moves,
+                  removed_types,
+                  item,
+                  Some(&mut ty),
+                );
               }
             }
           }
         }
       }
       Content::Doc { .. } => {
-        // TODO: remove subdoc
+        if let Some(doc) = item.content.subdoc() {
+          removed_subdocs.push(doc);
+        }
       }
+      Content::Move(_) => removed_moves.push(item.id),
       _ => {}
     }
   }
@@ -713,6 +1325,9 @@ impl DocStore {
         };
 
         let mut pending_delete_sets = HashMap::new();
+        let mut removed_subdocs = Vec::new();
+        let mut removed_moves = Vec::new();
+        let mut removed_types = Vec::new();
         while idx < items.len() {
           let node = items[idx].clone();
           let id = node.id();
@@ -727,7 +1342,14 @@ impl DocStore {
                   DocStore::split_node_at(items, idx, end - id.clock)?;
                 }
 
-                Self::delete_item_inner(&mut pending_delete_sets, item, None);
+                Self::delete_item_inner(
+                  &mut pending_delete_sets,
+                  &mut removed_subdocs,
+                  &mut removed_moves,
+                  &mut removed_types,
+                  item,
+                  None,
+                );
               }
             }
           } else {
@@ -736,9 +1358,10 @@ impl DocStore {
 
           idx += 1;
         }
-        for (client, ranges) in pending_delete_sets {
-          self.delete_set.batch_add_ranges(client, ranges);
-        }
+        self.commit_delete_sets(pending_delete_sets);
+        self.remove_subdocs(removed_subdocs);
+        self.remove_moves(removed_moves);
+        self.observers.remove_types(removed_types);
       }
     }
 
@@ -772,7 +1395,7 @@ impl DocStore {
 
     let mut update = Update {
       structs: update_structs,
-      delete_set: Self::generate_delete_set(&self.items),
+      delete_set: self.generate_delete_set(),
       ..Update::default()
     };
 
@@ -825,7 +1448,11 @@ impl DocStore {
     Ok(update_structs)
   }
 
-  fn generate_delete_set(refs: &ClientMap<VecDeque<Node>>) -> DeleteSet {
+  pub fn generate_delete_set(&self) -> DeleteSet {
+    Self::generate_delete_set_from(&self.items)
+  }
+
+  fn generate_delete_set_from(refs: &ClientMap<VecDeque<Node>>) -> DeleteSet {
     let mut delete_set = DeleteSet::default();
 
     for (client, nodes) in refs {
@@ -840,16 +1467,98 @@ impl DocStore {
 
   /// Optimize the memory usage of store
   pub fn optimize(&mut self) -> JwstCodecResult {
+    self.optimize_with(&GcOptions::default()).map(|_| ())
+  }
+
+  /// Optimize the memory usage of store, only the deleted content allowed by
+  /// the options is collected
+  pub fn optimize_with(&mut self, options: &GcOptions) -> JwstCodecResult<GcStats> {
     //  1. gc delete set
-    self.gc_delete_set()?;
+    let stats = self.gc_delete_set(options)?;
+    if options.dry_run {
+      return Ok(stats);
+    }
     //  2. merge delete set (in our delete set impl, which is based on `OrderRange`
     //     has already have auto-merge functionality), pass
     //  3. merge same content siblings, e.g contentString + ContentString
     self.make_continuous();
-    Ok(())
+    Ok(stats)
+  }
+
+  /// the metrics of the structs in the store
+  pub fn status(&self) -> DocStoreStatus {
+    let mut status = DocStoreStatus {
+      nodes: self.total_nodes(),
+      delete_sets: self.total_delete_sets(),
+      types: self.total_types(),
+      dangling_types: self.total_dangling_types(),
+      pending_nodes: self.total_pending_nodes(),
+      ..Default::default()
+    };
+
+    for (client, nodes) in self.items.iter() {
+      let mut items = 0;
+      for node in nodes {
+        match node {
+          Node::Item(item_ref) => {
+            let item = unsafe { item_ref.get_unchecked() };
+            items += 1;
+
+            if let Content::Deleted(len) = &item.content {
+              status.gc_ranges += 1;
+              status.gc_clocks += len;
+              continue;
+            }
+
+            let size = Self::content_size(&item.content);
+            status.content_bytes += size;
+            if item.deleted() {
+              status.deleted_items += 1;
+              status.deleted_content_bytes += size;
+            }
+          }
+          Node::GC(_) => {
+            status.gc_ranges += 1;
+            status.gc_clocks += node.len();
+          }
+          Node::Skip(_) => {}
+        }
+      }
+      status.items_per_client.insert(*client, items);
+    }
+
+    status
   }
 
-  fn gc_delete_set(&mut self) -> JwstCodecResult<()> {
+  /// the size of the content encoded in v1 format
+  fn content_size(content: &Content) -> usize {
+    let mut encoder = RawEncoder::default();
+    match content.write(&mut encoder) {
+      Ok(_) => encoder.into_inner().len(),
+      Err(_) => 0,
+    }
+  }
+
+  /// the name of the root type the item belongs to
+  fn root_name(item: &Item) -> Option<String> {
+    let mut parent = item.parent.clone();
+    loop {
+      match parent? {
+        Parent::Type(ty) => {
+          let ty = ty.ty()?;
+          if let Some(name) = &ty.root_name {
+            return Some(name.clone());
+          }
+          parent = ty.item.get()?.parent.clone();
+        }
+        Parent::String(name) => return Some(name.to_string()),
+        Parent::Id(_) => return None,
+      }
+    }
+  }
+
+  fn gc_delete_set(&mut self, options: &GcOptions) -> JwstCodecResult<GcStats> {
+    let mut stats = GcStats::default();
     for (client, deletes) in self.delete_set.deref() {
       for range in deletes {
         let start = range.start;
@@ -864,7 +1573,7 @@ impl DocStore {
                 break;
               }
 
-              if !item.keep() {
+              if !item.keep() && !Self::gc_retains(options, item) {
                 let parent_gced = matches!(&item.parent, Some(p) if {
                     if let Parent::Type(ty) = p {
                         if let Some(ty) = ty.ty() {
@@ -876,7 +1585,20 @@ impl DocStore {
                         false
                     }
                 });
-                Self::gc_item(items, idx, parent_gced)?;
+
+                // the content collected before is only replaced by a gc struct
+                let collected = matches!(item.content, Content::Deleted(_));
+                if !collected || parent_gced {
+                  stats.items += 1;
+                  if !collected {
+                    stats.clocks += item.len();
+                    stats.bytes += Self::content_size(&item.content);
+                  }
+                }
+
+                if !options.dry_run {
+                  Self::gc_item(items, idx, parent_gced)?;
+                }
               }
             }
 
@@ -886,7 +1608,65 @@ impl DocStore {
       }
     }
 
-    Ok(())
+    Ok(stats)
+  }
+
+  /// whether the deleted item should be kept by the options
+  fn gc_retains(options: &GcOptions, item: &Item) -> bool {
+    if (options.keep_newer_than.is_some() || options.keep_snapshot.is_some())
+      && Self::gc_keeps_history(options, item)
+    {
+      return true;
+    }
+
+    if !options.roots.is_empty() {
+      return !Self::root_name(item).is_some_and(|name| options.roots.contains(&name));
+    }
+
+    false
+  }
+
+  /// whether the item or any item in its nested type is kept for the history,
+  /// the children kept are unreachable once the type is collected
+  fn gc_keeps_history(options: &GcOptions, item: &Item) -> bool {
+    if let Some(state) = &options.keep_newer_than {
+      if item.last_id().clock >= state.get(&item.id.client) {
+        return true;
+      }
+    }
+
+    if let Some(snapshot) = &options.keep_snapshot {
+      if !snapshot.visible_ranges(item).is_empty() {
+        return true;
+      }
+    }
+
+    let Content::Type(ty) = &item.content else {
+      return false;
+    };
+    let Some(ty) = ty.ty() else {
+      return false;
+    };
+
+    let mut child = ty.start.clone();
+    while let Some(item) = child.get() {
+      if Self::gc_keeps_history(options, item) {
+        return true;
+      }
+      child = item.right.clone();
+    }
+
+    // the overwritten values of the map are linked to the left of the current
+    ty.map.values().any(|item| {
+      let mut child = item.clone();
+      while let Some(item) = child.get() {
+        if Self::gc_keeps_history(options, item) {
+          return true;
+        }
+        child = item.left.clone();
+      }
+      false
+    })
   }
 
   fn gc_item(items: &mut VecDeque<Node>, idx: usize, replace: bool) -> JwstCodecResult {
@@ -941,14 +1721,18 @@ impl DocStore {
       let mut idx = nodes.len() - 1;
 
       while idx > 0 && idx >= first_change {
-        idx = idx.saturating_sub(Self::merge_with_lefts(nodes, idx) + 1);
+        idx = idx.saturating_sub(Self::merge_with_lefts(nodes, idx, &self.move_anchors) + 1);
       }
     }
 
     self.last_optimized_state = state;
   }
 
-  fn merge_with_lefts(nodes: &mut VecDeque<Node>, idx: usize) -> usize {
+  fn merge_with_lefts(
+    nodes: &mut VecDeque<Node>,
+    idx: usize,
+    move_anchors: &HashMap<Id, Vec<ItemRef>>,
+  ) -> usize {
     let mut pos = idx;
     loop {
       if pos == 0 {
@@ -958,6 +1742,13 @@ impl DocStore {
       let right = nodes.get(pos).unwrap().clone();
       let left = nodes.get_mut(pos - 1).unwrap();
 
+      // the anchors of the move ranges must stay at the item boundaries
+      if move_anchors.contains_key(&right.id())
+        || move_anchors.contains_key(&Id::new(right.client(), right.clock() - 1))
+      {
+        break;
+      }
+
       if !left.merge(right) {
         break;
       }
@@ -1192,7 +1983,7 @@ mod tests {
       store.add_node(Node::Item(Somr::new(item2))).unwrap();
       store.delete_set.add_range(1, 0..4);
 
-      store.gc_delete_set().unwrap();
+      store.gc_delete_set(&GcOptions::default()).unwrap();
 
       assert_eq!(
         &store
@@ -1222,7 +2013,7 @@ mod tests {
 
       arr.remove(0, 1).unwrap();
       let mut store = doc.store.write().unwrap();
-      store.gc_delete_set().unwrap();
+      store.gc_delete_set(&GcOptions::default()).unwrap();
 
       assert_eq!(arr.len(), 0);
       assert_eq!(
@@ -1273,7 +2064,7 @@ mod tests {
       pages.remove("page1");
 
       let mut store = doc.store.write().unwrap();
-      store.gc_delete_set().unwrap();
+      store.gc_delete_set(&GcOptions::default()).unwrap();
 
       assert_eq!(
         &store
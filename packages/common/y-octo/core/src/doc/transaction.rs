@@ -0,0 +1,777 @@
+use std::{panic::AssertUnwindSafe, sync::Arc};
+
+use log::warn;
+
+use super::{event::collect_events, store::StoreRef, *};
+use crate::sync::{
+  thread::{self, ThreadId},
+  Condvar, Mutex,
+};
+
+pub type ObserverId = u64;
+
+type EventCallback = Arc<dyn Fn(&Event) + Send + Sync>;
+type DeepEventCallback = Arc<dyn Fn(&[Event]) + Send + Sync>;
+pub(crate) type UpdateCallback = Arc<dyn Fn(&[u8], Option<&Origin>) + Send + Sync>;
+type SubdocsCallback = Arc<dyn Fn(&SubdocsEvent) + Send + Sync>;
+type ClientIdCallback = Arc<dyn Fn(&ClientIdEvent) + Send + Sync>;
+
+/// The state of the transaction in progress, kept in the store.
+#[derive(Debug)]
+pub(crate) struct TransactionState {
+  pub origin: Option<Origin>,
+  pub before_state: StateVector,
+  pub deletions: DeleteSet,
+  pub subdocs_added: Vec<Doc>,
+  pub subdocs_removed: Vec<Doc>,
+  pub subdocs_loaded: Vec<Doc>,
+  pub client_id_changed: Option<ClientIdEvent>,
+}
+
+impl TransactionState {
+  /// whether the item is created in this transaction
+  pub fn adds(&self, item: &Item) -> bool {
+    item.id.clock >= self.before_state.get(&item.id.client)
+  }
+
+  /// whether the item is deleted in this transaction
+  pub fn deletes(&self, item: &Item) -> bool {
+    self.deletions.contains(&item.id)
+  }
+}
+
+/// The key of the type the event observers are registered on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum TypeKey {
+  Root(String),
+  Item(Id),
+}
+
+#[derive(Default)]
+pub(crate) struct Observers {
+  next_id: ObserverId,
+  events: HashMap<TypeKey, Vec<(ObserverId, EventCallback)>>,
+  deep_events: HashMap<TypeKey, Vec<(ObserverId, DeepEventCallback)>>,
+  updates: Vec<(ObserverId, UpdateCallback)>,
+  subdocs: Vec<(ObserverId, SubdocsCallback)>,
+  client_ids: Vec<(ObserverId, ClientIdCallback)>,
+}
+
+impl Observers {
+  pub fn is_empty(&self) -> bool {
+    self.events.is_empty()
+      && self.deep_events.is_empty()
+      && self.updates.is_empty()
+      && self.subdocs.is_empty()
+      && self.client_ids.is_empty()
+  }
+
+  fn next_id(&mut self) -> ObserverId {
+    self.next_id += 1;
+    self.next_id
+  }
+
+  pub fn add_update(&mut self, cb: UpdateCallback) -> ObserverId {
+    let id = self.next_id();
+    self.updates.push((id, cb));
+    id
+  }
+
+  pub fn remove(&mut self, id: ObserverId) {
+    self.events.retain(|_, observers| {
+      observers.retain(|(i, _)| *i != id);
+      !observers.is_empty()
+    });
+    self.deep_events.retain(|_, observers| {
+      observers.retain(|(i, _)| *i != id);
+      !observers.is_empty()
+    });
+    self.updates.retain(|(i, _)| *i != id);
+    self.subdocs.retain(|(i, _)| *i != id);
+    self.client_ids.retain(|(i, _)| *i != id);
+  }
+
+  fn has_type_observers(&self) -> bool {
+    !self.events.is_empty() || !self.deep_events.is_empty()
+  }
+
+  /// whether the events of the type are delivered to any observer
+  pub fn observes(&self, key: &TypeKey, ty: &YTypeRef) -> bool {
+    if self.events.contains_key(key) {
+      return true;
+    }
+    if self.deep_events.is_empty() {
+      return false;
+    }
+
+    let mut parent = Some(ty.clone());
+    while let Some(ty) = parent {
+      if ty
+        .key()
+        .is_some_and(|key| self.deep_events.contains_key(&key))
+      {
+        return true;
+      }
+      parent = ty.parent_type();
+    }
+
+    false
+  }
+
+  /// drop the event observers of the deleted types
+  pub fn remove_types(&mut self, ids: Vec<Id>) {
+    if !self.has_type_observers() {
+      return;
+    }
+
+    for id in ids {
+      let key = TypeKey::Item(id);
+      self.events.remove(&key);
+      self.deep_events.remove(&key);
+    }
+  }
+}
+
+impl std::fmt::Debug for Observers {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Observers").finish()
+  }
+}
+
+/// A handle of the transaction in progress, see [Doc::transact].
+pub struct Transaction<'doc> {
+  doc: &'doc Doc,
+  origin: Origin,
+}
+
+impl<'doc> Transaction<'doc> {
+  pub fn doc(&self) -> &'doc Doc {
+    self.doc
+  }
+
+  pub fn origin(&self) -> &Origin {
+    &self.origin
+  }
+}
+
+impl Doc {
+  /// Batch the changes made in `f` into a single transaction tagged with the
+  /// given [Origin].
+  ///
+  /// The observers are notified once the transaction is committed, with all
+  /// the changes in it. Transactions started in `f` are merged into this one.
+  ///
+  /// ```
+  /// use y_octo::Doc;
+  ///
+  /// let doc = Doc::new();
+  /// let mut text = doc.get_or_create_text("text").unwrap();
+  ///
+  /// doc.transact("typing", |_| {
+  ///   text.insert(0, "hello").unwrap();
+  ///   text.insert(5, " world").unwrap();
+  /// });
+  /// assert_eq!(text.to_string(), "hello world");
+  /// ```
+  pub fn transact<T, O: Into<Origin>>(&self, origin: O, f: impl FnOnce(&Transaction) -> T) -> T {
+    let origin = origin.into();
+    transact(&self.store, Some(origin.clone()), || {
+      f(&Transaction { doc: self, origin })
+    })
+  }
+
+  /// Subscribe the updates of the committed transactions, the update is
+  /// encoded in v1 format.
+  pub fn observe_update(
+    &self,
+    cb: impl Fn(&[u8], Option<&Origin>) + Send + Sync + 'static,
+  ) -> ObserverId {
+    self
+      .store
+      .write()
+      .unwrap()
+      .observers
+      .add_update(Arc::new(cb))
+  }
+
+  /// Subscribe the subdocs added, removed or requested to load in the
+  /// committed transactions.
+  pub fn observe_subdocs(&self, cb: impl Fn(&SubdocsEvent) + Send + Sync + 'static) -> ObserverId {
+    let mut store = self.store.write().unwrap();
+    let id = store.observers.next_id();
+    store.observers.subdocs.push((id, Arc::new(cb)));
+    id
+  }
+
+  /// Subscribe the changes of the local client id, which is replaced once a
+  /// remote update turns out to be using it.
+  pub fn observe_client_id(
+    &self,
+    cb: impl Fn(&ClientIdEvent) + Send + Sync + 'static,
+  ) -> ObserverId {
+    let mut store = self.store.write().unwrap();
+    let id = store.observers.next_id();
+    store.observers.client_ids.push((id, Arc::new(cb)));
+    id
+  }
+
+  pub fn unobserve(&self, id: ObserverId) {
+    self.store.write().unwrap().observers.remove(id);
+  }
+}
+
+impl YTypeRef {
+  /// run `f` in a transaction of the store this type belongs to
+  pub fn transact<T>(&self, f: impl FnOnce() -> T) -> T {
+    match self.store.upgrade() {
+      Some(store) => transact(&store, None, f),
+      None => f(),
+    }
+  }
+
+  pub(crate) fn key(&self) -> Option<TypeKey> {
+    let ty = self.ty()?;
+    match (ty.item.get(), &ty.root_name) {
+      (Some(item), _) => Some(TypeKey::Item(item.id)),
+      (None, Some(name)) => Some(TypeKey::Root(name.clone())),
+      (None, None) => None,
+    }
+  }
+
+  fn parent_type(&self) -> Option<YTypeRef> {
+    let ty = self.ty()?;
+    match ty.item.get().and_then(|item| item.parent.as_ref()) {
+      Some(Parent::Type(parent)) => Some(parent.clone()),
+      _ => None,
+    }
+  }
+
+  pub fn observe(&self, cb: impl Fn(&Event) + Send + Sync + 'static) -> ObserverId {
+    let (Some(key), Some(mut store)) = (self.key(), self.store_mut()) else {
+      return ObserverId::default();
+    };
+
+    let id = store.observers.next_id();
+    store
+      .observers
+      .events
+      .entry(key)
+      .or_default()
+      .push((id, Arc::new(cb)));
+    id
+  }
+
+  pub fn observe_deep(&self, cb: impl Fn(&[Event]) + Send + Sync + 'static) -> ObserverId {
+    let (Some(key), Some(mut store)) = (self.key(), self.store_mut()) else {
+      return ObserverId::default();
+    };
+
+    let id = store.observers.next_id();
+    store
+      .observers
+      .deep_events
+      .entry(key)
+      .or_default()
+      .push((id, Arc::new(cb)));
+    id
+  }
+
+  pub fn unobserve(&self, id: ObserverId) {
+    if let Some(mut store) = self.store_mut() {
+      store.observers.remove(id);
+    }
+  }
+}
+
+/// Run `f` in a transaction, or join the transaction in progress on the
+/// current thread. Other threads wait for the transaction to be committed
+/// before starting their own.
+pub(crate) fn transact<T>(store: &StoreRef, origin: Option<Origin>, f: impl FnOnce() -> T) -> T {
+  let _guard = TransactionGuard::begin(store, origin);
+  f()
+}
+
+/// The lock serializing the transactions of a store across threads, the
+/// thread holding it can re-enter it.
+pub(crate) struct TransactionLock {
+  owner: Mutex<Option<ThreadId>>,
+  released: Condvar,
+}
+
+impl Default for TransactionLock {
+  fn default() -> Self {
+    Self {
+      owner: Mutex::new(None),
+      released: Condvar::new(),
+    }
+  }
+}
+
+impl std::fmt::Debug for TransactionLock {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("TransactionLock").finish()
+  }
+}
+
+impl TransactionLock {
+  /// wait for the transaction of other threads to finish, returns whether the
+  /// lock is newly taken by the current thread
+  fn acquire(&self) -> bool {
+    let current = thread::current().id();
+    let mut owner = self.owner.lock().unwrap();
+    loop {
+      match *owner {
+        Some(thread) if thread == current => return false,
+        Some(_) => owner = self.released.wait(owner).unwrap(),
+        None => {
+          *owner = Some(current);
+          return true;
+        }
+      }
+    }
+  }
+
+  /// release the lock if it's held by the current thread
+  fn release(&self) {
+    let mut owner = self.owner.lock().unwrap();
+    if *owner == Some(thread::current().id()) {
+      *owner = None;
+      self.released.notify_one();
+    }
+  }
+}
+
+struct TransactionGuard<'a> {
+  store: &'a StoreRef,
+  lock: Arc<TransactionLock>,
+  outermost: bool,
+}
+
+impl<'a> TransactionGuard<'a> {
+  fn begin(store: &'a StoreRef, origin: Option<Origin>) -> Self {
+    let lock = store.read().unwrap().txn_lock.clone();
+    let outermost = lock.acquire();
+    if outermost {
+      let mut guard = store.write().unwrap();
+      guard.change_batch += 1;
+      guard.txn = Some(TransactionState {
+        origin,
+        before_state: guard.get_state_vector(),
+        deletions: DeleteSet::default(),
+        subdocs_added: Vec::new(),
+        subdocs_removed: Vec::new(),
+        subdocs_loaded: Vec::new(),
+        client_id_changed: None,
+      });
+    }
+
+    Self {
+      store,
+      lock,
+      outermost,
+    }
+  }
+}
+
+impl Drop for TransactionGuard<'_> {
+  fn drop(&mut self) {
+    if !self.outermost {
+      return;
+    }
+
+    if std::thread::panicking() {
+      // the changes made before the panic are kept in the store, the observers
+      // still need them to stay in sync
+      let commit = std::panic::catch_unwind(AssertUnwindSafe(|| commit(self.store, &self.lock)));
+      if let Err(e) = commit {
+        warn!("Failed to commit transaction: {:?}", e);
+        self.lock.release();
+      }
+      return;
+    }
+
+    commit(self.store, &self.lock);
+  }
+}
+
+fn commit(store: &StoreRef, lock: &TransactionLock) {
+  let Ok(mut guard) = store.write() else {
+    // poisoned by a panic while the store is being modified
+    lock.release();
+    return;
+  };
+  let txn = guard.txn.take();
+  // the observers run out of the transaction
+  lock.release();
+  let Some(txn) = txn else {
+    return;
+  };
+  if guard.observers.is_empty() {
+    return;
+  }
+
+  // the deltas are built by walking the types, skip them if nobody listens
+  let events = if guard.observers.has_type_observers() {
+    collect_events(&guard, &txn)
+  } else {
+    Vec::new()
+  };
+
+  let mut shallow = Vec::new();
+  let mut deep: Vec<(TypeKey, Vec<Event>)> = Vec::new();
+  let mut deep_index: HashMap<TypeKey, usize> = HashMap::new();
+  for (key, ty, event) in events {
+    if let Some(observers) = guard.observers.events.get(&key) {
+      for (_, cb) in observers {
+        shallow.push((cb.clone(), event.clone()));
+      }
+    }
+
+    // deliver the event to the observers of the type and all its ancestors
+    let mut parent = Some(ty);
+    while let Some(ty) = parent {
+      if let Some(key) = ty
+        .key()
+        .filter(|key| guard.observers.deep_events.contains_key(key))
+      {
+        match deep_index.get(&key) {
+          Some(&idx) => deep[idx].1.push(event.clone()),
+          None => {
+            deep_index.insert(key.clone(), deep.len());
+            deep.push((key, vec![event.clone()]));
+          }
+        }
+      }
+
+      parent = ty.parent_type();
+    }
+  }
+
+  let deep = deep
+    .into_iter()
+    .flat_map(|(key, events)| {
+      guard.observers.deep_events[&key]
+        .iter()
+        .map(|(_, cb)| (cb.clone(), events.clone()))
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+
+  let updates = if guard.observers.updates.is_empty() {
+    None
+  } else {
+    encode_transaction_update(&guard, &txn).map(|update| {
+      let observers = guard
+        .observers
+        .updates
+        .iter()
+        .map(|(_, cb)| cb.clone())
+        .collect::<Vec<_>>();
+      (update, observers)
+    })
+  };
+
+  let subdocs = SubdocsEvent::new(&txn);
+  let subdocs = (!subdocs.is_empty()).then(|| {
+    let observers = guard
+      .observers
+      .subdocs
+      .iter()
+      .map(|(_, cb)| cb.clone())
+      .collect::<Vec<_>>();
+    (subdocs, observers)
+  });
+
+  let client_id = txn.client_id_changed.map(|event| {
+    let observers = guard
+      .observers
+      .client_ids
+      .iter()
+      .map(|(_, cb)| cb.clone())
+      .collect::<Vec<_>>();
+    (event, observers)
+  });
+
+  // observers may read or modify the doc
+  drop(guard);
+
+  for (cb, event) in shallow {
+    call_observer(|| cb(&event));
+  }
+  for (cb, events) in deep {
+    call_observer(|| cb(&events));
+  }
+  if let Some((update, observers)) = updates {
+    for cb in observers {
+      call_observer(|| cb(&update, txn.origin.as_ref()));
+    }
+  }
+  if let Some((event, observers)) = subdocs {
+    for cb in observers {
+      call_observer(|| cb(&event));
+    }
+  }
+  if let Some((event, observers)) = client_id {
+    for cb in observers {
+      call_observer(|| cb(&event));
+    }
+  }
+}
+
+fn encode_transaction_update(store: &DocStore, txn: &TransactionState) -> Option<Vec<u8>> {
+  let mut update = match store.diff_state_vector(&txn.before_state, false) {
+    Ok(update) => update,
+    Err(e) => {
+      warn!("Failed to diff document: {}", e);
+      return None;
+    }
+  };
+  update.delete_set = txn.deletions.clone();
+
+  if update.is_empty() {
+    return None;
+  }
+
+  let mut encoder = RawEncoder::default();
+  if let Err(e) = update.write(&mut encoder) {
+    warn!("Failed to encode document: {}", e);
+    return None;
+  }
+
+  Some(encoder.into_inner())
+}
+
+fn call_observer(f: impl FnOnce()) {
+  // catch panic if callback throw
+  std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| {
+    warn!("Failed to call observer: {:?}", e);
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_transact_batches_update() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+
+      let updates = Arc::new(Mutex::new(Vec::new()));
+      let updates_clone = updates.clone();
+      doc.observe_update(move |update, origin| {
+        updates_clone
+          .lock()
+          .unwrap()
+          .push((update.to_vec(), origin.cloned()));
+      });
+
+      doc.transact("typing", |txn| {
+        assert_eq!(txn.origin().as_str(), "typing");
+        text.insert(0, "hello").unwrap();
+        text.insert(5, " world").unwrap();
+        text.remove(0, 1).unwrap();
+        // nested transactions are merged into the outer one
+        txn
+          .doc()
+          .transact("nested", |_| text.insert(0, "H").unwrap());
+      });
+      text.insert(11, "!").unwrap();
+
+      let updates = std::mem::take(&mut *updates.lock().unwrap());
+      assert_eq!(updates.len(), 2);
+      assert_eq!(updates[0].1, Some(Origin::from("typing")));
+      assert_eq!(updates[1].1, None);
+
+      let mut new_doc = Doc::new();
+      for (update, _) in updates {
+        new_doc.apply_update_from_binary_v1(update).unwrap();
+      }
+      assert_eq!(
+        new_doc.get_or_create_text("text").unwrap().to_string(),
+        "Hello world!"
+      );
+    });
+  }
+
+  #[test]
+  #[cfg(not(loom))]
+  fn test_transact_commits_on_panic() {
+    let doc = Doc::new();
+    let mut text = doc.get_or_create_text("text").unwrap();
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let updates_clone = updates.clone();
+    doc.observe_update(move |update, origin| {
+      updates_clone
+        .lock()
+        .unwrap()
+        .push((update.to_vec(), origin.cloned()));
+    });
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+      doc.transact("typing", |_| {
+        text.insert(0, "hello").unwrap();
+        panic!("failed in transaction");
+      })
+    }));
+    assert!(result.is_err());
+
+    let committed = std::mem::take(&mut *updates.lock().unwrap());
+    assert_eq!(committed.len(), 1);
+    assert_eq!(committed[0].1, Some(Origin::from("typing")));
+    let mut new_doc = Doc::new();
+    new_doc
+      .apply_update_from_binary_v1(committed[0].0.clone())
+      .unwrap();
+    assert_eq!(
+      new_doc.get_or_create_text("text").unwrap().to_string(),
+      "hello"
+    );
+
+    // the next transaction is not blocked by the failed one
+    text.insert(5, "!").unwrap();
+    assert_eq!(updates.lock().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn test_unobserve() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut map = doc.get_or_create_map("map").unwrap();
+
+      let count = Arc::new(Mutex::new(0));
+      let count_clone = count.clone();
+      let id = doc.observe_update(move |_, _| *count_clone.lock().unwrap() += 1);
+
+      map.insert("a".into(), 1).unwrap();
+      doc.unobserve(id);
+      map.insert("b".into(), 2).unwrap();
+
+      assert_eq!(*count.lock().unwrap(), 1);
+    });
+  }
+
+  #[test]
+  fn test_observer_can_access_doc() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+
+      let content = Arc::new(Mutex::new(String::new()));
+      let content_clone = content.clone();
+      text.observe(move |event| {
+        *content_clone.lock().unwrap() = event.target().to_string();
+      });
+
+      text.insert(0, "hello").unwrap();
+      assert_eq!(*content.lock().unwrap(), "hello");
+    });
+  }
+
+  #[test]
+  fn test_observers_of_deleted_types() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      let sub_map = doc.create_map().unwrap();
+      map.insert("sub".to_string(), sub_map).unwrap();
+
+      let count = Arc::new(Mutex::new(0));
+      let count_clone = count.clone();
+      // the observers are kept by the type, not the handle
+      map
+        .get("sub")
+        .unwrap()
+        .to_map()
+        .unwrap()
+        .observe(move |_| *count_clone.lock().unwrap() += 1);
+      let count_clone = count.clone();
+      doc
+        .get_or_create_map("map")
+        .unwrap()
+        .observe_deep(move |_| *count_clone.lock().unwrap() += 1);
+
+      map
+        .get("sub")
+        .unwrap()
+        .to_map()
+        .unwrap()
+        .insert("key".to_string(), "value")
+        .unwrap();
+      assert_eq!(*count.lock().unwrap(), 2);
+
+      map.remove("sub");
+      {
+        let store = doc.store.read().unwrap();
+        assert_eq!(store.observers.events.len(), 0);
+        assert_eq!(store.observers.deep_events.len(), 1);
+      }
+      assert_eq!(*count.lock().unwrap(), 3);
+
+      let new_map = doc.create_map().unwrap();
+      map.insert("sub".to_string(), new_map).unwrap();
+      map
+        .get("sub")
+        .unwrap()
+        .to_map()
+        .unwrap()
+        .insert("key".to_string(), "value")
+        .unwrap();
+      assert_eq!(*count.lock().unwrap(), 5);
+    });
+  }
+
+  #[test]
+  #[cfg(not(loom))]
+  #[cfg_attr(miri, ignore)]
+  fn test_transact_excludes_other_threads() {
+    use std::{sync::mpsc::channel, time::Duration};
+
+    let doc = Doc::new();
+    let mut text = doc.get_or_create_text("text").unwrap();
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let updates_clone = updates.clone();
+    doc.observe_update(move |update, origin| {
+      updates_clone
+        .lock()
+        .unwrap()
+        .push((update.to_vec(), origin.cloned()));
+    });
+
+    let (tx, rx) = channel();
+    let handle = {
+      let mut text = text.clone();
+      std::thread::spawn(move || {
+        rx.recv().unwrap();
+        text.insert(0, "b").unwrap();
+      })
+    };
+
+    doc.transact("a", |_| {
+      text.insert(0, "a").unwrap();
+      tx.send(()).unwrap();
+      // the other thread waits for this transaction to be committed
+      std::thread::sleep(Duration::from_millis(20));
+      assert_eq!(text.to_string(), "a");
+    });
+    handle.join().unwrap();
+    assert_eq!(text.to_string(), "ba");
+
+    let updates = std::mem::take(&mut *updates.lock().unwrap());
+    assert_eq!(updates.len(), 2);
+    assert_eq!(updates[0].1, Some(Origin::from("a")));
+    assert_eq!(updates[1].1, None);
+
+    let mut new_doc = Doc::new();
+    new_doc
+      .apply_update_from_binary_v1(updates[0].0.clone())
+      .unwrap();
+    assert_eq!(new_doc.get_or_create_text("text").unwrap().to_string(), "a");
+  }
+}
@@ -4,16 +4,31 @@ impl_type!(Array);
 
 impl ListType for Array {}
 
-pub struct ArrayIter<'a>(ListIterator<'a>);
+pub struct ArrayIter<'a> {
+  items: ListIterator<'a>,
+  /// the rest values of an item that holds multiple values
+  values: std::vec::IntoIter<Any>,
+}
 
 impl Iterator for ArrayIter<'_> {
   type Item = Value;
 
   fn next(&mut self) -> Option<Self::Item> {
-    for item in self.0.by_ref() {
+    if let Some(value) = self.values.next() {
+      return Some(Value::Any(value));
+    }
+
+    for item in self.items.by_ref() {
       if let Some(item) = item.get() {
         if item.countable() {
-          return Some(Value::from(&item.content));
+          if let Content::Any(any) = &item.content {
+            self.values = any.clone().into_iter();
+            if let Some(value) = self.values.next() {
+              return Some(Value::Any(value));
+            }
+          } else {
+            return Some(Value::from(&item.content));
+          }
         }
       }
     }
@@ -48,7 +63,24 @@ impl Array {
   }
 
   pub fn iter(&self) -> ArrayIter {
-    ArrayIter(self.iter_item())
+    ArrayIter {
+      items: self.iter_item(),
+      values: Vec::new().into_iter(),
+    }
+  }
+
+  /// The values of the array as of the given [Snapshot].
+  pub fn to_vec_at(&self, snapshot: &Snapshot) -> Vec<Value> {
+    let mut values = Vec::new();
+    for content in self.contents_at(snapshot) {
+      match content {
+        Content::Any(any) => values.extend(any.into_iter().map(Value::Any)),
+        content if content.countable() => values.push(Value::from(&content)),
+        _ => {}
+      }
+    }
+
+    values
   }
 
   pub fn push<V: Into<Value>>(&mut self, val: V) -> JwstCodecResult {
@@ -59,9 +91,198 @@ impl Array {
     self.insert_at(idx, val.into().into())
   }
 
+  pub fn push_json(&mut self, value: serde_json::Value) -> JwstCodecResult {
+    self.insert_json(self.len(), value)
+  }
+
+  /// Insert a JSON value, the objects and arrays are inserted as nested
+  /// [Map]s and [Array]s, the other values as [Any].
+  pub fn insert_json(&mut self, idx: u64, value: serde_json::Value) -> JwstCodecResult {
+    self.0.clone().transact(|| {
+      match value {
+        serde_json::Value::Object(entries) => {
+          self.insert(idx, self.0.create_type::<Map>(YTypeKind::Map)?)?;
+          let mut map = self
+            .get(idx)
+            .and_then(|value| value.to_map())
+            .ok_or(JwstCodecError::Unexpected)?;
+          for (key, value) in entries {
+            map.insert_json(key, value)?;
+          }
+        }
+        serde_json::Value::Array(values) => {
+          self.insert(idx, self.0.create_type::<Array>(YTypeKind::Array)?)?;
+          let mut array = self
+            .get(idx)
+            .and_then(|value| value.to_array())
+            .ok_or(JwstCodecError::Unexpected)?;
+          for value in values {
+            array.push_json(value)?;
+          }
+        }
+        value => self.insert(idx, Any::from(value))?,
+      }
+
+      Ok(())
+    })
+  }
+
   pub fn remove(&mut self, idx: u64, len: u64) -> JwstCodecResult {
     self.remove_at(idx, len)
   }
+
+  /// Move the element at `from` to the position `to`, both are the indexes
+  /// before the move. Unlike removing and inserting the element again, the
+  /// element keeps its identity and concurrent moves of it don't duplicate it,
+  /// compatible with the move of yjs.
+  ///
+  /// ```
+  /// use y_octo::Doc;
+  ///
+  /// let doc = Doc::new();
+  /// let mut array = doc.get_or_create_array("array").unwrap();
+  /// for i in 0..4 {
+  ///   array.push(i).unwrap();
+  /// }
+  ///
+  /// array.move_to(0, 3).unwrap();
+  /// let values = array.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+  /// assert_eq!(values, ["1", "2", "0", "3"]);
+  /// ```
+  pub fn move_to(&mut self, from: u64, to: u64) -> JwstCodecResult {
+    if to > self.len() {
+      return Err(JwstCodecError::IndexOutOfBound(to));
+    }
+    let (id, _) = self.element(from)?;
+    if from == to || from + 1 == to {
+      return Ok(());
+    }
+
+    self.insert_at(
+      to,
+      Content::Move(Box::new(Move::new(id, Assoc::After, id, Assoc::Before))),
+    )
+  }
+
+  /// Move the elements in `start..=end` to the position `to`, the indexes are
+  /// the ones before the move.
+  ///
+  /// The elements inserted concurrently at the edges of the range are moved
+  /// as well if `assoc_start` is [Assoc::Before] or `assoc_end` is
+  /// [Assoc::After].
+  ///
+  /// Fails with [JwstCodecError::InvalidMoveRange] if the edges of the range
+  /// are shown by different moves, e.g. only a part of the range has been
+  /// moved there before.
+  pub fn move_range_to(
+    &mut self,
+    start: u64,
+    assoc_start: Assoc,
+    end: u64,
+    assoc_end: Assoc,
+    to: u64,
+  ) -> JwstCodecResult {
+    if start > end {
+      return Err(JwstCodecError::IndexOutOfBound(start));
+    }
+    if to > self.len() {
+      return Err(JwstCodecError::IndexOutOfBound(to));
+    }
+
+    let (start_id, start_moved) = match assoc_start {
+      Assoc::After => self.element(start)?,
+      Assoc::Before => match start.checked_sub(1) {
+        Some(index) => self.element(index)?,
+        None => return Err(JwstCodecError::IndexOutOfBound(start)),
+      },
+    };
+    let (end_id, end_moved) = match assoc_end {
+      Assoc::After => self.element(end + 1)?,
+      Assoc::Before => self.element(end)?,
+    };
+    // the range is walked in the order the items are stored, it only matches the
+    // shown order when the whole range is shown by the same move
+    if start_moved != end_moved {
+      return Err(JwstCodecError::InvalidMoveRange { start, end });
+    }
+    if start <= to && to <= end + 1 {
+      return Ok(());
+    }
+
+    self.insert_at(
+      to,
+      Content::Move(Box::new(Move::new(
+        start_id,
+        assoc_start,
+        end_id,
+        assoc_end,
+      ))),
+    )
+  }
+
+  /// the id of the element at the index, along with the move showing it
+  fn element(&self, index: u64) -> JwstCodecResult<(Id, Option<Id>)> {
+    self
+      .get_item_at(index)
+      .and_then(|(item, offset)| {
+        item
+          .get()
+          .map(|item| (Id::new(item.id.client, item.id.clock + offset), item.moved))
+      })
+      .ok_or(JwstCodecError::IndexOutOfBound(index))
+  }
+
+  /// Create a position at the index that survives concurrent edits.
+  pub fn sticky_index(&self, index: u64, assoc: Assoc) -> Option<StickyIndex> {
+    StickyIndex::from_type(&self.0, index, assoc)
+  }
+
+  /// The values of the array including the deleted ones, split by the users
+  /// inserted and deleted them, see [PermanentUserData].
+  pub fn attributions(&self, users: &PermanentUserData) -> Vec<Attribution<Vec<Value>>> {
+    let index = users.index();
+    let mut attributions = Vec::new();
+    let Some(inner) = self.0.ty() else {
+      return attributions;
+    };
+
+    let mut item_ref = inner.start.clone();
+    while let Some(item) = item_ref.get() {
+      let values = match &item.content {
+        Content::Any(any) => any.iter().cloned().map(Value::Any).collect(),
+        content if content.countable() => vec![Value::from(content)],
+        _ => Vec::new(),
+      };
+
+      if !values.is_empty() {
+        index
+          .attribute(item, values)
+          .push_to(&mut attributions, |last, values| last.extend(values));
+      }
+      item_ref = item.right.clone();
+    }
+
+    attributions
+  }
+
+  /// Subscribe the changes made to the array in each transaction.
+  pub fn observe(&self, cb: impl Fn(&ArrayEvent) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe(move |event| {
+      if let Event::Array(event) = event {
+        cb(event)
+      }
+    })
+  }
+
+  /// Subscribe the changes made to the array and all its nested types in
+  /// each transaction.
+  pub fn observe_deep(&self, cb: impl Fn(&[Event]) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe_deep(cb)
+  }
+
+  pub fn unobserve(&self, id: ObserverId) {
+    self.0.unobserve(id)
+  }
 }
 
 impl serde::Serialize for Array {
@@ -202,6 +423,7 @@ mod tests {
           guid: String::from("1"),
           client_id: 1,
           gc: true,
+          ..Default::default()
         },
       )
       .unwrap();
@@ -213,4 +435,301 @@ mod tests {
       )
     });
   }
+
+  fn values(array: &Array) -> Vec<String> {
+    (0..array.len())
+      .filter_map(|i| array.get(i))
+      .map(|v| v.to_string())
+      .collect()
+  }
+
+  fn yrs_values(doc: &yrs::Doc, name: &str) -> Vec<String> {
+    use yrs::{Array, Transact};
+
+    let array = doc.get_or_insert_array(name);
+    let txn = doc.transact();
+    array.iter(&txn).map(|v| v.to_string(&txn)).collect()
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_array_iter_multi_values() {
+    use yrs::{Array as _, ReadTxn, Transact};
+
+    loom_model!({
+      // yjs inserts a range of values as one item
+      let ydoc = yrs::Doc::with_client_id(1);
+      let yarray = ydoc.get_or_insert_array("array");
+      yarray.insert_range(&mut ydoc.transact_mut(), 0, [0, 1, 2]);
+      yarray.push_back(&mut ydoc.transact_mut(), 3);
+      let update = ydoc
+        .transact()
+        .encode_state_as_update_v1(&Default::default());
+
+      let doc = Doc::try_from_binary_v1(update).unwrap();
+      let array = doc.get_or_create_array("array").unwrap();
+      let values = array.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+      assert_eq!(values, ["0", "1", "2", "3"]);
+      assert_eq!(values, yrs_values(&ydoc, "array"));
+      assert_eq!(array.iter().count() as u64, array.len());
+    });
+  }
+
+  #[test]
+  fn test_array_move() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut array = doc.get_or_create_array("array").unwrap();
+      for i in 0..6 {
+        array.push(i).unwrap();
+      }
+
+      array.move_to(0, 3).unwrap();
+      assert_eq!(values(&array), ["1", "2", "0", "3", "4", "5"]);
+      assert_eq!(array.len(), 6);
+      assert_eq!(array.get(2).unwrap().to_string(), "0");
+
+      array
+        .move_range_to(0, Assoc::After, 1, Assoc::Before, 6)
+        .unwrap();
+      assert_eq!(values(&array), ["0", "3", "4", "5", "1", "2"]);
+
+      // moving an element into its own position is a no-op
+      array.move_to(1, 2).unwrap();
+      array
+        .move_range_to(1, Assoc::After, 3, Assoc::Before, 2)
+        .unwrap();
+      assert_eq!(values(&array), ["0", "3", "4", "5", "1", "2"]);
+
+      // edits work on the moved elements
+      array.insert(5, 6).unwrap();
+      array.insert(6, 7).unwrap();
+      array.remove(0, 1).unwrap();
+      array.push(8).unwrap();
+      assert_eq!(values(&array), ["3", "4", "5", "1", "6", "7", "2", "8"]);
+
+      assert_eq!(array.move_to(8, 0), Err(JwstCodecError::IndexOutOfBound(8)));
+      assert_eq!(
+        array.move_range_to(0, Assoc::After, 7, Assoc::After, 0),
+        Err(JwstCodecError::IndexOutOfBound(8))
+      );
+
+      let doc = Doc::try_from_binary_v1(doc.encode_update_v1().unwrap()).unwrap();
+      let array = doc.get_or_create_array("array").unwrap();
+      assert_eq!(values(&array), ["3", "4", "5", "1", "6", "7", "2", "8"]);
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_array_move_yrs_compatibility() {
+    use yrs::{updates::decoder::Decode, Array as _, ReadTxn, Transact};
+
+    loom_model!({
+      let ydoc = yrs::Doc::with_client_id(1);
+      let yarray = ydoc.get_or_insert_array("array");
+      yarray.insert_range(&mut ydoc.transact_mut(), 0, [0, 1, 2, 3, 4, 5]);
+      yarray.move_to(&mut ydoc.transact_mut(), 0, 3);
+      yarray.move_range_to(
+        &mut ydoc.transact_mut(),
+        3,
+        yrs::Assoc::After,
+        4,
+        yrs::Assoc::Before,
+        0,
+      );
+      let update = ydoc
+        .transact()
+        .encode_state_as_update_v1(&Default::default());
+
+      let mut doc = Doc::with_client(2);
+      doc.apply_update_from_binary_v1(&update).unwrap();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      assert_eq!(values(&array), yrs_values(&ydoc, "array"));
+
+      array.move_to(5, 0).unwrap();
+      array
+        .move_range_to(1, Assoc::After, 2, Assoc::Before, 6)
+        .unwrap();
+      let update = doc.encode_update_v1().unwrap();
+      ydoc
+        .transact_mut()
+        .apply_update(yrs::Update::decode_v1(&update).unwrap())
+        .unwrap();
+      assert_eq!(values(&array), yrs_values(&ydoc, "array"));
+
+      let update = doc.encode_update_v2().unwrap();
+      let ydoc = yrs::Doc::new();
+      ydoc
+        .transact_mut()
+        .apply_update(yrs::Update::decode_v2(&update).unwrap())
+        .unwrap();
+      assert_eq!(values(&array), yrs_values(&ydoc, "array"));
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_array_concurrent_move() {
+    use yrs::{updates::decoder::Decode, Transact};
+
+    loom_model!({
+      let mut doc1 = Doc::with_client(1);
+      let mut array1 = doc1.get_or_create_array("array").unwrap();
+      for i in 0..5 {
+        array1.push(i).unwrap();
+      }
+      let mut doc2 = Doc::with_client(2);
+      doc2
+        .apply_update_from_binary_v1(doc1.encode_update_v1().unwrap())
+        .unwrap();
+      let mut array2 = doc2.get_or_create_array("array").unwrap();
+      let sv = doc1.get_state_vector();
+
+      // move the same element to different places at the same time
+      array1.move_to(0, 5).unwrap();
+      array2.move_to(0, 2).unwrap();
+      array2.insert(4, 5).unwrap();
+      let update1 = doc1.encode_state_as_update_v1(&sv).unwrap();
+      let update2 = doc2.encode_state_as_update_v1(&sv).unwrap();
+
+      doc1.apply_update_from_binary_v1(&update2).unwrap();
+      doc2.apply_update_from_binary_v1(&update1).unwrap();
+      assert_eq!(values(&array1), values(&array2));
+      assert_eq!(array1.len(), 6);
+      assert_eq!(values(&array1).iter().filter(|v| *v == "0").count(), 1);
+      // the move of doc2 wins by its client id, the local move of doc1 has
+      // nothing left to move once it arrives and is deleted with its anchor
+      let store = doc1.store.read().unwrap();
+      assert_eq!(store.move_anchors.values().map(Vec::len).sum::<usize>(), 1);
+      drop(store);
+
+      let ydoc = yrs::Doc::new();
+      ydoc
+        .transact_mut()
+        .apply_update(yrs::Update::decode_v1(&doc1.encode_update_v1().unwrap()).unwrap())
+        .unwrap();
+      assert_eq!(values(&array1), yrs_values(&ydoc, "array"));
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_array_cyclic_and_nested_moves_yrs_compatibility() {
+    use yrs::{updates::decoder::Decode, Array as _, ReadTxn, StateVector, Transact};
+
+    fn sync(from: &yrs::Doc, to: &yrs::Doc) {
+      let update = from
+        .transact()
+        .encode_state_as_update_v1(&to.transact().state_vector());
+      to.transact_mut()
+        .apply_update(yrs::Update::decode_v1(&update).unwrap())
+        .unwrap();
+    }
+
+    // the updates applied in the same order give the same result
+    fn assert_same(updates: &[&Vec<u8>]) {
+      let ydoc = yrs::Doc::new();
+      let mut doc = Doc::default();
+      for update in updates {
+        ydoc
+          .transact_mut()
+          .apply_update(yrs::Update::decode_v1(update).unwrap())
+          .unwrap();
+        doc.apply_update_from_binary_v1(update).unwrap();
+      }
+      let array = doc.get_or_create_array("array").unwrap();
+      assert_eq!(values(&array), yrs_values(&ydoc, "array"));
+    }
+
+    loom_model!({
+      let ydoc1 = yrs::Doc::with_client_id(1);
+      let yarray1 = ydoc1.get_or_insert_array("array");
+      yarray1.insert_range(&mut ydoc1.transact_mut(), 0, [0, 1, 2, 3, 4, 5]);
+      let ydoc2 = yrs::Doc::with_client_id(2);
+      let yarray2 = ydoc2.get_or_insert_array("array");
+      sync(&ydoc1, &ydoc2);
+      let sv = ydoc1.transact().state_vector();
+      let base = ydoc1
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+
+      // each range is moved into the other one at the same time, the move that
+      // would move itself is deleted
+      let assoc = yrs::Assoc::After;
+      yarray1.move_range_to(&mut ydoc1.transact_mut(), 0, assoc, 1, assoc, 4);
+      yarray2.move_range_to(&mut ydoc2.transact_mut(), 3, assoc, 4, assoc, 1);
+      let update1 = ydoc1.transact().encode_state_as_update_v1(&sv);
+      let update2 = ydoc2.transact().encode_state_as_update_v1(&sv);
+      assert_same(&[&base, &update1, &update2]);
+      assert_same(&[&base, &update2, &update1]);
+
+      // move ranges with the moved elements in them
+      sync(&ydoc2, &ydoc1);
+      yarray1.move_to(&mut ydoc1.transact_mut(), 0, 3);
+      yarray1.move_range_to(&mut ydoc1.transact_mut(), 1, assoc, 4, assoc, 0);
+      yarray1.move_range_to(&mut ydoc1.transact_mut(), 0, assoc, 2, assoc, 6);
+      let update = ydoc1
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+      assert_same(&[&update]);
+
+      // and the other way around
+      let mut doc = Doc::with_client(3);
+      doc.apply_update_from_binary_v1(&update).unwrap();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      array.move_to(2, 0).unwrap();
+      array.move_to(5, 1).unwrap();
+      // the elements shown by the moves above are moved again
+      array
+        .move_range_to(0, Assoc::After, 0, Assoc::Before, 4)
+        .unwrap();
+      ydoc1
+        .transact_mut()
+        .apply_update(yrs::Update::decode_v1(&doc.encode_update_v1().unwrap()).unwrap())
+        .unwrap();
+      assert_eq!(values(&array), yrs_values(&ydoc1, "array"));
+      assert_same(&[&doc.encode_update_v1().unwrap()]);
+    });
+  }
+
+  #[test]
+  fn test_array_concurrent_range_move() {
+    loom_model!({
+      let mut doc1 = Doc::with_client(1);
+      let mut array1 = doc1.get_or_create_array("array").unwrap();
+      for i in 0..6 {
+        array1.push(i).unwrap();
+      }
+      let mut doc2 = Doc::with_client(2);
+      doc2
+        .apply_update_from_binary_v1(doc1.encode_update_v1().unwrap())
+        .unwrap();
+      let mut array2 = doc2.get_or_create_array("array").unwrap();
+      let sv = doc1.get_state_vector();
+
+      // overlapping ranges moved to different places at the same time
+      array1
+        .move_range_to(0, Assoc::After, 2, Assoc::After, 6)
+        .unwrap();
+      array2
+        .move_range_to(2, Assoc::After, 4, Assoc::After, 0)
+        .unwrap();
+      array2.insert(1, 6).unwrap();
+      let update1 = doc1.encode_state_as_update_v1(&sv).unwrap();
+      let update2 = doc2.encode_state_as_update_v1(&sv).unwrap();
+
+      doc1.apply_update_from_binary_v1(&update2).unwrap();
+      doc2.apply_update_from_binary_v1(&update1).unwrap();
+      assert_eq!(values(&array1), values(&array2));
+      assert_eq!(array1.len(), 7);
+      for (i, value) in values(&array1).iter().enumerate() {
+        assert_eq!(&array2.get(i as u64).unwrap().to_string(), value);
+      }
+
+      let doc = Doc::try_from_binary_v1(doc1.encode_update_v1().unwrap()).unwrap();
+      let array = doc.get_or_create_array("array").unwrap();
+      assert_eq!(values(&array), values(&array1));
+    });
+  }
 }
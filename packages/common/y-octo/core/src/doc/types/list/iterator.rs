@@ -2,22 +2,98 @@ use super::*;
 
 pub(crate) struct ListIterator<'a> {
   pub(super) _lock: RwLockReadGuard<'a, YType>,
-  pub(super) cur: Somr<Item>,
+  pub(super) walker: ListWalker,
 }
 
 impl Iterator for ListIterator<'_> {
   type Item = Somr<Item>;
 
   fn next(&mut self) -> Option<Self::Item> {
-    while let Some(item) = self.cur.clone().get() {
-      let cur = std::mem::replace(&mut self.cur, item.right.clone());
-      if item.deleted() {
-        continue;
+    self
+      .walker
+      .find(|item| item.get().is_some_and(|item| !item.deleted()))
+  }
+}
+
+/// a move item being walked through
+#[derive(Clone)]
+pub(crate) struct MoveScope {
+  pub item: ItemRef,
+  /// the first item after the moved range
+  pub end: ItemRef,
+}
+
+/// Walk the items of a list in the order they are shown, including the
+/// deleted ones. The items moved by a [Move] are visited in place of the move
+/// item instead of their original position, the move items themselves are
+/// not yielded.
+#[derive(Default, Clone)]
+pub(crate) struct ListWalker {
+  pub cur: ItemRef,
+  /// the move items the walker is inside of, the innermost one at last
+  pub moves: Vec<MoveScope>,
+}
+
+impl ListWalker {
+  pub fn new(start: ItemRef) -> Self {
+    Self {
+      cur: start,
+      moves: Vec::new(),
+    }
+  }
+
+  pub fn current_move(&self) -> Option<Id> {
+    self
+      .moves
+      .last()
+      .and_then(|scope| scope.item.get().map(|item| item.id))
+  }
+
+  /// continue after the move items whose range has been walked through
+  fn leave_finished_moves(&mut self) {
+    while let Some(scope) = self.moves.last() {
+      if self.cur.is_some() && self.cur != scope.end {
+        break;
       }
 
-      return Some(cur);
+      self.cur = scope
+        .item
+        .get()
+        .map(|item| item.right.clone())
+        .unwrap_or_default();
+      self.moves.pop();
     }
+  }
+}
 
-    None
+impl Iterator for ListWalker {
+  type Item = ItemRef;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      self.leave_finished_moves();
+
+      let item_ref = self.cur.clone();
+      let item = item_ref.get()?;
+      self.cur = item.right.clone();
+
+      // shown at the position of another move item
+      if item.moved != self.current_move() {
+        continue;
+      }
+
+      if let Content::Move(mv) = &item.content {
+        if !item.deleted() {
+          self.moves.push(MoveScope {
+            item: item_ref.clone(),
+            end: mv.end_item(),
+          });
+          self.cur = mv.start_item();
+        }
+        continue;
+      }
+
+      return Some(item_ref);
+    }
   }
 }
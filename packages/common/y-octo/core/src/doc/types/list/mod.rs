@@ -1,7 +1,7 @@
 mod iterator;
 mod search_marker;
 
-pub(crate) use iterator::ListIterator;
+pub(crate) use iterator::{ListIterator, ListWalker, MoveScope};
 pub(crate) use search_marker::MarkerList;
 
 use super::*;
@@ -12,19 +12,50 @@ pub(crate) struct ItemPosition {
   pub right: ItemRef,
   pub index: u64,
   pub offset: u64,
+  /// the move items the position is inside of, see [ListWalker]
+  pub moves: Vec<MoveScope>,
 }
 
 impl ItemPosition {
-  pub fn forward(&mut self) {
-    if let Some(right) = self.right.get() {
-      if !right.deleted() {
-        self.index += right.len();
+  /// walk the items shown after the position
+  pub fn walker(&self) -> ListWalker {
+    ListWalker {
+      cur: self.right.clone(),
+      moves: self.moves.clone(),
+    }
+  }
+
+  /// Move the position out of the moved ranges it's at the end of, so the
+  /// content inserted at the position is shown after the moved items.
+  pub fn leave_moved_range(&mut self) {
+    while let Some(scope) = self.moves.last() {
+      let current_move = scope.item.get().map(|item| item.id);
+      // skip the items that are not shown in the range
+      while self.right != scope.end {
+        match self.right.get() {
+          Some(right)
+            if right.moved != current_move
+              || right.deleted()
+              || !right.countable() && !matches!(right.content, Content::Move(_)) =>
+          {
+            self.left = self.right.clone();
+            self.right = right.right.clone();
+          }
+          _ => break,
+        }
+      }
+
+      if self.right.is_some() && self.right != scope.end {
+        break;
       }
 
-      self.left = self.right.clone();
-      self.right = right.right.clone();
-    } else {
-      // FAIL
+      self.left = scope.item.clone();
+      self.right = scope
+        .item
+        .get()
+        .map(|item| item.right.clone())
+        .unwrap_or_default();
+      self.moves.pop();
     }
   }
 
@@ -63,11 +94,28 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
   fn iter_item(&self) -> ListIterator {
     let inner = self.as_inner().ty().unwrap();
     ListIterator {
-      cur: inner.start.clone(),
+      walker: ListWalker::new(inner.start.clone()),
       _lock: inner,
     }
   }
 
+  /// the contents of the items visible in the snapshot, including the deleted
+  /// ones
+  fn contents_at(&self, snapshot: &Snapshot) -> Vec<Content> {
+    let mut contents = Vec::new();
+    let Some(inner) = self.as_inner().ty() else {
+      return contents;
+    };
+
+    let mut item_ref = inner.start.clone();
+    while let Some(item) = item_ref.get() {
+      contents.extend(snapshot.visible_contents(item));
+      item_ref = item.right.clone();
+    }
+
+    contents
+  }
+
   fn find_pos(&self, inner: &YType, index: u64) -> Option<ItemPosition> {
     let mut remaining = index;
     let start = inner.start.clone();
@@ -78,6 +126,7 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
       right: start,
       index: 0,
       offset: 0,
+      moves: Vec::new(),
     };
 
     if pos.right.is_none() {
@@ -101,9 +150,13 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
       }
     };
 
+    // the markers are disabled once the type has moved items, walk the items
+    // in the order they are shown
+    let mut walker = pos.walker();
     while remaining > 0 {
-      if let Some(item) = pos.right.get() {
-        if !item.deleted() {
+      let item_ref = walker.next()?;
+      if let Some(item) = item_ref.get() {
+        if item.indexable() {
           let content_len = item.len();
           if remaining < content_len {
             pos.offset = remaining;
@@ -114,12 +167,15 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
           }
         }
 
-        pos.left = pos.right.clone();
+        pos.left = item_ref.clone();
         pos.right = item.right.clone();
-      } else {
-        return None;
       }
     }
+    pos.moves = walker.moves;
+
+    if pos.offset == 0 {
+      pos.leave_moved_range();
+    }
 
     Some(pos)
   }
@@ -129,16 +185,18 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
       return Err(JwstCodecError::IndexOutOfBound(index));
     }
 
-    if let Some((mut store, mut ty)) = self.as_inner().write() {
-      if let Some(mut pos) = self.find_pos(&ty, index) {
-        pos.normalize(&mut store)?;
-        Self::insert_after(&mut ty, &mut store, pos, content)?;
+    self.as_inner().transact(|| {
+      if let Some((mut store, mut ty)) = self.as_inner().write() {
+        if let Some(mut pos) = self.find_pos(&ty, index) {
+          pos.normalize(&mut store)?;
+          Self::insert_after(&mut ty, &mut store, pos, content)?;
+        }
+      } else {
+        return Err(JwstCodecError::DocReleased);
       }
-    } else {
-      return Err(JwstCodecError::DocReleased);
-    }
 
-    Ok(())
+      Ok(())
+    })
   }
 
   fn insert_after(
@@ -173,7 +231,10 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
 
     if let Some(pos) = self.find_pos(&ty, index) {
       if pos.offset == 0 {
-        return Some((pos.right, 0));
+        return pos
+          .walker()
+          .find(|item| item.get().is_some_and(|item| item.indexable()))
+          .map(|item| (item, 0));
       } else {
         return Some((pos.left, pos.offset));
       }
@@ -191,15 +252,17 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
       return Err(JwstCodecError::IndexOutOfBound(idx));
     }
 
-    if let Some((mut store, mut ty)) = self.as_inner().write() {
-      if let Some(pos) = self.find_pos(&ty, idx) {
-        Self::remove_after(&mut ty, &mut store, pos, len)?;
+    self.as_inner().transact(|| {
+      if let Some((mut store, mut ty)) = self.as_inner().write() {
+        if let Some(pos) = self.find_pos(&ty, idx) {
+          Self::remove_after(&mut ty, &mut store, pos, len)?;
+        }
+      } else {
+        return Err(JwstCodecError::DocReleased);
       }
-    } else {
-      return Err(JwstCodecError::DocReleased);
-    }
 
-    Ok(())
+      Ok(())
+    })
   }
 
   fn remove_after(
@@ -212,9 +275,13 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
 
     let mut remaining = len;
 
-    while remaining > 0 {
-      if let Some(item) = pos.right.get() {
-        if !item.deleted() {
+    for item_ref in pos.walker() {
+      if remaining == 0 {
+        break;
+      }
+
+      if let Some(item) = item_ref.get() {
+        if item.indexable() {
           let content_len = item.len();
           if remaining < content_len {
             store.split_node(item.id, remaining)?;
@@ -225,10 +292,6 @@ pub(crate) trait ListType: AsInner<Inner = YTypeRef> {
 
           store.delete_item(item, Some(ty));
         }
-
-        pos.forward();
-      } else {
-        break;
       }
     }
 
@@ -12,17 +12,27 @@ const MAX_SEARCH_MARKER: usize = 80;
 #[derive(Clone, Debug)]
 pub(crate) struct SearchMarker {
   pub ptr: Somr<Item>,
+  /// the index in the unit items are measured in, i.e. utf-16 code units for
+  /// strings, [Text] converts its [OffsetKind] into it before searching
   pub index: u64,
+  /// the index in the [OffsetKind] of the doc, only known by the markers
+  /// [Text] converts its offsets with, and forgotten once the marker moves
+  pub offset: Option<u64>,
 }
 
 impl SearchMarker {
   fn new(ptr: Somr<Item>, index: u64) -> Self {
-    SearchMarker { ptr, index }
+    SearchMarker {
+      ptr,
+      index,
+      offset: None,
+    }
   }
 
   fn overwrite_marker(&mut self, ptr: Somr<Item>, index: u64) {
     self.ptr = ptr;
     self.index = index;
+    self.offset = None;
   }
 }
 
@@ -90,6 +100,7 @@ impl MarkerList {
     let mut list = self.borrow_mut();
 
     for marker in list.iter_mut() {
+      let marker_index = marker.index;
       if len > 0 {
         while let Some(ptr) = marker.ptr.get() {
           if !ptr.indexable() {
@@ -97,6 +108,7 @@ impl MarkerList {
             if let Some(left) = left_ref.get() {
               if left.indexable() {
                 marker.index -= left.len();
+                marker.offset = None;
               }
               marker.ptr = left_ref;
             } else {
@@ -113,6 +125,10 @@ impl MarkerList {
       if marker.ptr.is_some() && (index < marker.index || (len > 0 && index == marker.index)) {
         marker.index = max(index as i64, marker.index as i64 + len) as u64;
       }
+
+      if marker.index != marker_index {
+        marker.offset = None;
+      }
     }
 
     list.retain(|marker| marker.index > 0);
@@ -227,9 +243,37 @@ impl MarkerList {
       if marker.ptr == raw {
         marker.ptr = new.clone();
         marker.index = ((marker.index as i64) + len_shift) as u64;
+        marker.offset = None;
       }
     }
   }
+
+  /// find the marker closest before the offset in the [OffsetKind] of the
+  /// doc, see [SearchMarker::offset]
+  pub fn find_offset_marker(&self, offset: u64) -> Option<SearchMarker> {
+    self
+      .borrow()
+      .iter()
+      .filter(|marker| marker.ptr.is_some() && marker.offset.is_some_and(|o| o <= offset))
+      .max_by_key(|marker| marker.offset)
+      .cloned()
+  }
+
+  /// remember the offset of the item in the [OffsetKind] of the doc
+  pub fn mark_offset(&self, ptr: Somr<Item>, index: u64, offset: u64) {
+    let mut list = self.borrow_mut();
+
+    if let Some(marker) = list.iter_mut().find(|marker| marker.ptr.ptr_eq(&ptr)) {
+      marker.index = index;
+      marker.offset = Some(offset);
+      return;
+    }
+
+    Self::mark_position(&mut list, ptr, index);
+    if let Some(marker) = list.back_mut() {
+      marker.offset = Some(offset);
+    }
+  }
 }
 
 #[cfg(test)]
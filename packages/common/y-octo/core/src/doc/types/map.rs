@@ -3,27 +3,29 @@ use std::{collections::hash_map::Iter, rc::Rc};
 use super::*;
 use crate::{
   doc::{AsInner, Node, Parent, YTypeRef},
-  impl_type, JwstCodecResult,
+  impl_type, JwstCodecError, JwstCodecResult,
 };
 
 impl_type!(Map);
 
 pub(crate) trait MapType: AsInner<Inner = YTypeRef> {
   fn _insert<V: Into<Value>>(&mut self, key: String, value: V) -> JwstCodecResult {
-    if let Some((mut store, mut ty)) = self.as_inner().write() {
-      let left = ty.map.get(&SmolStr::new(&key)).cloned();
-
-      let item = store.create_item(
-        value.into().into(),
-        left.unwrap_or(Somr::none()),
-        Somr::none(),
-        Some(Parent::Type(self.as_inner().clone())),
-        Some(SmolStr::new(key)),
-      );
-      store.integrate(Node::Item(item), 0, Some(&mut ty))?;
-    }
+    self.as_inner().transact(|| {
+      if let Some((mut store, mut ty)) = self.as_inner().write() {
+        let left = ty.map.get(&SmolStr::new(&key)).cloned();
+
+        let item = store.create_item(
+          value.into().into(),
+          left.unwrap_or(Somr::none()),
+          Somr::none(),
+          Some(Parent::Type(self.as_inner().clone())),
+          Some(SmolStr::new(key)),
+        );
+        store.integrate(Node::Item(item), 0, Some(&mut ty))?;
+      }
 
-    Ok(())
+      Ok(())
+    })
   }
 
   fn _get(&self, key: &str) -> Option<Value> {
@@ -42,6 +44,22 @@ pub(crate) trait MapType: AsInner<Inner = YTypeRef> {
     })
   }
 
+  fn _get_at(&self, key: &str, snapshot: &Snapshot) -> Option<Value> {
+    let ty = self.as_inner().ty()?;
+    let mut item_ref = ty.map.get(key).cloned()?;
+
+    // find the latest value set before the snapshot
+    while let Some(item) = item_ref.get() {
+      if snapshot.state_vector.get(&item.id.client) > item.id.clock {
+        return (snapshot.contains(&item.id) && item.content.countable())
+          .then(|| Value::from(&item.content));
+      }
+      item_ref = item.left.clone();
+    }
+
+    None
+  }
+
   fn _contains_key(&self, key: &str) -> bool {
     if let Some(ty) = self.as_inner().ty() {
       ty.map
@@ -54,13 +72,15 @@ pub(crate) trait MapType: AsInner<Inner = YTypeRef> {
   }
 
   fn _remove(&mut self, key: &str) {
-    if let Some((mut store, mut ty)) = self.as_inner().write() {
-      if let Some(item) = ty.map.get(key).cloned() {
-        if let Some(item) = item.get() {
-          store.delete_item(item, Some(&mut ty));
+    self.as_inner().transact(|| {
+      if let Some((mut store, mut ty)) = self.as_inner().write() {
+        if let Some(item) = ty.map.get(key).cloned() {
+          if let Some(item) = item.get() {
+            store.delete_item(item, Some(&mut ty));
+          }
         }
       }
-    }
+    })
   }
 
   fn _len(&self) -> u64 {
@@ -164,6 +184,60 @@ impl Map {
     self._get(key)
   }
 
+  /// Insert a JSON value, the objects and arrays are inserted as nested
+  /// [Map]s and [Array]s, the other values as [Any].
+  pub fn insert_json(&mut self, key: String, value: serde_json::Value) -> JwstCodecResult {
+    self.0.clone().transact(|| {
+      match value {
+        serde_json::Value::Object(entries) => {
+          self.insert(key.clone(), self.0.create_type::<Map>(YTypeKind::Map)?)?;
+          let mut map = self
+            .get(&key)
+            .and_then(|value| value.to_map())
+            .ok_or(JwstCodecError::Unexpected)?;
+          for (key, value) in entries {
+            map.insert_json(key, value)?;
+          }
+        }
+        serde_json::Value::Array(values) => {
+          self.insert(key.clone(), self.0.create_type::<Array>(YTypeKind::Array)?)?;
+          let mut array = self
+            .get(&key)
+            .and_then(|value| value.to_array())
+            .ok_or(JwstCodecError::Unexpected)?;
+          for value in values {
+            array.push_json(value)?;
+          }
+        }
+        value => self.insert(key, Any::from(value))?,
+      }
+
+      Ok(())
+    })
+  }
+
+  /// The value of the key as of the given [Snapshot].
+  pub fn get_at(&self, key: &str, snapshot: &Snapshot) -> Option<Value> {
+    self._get_at(key, snapshot)
+  }
+
+  /// The entries of the map as of the given [Snapshot].
+  pub fn to_map_at(&self, snapshot: &Snapshot) -> HashMap<String, Value> {
+    let keys = match self.as_inner().ty() {
+      Some(ty) => ty.map.keys().cloned().collect::<Vec<_>>(),
+      None => return HashMap::new(),
+    };
+
+    keys
+      .into_iter()
+      .filter_map(|key| {
+        self
+          ._get_at(&key, snapshot)
+          .map(|value| (key.to_string(), value))
+      })
+      .collect()
+  }
+
   #[inline(always)]
   pub fn contains_key(&self, key: &str) -> bool {
     self._contains_key(key)
@@ -203,6 +277,25 @@ impl Map {
   pub fn values(&self) -> ValuesIterator {
     self._values()
   }
+
+  /// Subscribe the changes made to the map in each transaction.
+  pub fn observe(&self, cb: impl Fn(&MapEvent) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe(move |event| {
+      if let Event::Map(event) = event {
+        cb(event)
+      }
+    })
+  }
+
+  /// Subscribe the changes made to the map and all its nested types in
+  /// each transaction.
+  pub fn observe_deep(&self, cb: impl Fn(&[Event]) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe_deep(cb)
+  }
+
+  pub fn unobserve(&self, id: ObserverId) {
+    self.0.unobserve(id)
+  }
 }
 
 impl serde::Serialize for Map {
@@ -1,6 +1,8 @@
 mod array;
 mod list;
 mod map;
+mod offset;
+mod path;
 mod text;
 mod value;
 mod xml;
@@ -8,8 +10,11 @@ mod xml;
 use std::{collections::hash_map::Entry, sync::Weak};
 
 pub use array::*;
+pub(crate) use list::MarkerList;
 use list::*;
 pub use map::*;
+pub use offset::*;
+pub use path::*;
 pub use text::*;
 pub use value::*;
 pub use xml::*;
@@ -139,6 +144,16 @@ impl YTypeRef {
       .store_mut()
       .and_then(|store| self.ty_mut().map(|ty| (store, ty)))
   }
+
+  /// create a type without name in the same doc, e.g. to be nested in this one
+  pub fn create_type<T: TryFrom<YTypeRef, Error = JwstCodecError>>(
+    &self,
+    kind: YTypeKind,
+  ) -> JwstCodecResult<T> {
+    let store = self.store.upgrade().ok_or(JwstCodecError::DocReleased)?;
+
+    YTypeBuilder::new(store).with_kind(kind).build()
+  }
 }
 
 pub(crate) struct YTypeBuilder {
@@ -172,7 +187,6 @@ impl YTypeBuilder {
     self
   }
 
-  #[allow(dead_code)]
   pub fn set_tag_name(mut self, tag_name: String) -> Self {
     self.name = Some(tag_name);
 
@@ -223,26 +237,7 @@ impl YTypeBuilder {
         }
       }
     } else {
-      let inner = Somr::new(RwLock::new(YType {
-        kind: self.kind,
-        name: self.name,
-        root_name: self.root_name.clone(),
-        markers: Self::markers(self.kind),
-        ..Default::default()
-      }));
-
-      let ty = YTypeRef {
-        store: Arc::downgrade(&self.store),
-        inner,
-      };
-
-      let ty_ref = ty.clone();
-
-      store
-        .dangling_types
-        .insert(ty.inner.ptr().as_ptr() as usize, ty);
-
-      ty_ref
+      Self::dangling(&self.store, &mut store, self.kind, self.name)
     };
 
     drop(store);
@@ -250,6 +245,35 @@ impl YTypeBuilder {
     T::try_from(ty)
   }
 
+  /// create a type without name, the ownership is kept in
+  /// [DocStore::dangling_types]
+  pub fn dangling(
+    store_ref: &StoreRef,
+    store: &mut DocStore,
+    kind: YTypeKind,
+    name: Option<String>,
+  ) -> YTypeRef {
+    let inner = Somr::new(RwLock::new(YType {
+      kind,
+      name,
+      markers: Self::markers(kind),
+      ..Default::default()
+    }));
+
+    let ty = YTypeRef {
+      store: Arc::downgrade(store_ref),
+      inner,
+    };
+
+    let ty_ref = ty.clone();
+
+    store
+      .dangling_types
+      .insert(ty.inner.ptr().as_ptr() as usize, ty);
+
+    ty_ref
+  }
+
   fn markers(kind: YTypeKind) -> Option<MarkerList> {
     match kind {
       YTypeKind::Map => None,
@@ -354,6 +378,18 @@ macro_rules! impl_type {
       pub(crate) fn from_unchecked(value: super::YTypeRef) -> Self {
         $name::new(value.clone())
       }
+
+      /// The path from the root type to this type, [None] if it's not
+      /// integrated into the doc or has been deleted.
+      pub fn path(&self) -> Option<super::TypePath> {
+        self.0.path()
+      }
+
+      /// The id of the item holding this type, [None] for the root types and
+      /// the ones not integrated yet.
+      pub fn id(&self) -> Option<$crate::Id> {
+        self.0.ty().and_then(|ty| ty.item.get().map(|item| item.id))
+      }
     }
 
     impl From<$name> for super::Value {
@@ -0,0 +1,333 @@
+use super::*;
+
+/// The unit of the indexes and lengths taken and returned by [Text].
+///
+/// The items of a text are always measured in UTF-16 code units internally,
+/// the same as yjs, so the updates are compatible whatever the unit is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OffsetKind {
+  /// the bytes of the utf-8 encoded string, a.k.a [str::len]
+  Bytes,
+  /// the utf-16 code units, a.k.a `String.prototype.length` in javascript
+  #[default]
+  Utf16,
+  /// the unicode scalar values, a.k.a [str::chars]
+  Utf32,
+}
+
+impl OffsetKind {
+  /// the length of the content of an item, the contents other than strings
+  /// are counted the same in all units
+  pub(crate) fn content_len(&self, content: &Content) -> u64 {
+    match content {
+      Content::String(str) => self.str_len(str),
+      content => content.clock_len(),
+    }
+  }
+
+  pub fn char_len(&self, ch: char) -> u64 {
+    match self {
+      OffsetKind::Bytes => ch.len_utf8() as u64,
+      OffsetKind::Utf16 => ch.len_utf16() as u64,
+      OffsetKind::Utf32 => 1,
+    }
+  }
+
+  /// the length of the string in this unit
+  pub fn str_len(&self, str: &str) -> u64 {
+    match self {
+      OffsetKind::Bytes => str.len() as u64,
+      OffsetKind::Utf16 => str.chars().map(|ch| ch.len_utf16() as u64).sum(),
+      OffsetKind::Utf32 => str.chars().count() as u64,
+    }
+  }
+
+  /// Convert the offset in the string from this unit to another.
+  ///
+  /// An offset in the middle of a char is rounded up to the end of the char,
+  /// and the part beyond the end of the string is kept as it is.
+  ///
+  /// ```
+  /// use y_octo::OffsetKind;
+  ///
+  /// let str = "a😀b";
+  /// assert_eq!(OffsetKind::Utf32.convert(str, 2, OffsetKind::Utf16), 3);
+  /// assert_eq!(OffsetKind::Utf16.convert(str, 3, OffsetKind::Bytes), 5);
+  /// assert_eq!(OffsetKind::Bytes.convert(str, 5, OffsetKind::Utf32), 2);
+  /// ```
+  pub fn convert(&self, str: &str, offset: u64, to: OffsetKind) -> u64 {
+    if *self == to {
+      return offset;
+    }
+
+    let mut from_offset = 0;
+    let mut to_offset = 0;
+    for ch in str.chars() {
+      if from_offset >= offset {
+        return to_offset;
+      }
+      from_offset += self.char_len(ch);
+      to_offset += to.char_len(ch);
+    }
+
+    to_offset + offset.saturating_sub(from_offset)
+  }
+}
+
+/// Walks the visible content of a text, converting the lengths measured in
+/// one offset kind into another, e.g. into the utf-16 ones the items are
+/// measured in.
+pub(crate) struct OffsetCursor<'a> {
+  from: OffsetKind,
+  to: OffsetKind,
+  items: ListIterator<'a>,
+  /// the item the cursor is in, [None] once the end is reached
+  item: Option<ItemRef>,
+  /// the offset in the current item, in the `from` kind
+  offset: u64,
+}
+
+impl<'a> OffsetCursor<'a> {
+  pub fn new(text: &'a Text, from: OffsetKind, to: OffsetKind) -> Self {
+    let mut items = text.iter_item();
+    let item = Self::next_item(&mut items);
+
+    Self {
+      from,
+      to,
+      items,
+      item,
+      offset: 0,
+    }
+  }
+
+  fn next_item(items: &mut ListIterator) -> Option<ItemRef> {
+    items.find(|item| item.get().is_some_and(|item| item.indexable()))
+  }
+
+  /// move forward by the length in the `from` kind, returns the length moved
+  /// over in the `to` kind, [None] if it goes beyond the end of the content
+  pub fn advance(&mut self, len: u64) -> Option<u64> {
+    let (reached, moved) = self.seek(len);
+    (reached == len).then_some(moved)
+  }
+
+  /// same as [OffsetCursor::advance], but the part beyond the end of the
+  /// content is kept as it is
+  pub fn advance_saturating(&mut self, len: u64) -> u64 {
+    let (reached, moved) = self.seek(len);
+    moved + (len - reached)
+  }
+
+  /// move forward by the length in the `from` kind or to the end of the
+  /// content, returns the lengths moved over in the `from` and `to` kinds
+  pub fn seek(&mut self, len: u64) -> (u64, u64) {
+    let mut reached = 0;
+    let mut moved = 0;
+
+    while reached < len {
+      let Some(item_ref) = self.item.clone() else {
+        break;
+      };
+      let Some(item) = item_ref.get() else {
+        break;
+      };
+      let start = self.convert(&item.content, self.offset);
+      let remaining = self.from.content_len(&item.content) - self.offset;
+
+      if len - reached < remaining {
+        self.offset += len - reached;
+        moved += self.convert(&item.content, self.offset) - start;
+        reached = len;
+        break;
+      }
+
+      moved += self.to.content_len(&item.content) - start;
+      reached += remaining;
+      self.item = Self::next_item(&mut self.items);
+      self.offset = 0;
+    }
+
+    (reached, moved)
+  }
+
+  fn convert(&self, content: &Content, offset: u64) -> u64 {
+    match content {
+      Content::String(str) => self.from.convert(str, offset, self.to),
+      _ => offset,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_offset_kind_convert() {
+    let str = "a😀中";
+    assert_eq!(OffsetKind::Bytes.str_len(str), 8);
+    assert_eq!(OffsetKind::Utf16.str_len(str), 4);
+    assert_eq!(OffsetKind::Utf32.str_len(str), 3);
+
+    assert_eq!(OffsetKind::Utf32.convert(str, 2, OffsetKind::Bytes), 5);
+    assert_eq!(OffsetKind::Bytes.convert(str, 5, OffsetKind::Utf16), 3);
+    // the offset in the middle of a char is rounded up
+    assert_eq!(OffsetKind::Utf16.convert(str, 2, OffsetKind::Utf32), 2);
+    assert_eq!(OffsetKind::Bytes.convert(str, 6, OffsetKind::Utf32), 3);
+    // the part beyond the end is kept
+    assert_eq!(OffsetKind::Utf32.convert(str, 5, OffsetKind::Utf16), 6);
+  }
+
+  #[test]
+  fn test_text_offset_kind() {
+    loom_model!({
+      for kind in [OffsetKind::Bytes, OffsetKind::Utf16, OffsetKind::Utf32] {
+        let doc = DocOptions::new().with_offset_kind(kind).build();
+        let mut text = doc.get_or_create_text("text").unwrap();
+        let offset = |str: &str| kind.str_len(str);
+
+        text.insert(0, "a中b").unwrap();
+        text.insert(offset("a中"), "😀").unwrap();
+        assert_eq!(text.to_string(), "a中😀b");
+        assert_eq!(text.len(), offset("a中😀b"));
+
+        text
+          .insert_embed(offset("a"), Any::from("img"), TextAttributes::new())
+          .unwrap();
+        text
+          .format(
+            offset("a") + 1,
+            offset("中😀"),
+            TextAttributes::from_iter([("bold".into(), true.into())]),
+          )
+          .unwrap();
+        assert_eq!(
+          text.to_delta(),
+          vec![
+            TextDelta::Insert {
+              insert: TextInsert::Text("a".into()),
+              attributes: None,
+            },
+            TextDelta::Insert {
+              insert: TextInsert::Embed(Any::from("img")),
+              attributes: None,
+            },
+            TextDelta::Insert {
+              insert: TextInsert::Text("中😀".into()),
+              attributes: Some(TextAttributes::from_iter([("bold".into(), true.into())])),
+            },
+            TextDelta::Insert {
+              insert: TextInsert::Text("b".into()),
+              attributes: None,
+            },
+          ]
+        );
+
+        text.remove(offset("a") + 1, offset("中")).unwrap();
+        assert_eq!(text.to_string(), "a😀b");
+        text
+          .apply_delta(&[
+            TextDelta::Retain {
+              retain: offset("a") + 1,
+              attributes: None,
+            },
+            TextDelta::Delete {
+              delete: offset("😀"),
+            },
+            TextDelta::Insert {
+              insert: TextInsert::Text("é".into()),
+              attributes: None,
+            },
+          ])
+          .unwrap();
+        assert_eq!(text.to_string(), "aéb");
+
+        assert_eq!(
+          text.convert_offset(offset("aé") + 1, kind, OffsetKind::Utf16),
+          Some(3)
+        );
+        assert_eq!(
+          text.convert_offset(3, OffsetKind::Utf16, OffsetKind::Bytes),
+          Some(4)
+        );
+        assert_eq!(text.convert_offset(5, OffsetKind::Utf32, kind), None);
+        assert!(text.insert(text.len() + 1, "x").is_err());
+
+        // the updates are the same whatever the unit is
+        let mut remote = Doc::new();
+        remote
+          .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+          .unwrap();
+        assert_eq!(
+          remote.get_or_create_text("text").unwrap().len(),
+          OffsetKind::Utf16.str_len("aéb") + 1
+        );
+      }
+    });
+  }
+
+  #[test]
+  #[cfg(not(loom))]
+  fn test_text_offset_kind_markers() {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let iterations = if cfg!(miri) { 20 } else { 500 };
+
+    for kind in [OffsetKind::Bytes, OffsetKind::Utf32] {
+      let doc = DocOptions::new().with_offset_kind(kind).build();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut model = String::new();
+      // the offset of the char at the index in the model
+      let offset =
+        |model: &str, index: usize| kind.str_len(&model.chars().take(index).collect::<String>());
+
+      for _ in 0..iterations {
+        let chars = model.chars().count();
+        if chars > 0 && rng.random_bool(0.3) {
+          let index = rng.random_range(0..chars);
+          let len = rng.random_range(1..=(chars - index).min(3));
+          let start = offset(&model, index);
+          text
+            .remove(start, offset(&model, index + len) - start)
+            .unwrap();
+          model = model
+            .chars()
+            .take(index)
+            .chain(model.chars().skip(index + len))
+            .collect();
+        } else {
+          let index = rng.random_range(0..=chars);
+          let str = ["a", "中", "😀", "é"][rng.random_range(0..4)];
+          text.insert(offset(&model, index), str).unwrap();
+          model.insert_str(
+            model
+              .char_indices()
+              .nth(index)
+              .map_or(model.len(), |(i, _)| i),
+            str,
+          );
+        }
+
+        assert_eq!(text.len(), kind.str_len(&model));
+      }
+      assert_eq!(text.to_string(), model);
+
+      // the sticky index is resolved in the same unit
+      let index = model.chars().count() / 2;
+      let sticky = text
+        .sticky_index(offset(&model, index), Assoc::After)
+        .unwrap();
+      text.insert(0, "😀").unwrap();
+      assert_eq!(
+        sticky.resolve(&doc),
+        Some(offset(&model, index) + kind.str_len("😀"))
+      );
+      let end = text.sticky_index(text.len(), Assoc::After).unwrap();
+      assert_eq!(end.resolve(&doc), Some(text.len()));
+    }
+  }
+}
@@ -0,0 +1,296 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// A step from a type to the value nested in it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+  /// the key of the entry in a [Map]
+  Key(String),
+  /// the index of the value in a list type, e.g. [Array]
+  Index(u64),
+}
+
+impl Display for PathSegment {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PathSegment::Key(key) => write!(f, "{key}"),
+      PathSegment::Index(index) => write!(f, "{index}"),
+    }
+  }
+}
+
+impl From<&str> for PathSegment {
+  fn from(key: &str) -> Self {
+    PathSegment::Key(key.to_string())
+  }
+}
+
+impl From<String> for PathSegment {
+  fn from(key: String) -> Self {
+    PathSegment::Key(key)
+  }
+}
+
+impl From<u64> for PathSegment {
+  fn from(index: u64) -> Self {
+    PathSegment::Index(index)
+  }
+}
+
+/// The location of a value in a [Doc], made of the name of the root type and
+/// the steps from it.
+///
+/// ```
+/// use y_octo::{Doc, TypePath};
+///
+/// let doc = Doc::new();
+/// let mut blocks = doc.get_or_create_map("blocks").unwrap();
+/// blocks
+///   .insert_json("block".into(), serde_json::json!({ "children": [] }))
+///   .unwrap();
+/// let children = blocks
+///   .get("block")
+///   .and_then(|block| block.to_map())
+///   .and_then(|block| block.get("children"))
+///   .and_then(|children| children.to_array())
+///   .unwrap();
+///
+/// let path = children.path().unwrap();
+/// assert_eq!(path, TypePath::new("blocks", ["block", "children"]));
+/// assert_eq!(path.to_string(), "blocks -> block -> children");
+/// assert_eq!(doc.get_by_path(&path).and_then(|v| v.to_array()), Some(children));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypePath {
+  pub root: String,
+  pub segments: Vec<PathSegment>,
+}
+
+impl TypePath {
+  pub fn new<R: ToString, S: Into<PathSegment>>(
+    root: R,
+    segments: impl IntoIterator<Item = S>,
+  ) -> Self {
+    Self {
+      root: root.to_string(),
+      segments: segments.into_iter().map(Into::into).collect(),
+    }
+  }
+}
+
+impl Display for TypePath {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.root)?;
+    for segment in &self.segments {
+      write!(f, " -> {segment}")?;
+    }
+
+    Ok(())
+  }
+}
+
+impl YTypeRef {
+  /// the path from the root type to this type, [None] if the type is not
+  /// integrated into the doc or has been deleted
+  pub fn path(&self) -> Option<TypePath> {
+    let mut segments = Vec::new();
+    let mut ty = self.clone();
+
+    loop {
+      let (item_ref, root_name) = {
+        let inner = ty.ty()?;
+        (inner.item.clone(), inner.root_name.clone())
+      };
+
+      if let Some(root) = root_name {
+        segments.reverse();
+        return Some(TypePath { root, segments });
+      }
+
+      let item = item_ref.get()?;
+      if item.deleted() {
+        return None;
+      }
+      let Some(Parent::Type(parent)) = &item.parent else {
+        return None;
+      };
+
+      segments.push(match &item.parent_sub {
+        Some(key) => PathSegment::Key(key.to_string()),
+        None => PathSegment::Index(parent.index_of(&item_ref)?),
+      });
+      ty = parent.clone();
+    }
+  }
+
+  /// the index of the item in the list, the moved items are counted at the
+  /// position they are moved to
+  fn index_of(&self, item: &ItemRef) -> Option<u64> {
+    let inner = self.ty()?;
+
+    let mut index = 0;
+    for cur in ListWalker::new(inner.start.clone()) {
+      if cur.ptr_eq(item) {
+        return Some(index);
+      }
+      if let Some(cur) = cur.get() {
+        if cur.indexable() {
+          index += cur.len();
+        }
+      }
+    }
+
+    None
+  }
+
+  /// the value nested in this type at the segment
+  pub(crate) fn get_by_segment(&self, segment: &PathSegment) -> Option<Value> {
+    let inner = self.ty()?;
+
+    match segment {
+      PathSegment::Key(key) => inner
+        .map
+        .get(key.as_str())
+        .and_then(|item| item.get())
+        .filter(|item| !item.deleted())
+        .map(|item| Value::from(&item.content)),
+      PathSegment::Index(index) => {
+        let mut start = 0;
+        for cur in ListWalker::new(inner.start.clone()) {
+          let Some(item) = cur.get() else {
+            continue;
+          };
+          if !item.indexable() {
+            continue;
+          }
+
+          if *index < start + item.len() {
+            let offset = (*index - start) as usize;
+            return match &item.content {
+              Content::Any(any) => any.get(offset).cloned().map(Value::Any),
+              content if offset == 0 => Some(Value::from(content)),
+              _ => None,
+            };
+          }
+          start += item.len();
+        }
+
+        None
+      }
+    }
+  }
+}
+
+impl Value {
+  /// the shared type of the value, [None] for the plain values
+  pub(crate) fn as_type(&self) -> Option<&YTypeRef> {
+    match self {
+      Value::Array(v) => Some(&v.0),
+      Value::Map(v) => Some(&v.0),
+      Value::Text(v) => Some(&v.0),
+      Value::XMLElement(v) => Some(&v.0),
+      Value::XMLFragment(v) => Some(&v.0),
+      Value::XMLHook(v) => Some(&v.0),
+      Value::XMLText(v) => Some(&v.0),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_type_path() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut blocks = doc.get_or_create_map("blocks").unwrap();
+      blocks
+        .insert_json(
+          "block".into(),
+          serde_json::json!({ "children": [{ "a": 1 }, { "b": 2 }, { "c": 3 }] }),
+        )
+        .unwrap();
+      let mut block = blocks.get("block").and_then(|v| v.to_map()).unwrap();
+      block
+        .insert(
+          "prop:text".into(),
+          block.0.create_type::<Text>(YTypeKind::Text).unwrap(),
+        )
+        .unwrap();
+      let text = block.get("prop:text").and_then(|v| v.to_text()).unwrap();
+      let mut children = block.get("children").and_then(|v| v.to_array()).unwrap();
+      let last = children.get(2).and_then(|v| v.to_map()).unwrap();
+
+      assert_eq!(
+        blocks.path(),
+        Some(TypePath::new("blocks", Vec::<PathSegment>::new()))
+      );
+      assert_eq!(
+        text.path(),
+        Some(TypePath::new("blocks", ["block", "prop:text"]))
+      );
+      let path = last.path().unwrap();
+      assert_eq!(
+        path,
+        TypePath::new(
+          "blocks",
+          [
+            PathSegment::from("block"),
+            PathSegment::from("children"),
+            PathSegment::from(2),
+          ]
+        )
+      );
+      assert_eq!(path.to_string(), "blocks -> block -> children -> 2");
+      assert_eq!(
+        doc.get_by_path(&path).and_then(|v| v.to_map()),
+        Some(last.clone())
+      );
+      assert_eq!(
+        doc
+          .get_by_path(&TypePath::new(
+            "blocks",
+            [
+              PathSegment::from("block"),
+              PathSegment::from("children"),
+              PathSegment::from(0),
+              PathSegment::from("a"),
+            ]
+          ))
+          .and_then(|v| v.to_any()),
+        Some(Any::Integer(1))
+      );
+      assert_eq!(doc.get_by_path(&TypePath::new("blocks", ["unknown"])), None);
+      assert_eq!(doc.get_by_path(&TypePath::new("unknown", ["block"])), None);
+
+      // the moved items are indexed where they are shown
+      children.move_to(2, 0).unwrap();
+      assert_eq!(last.path().unwrap().segments[2], PathSegment::Index(0));
+      children.remove(1, 1).unwrap();
+      assert_eq!(last.path().unwrap().segments[2], PathSegment::Index(0));
+
+      let id = last.0.ty().unwrap().item.get().unwrap().id;
+      assert_eq!(
+        doc.get_by_id(id).and_then(|v| v.to_map()),
+        Some(last.clone())
+      );
+      assert_eq!(doc.get_by_id(Id::new(doc.client(), 10_000)), None);
+
+      children.remove(0, 1).unwrap();
+      assert_eq!(last.path(), None);
+      assert_eq!(doc.get_by_id(id), None);
+
+      let history = doc.history().parse_store(Default::default());
+      assert!(history
+        .iter()
+        .any(|h| h.path == Some(TypePath::new("blocks", ["block"]))));
+    });
+  }
+}
@@ -1,16 +1,229 @@
 use std::fmt::Display;
 
-use super::list::ListType;
-use crate::{impl_type, Content, JwstCodecResult};
+use super::*;
 
 impl_type!(Text);
 
 impl ListType for Text {}
 
+/// The formatting attributes applied to a range of text, e.g. `{"bold": true}`.
+///
+/// A `null` value means the attribute is removed from the range.
+pub type TextAttributes = HashMap<String, Any>;
+
+/// The content carried by an insert operation of a [TextDelta].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum TextInsert {
+  Text(String),
+  Embed(Any),
+}
+
+/// A single operation of a Quill-compatible delta.
+///
+/// Serialized in the same shape as yjs' `Y.Text#toDelta`, e.g.
+/// `{"insert": "hello", "attributes": {"bold": true}}`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum TextDelta {
+  Insert {
+    insert: TextInsert,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attributes: Option<TextAttributes>,
+  },
+  Retain {
+    retain: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attributes: Option<TextAttributes>,
+  },
+  Delete {
+    delete: u64,
+  },
+}
+
+pub(crate) trait TextType: ListType {
+  fn _insert_with_attributes(
+    &mut self,
+    char_index: u64,
+    content: Content,
+    attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    if char_index > self.content_len() {
+      return Err(JwstCodecError::IndexOutOfBound(char_index));
+    }
+
+    let parent = self.as_inner().clone();
+    parent.clone().transact(|| {
+      if let Some((mut store, mut ty)) = parent.write() {
+        let mut pos = TextPosition::find(&mut store, &ty, char_index)?;
+        pos.insert(&mut store, &mut ty, &parent, content, attrs)?;
+      } else {
+        return Err(JwstCodecError::DocReleased);
+      }
+
+      Ok(())
+    })
+  }
+
+  fn _format(&mut self, char_index: u64, len: u64, attrs: TextAttributes) -> JwstCodecResult {
+    if len == 0 || attrs.is_empty() {
+      return Ok(());
+    }
+
+    if char_index > self.content_len() {
+      return Err(JwstCodecError::IndexOutOfBound(char_index));
+    }
+
+    let parent = self.as_inner().clone();
+    parent.clone().transact(|| {
+      if let Some((mut store, mut ty)) = parent.write() {
+        let mut pos = TextPosition::find(&mut store, &ty, char_index)?;
+        pos.format(&mut store, &mut ty, &parent, len, &attrs)?;
+      } else {
+        return Err(JwstCodecError::DocReleased);
+      }
+
+      Ok(())
+    })
+  }
+
+  fn _to_delta(&self) -> Vec<TextDelta> {
+    let mut delta = DeltaBuilder::default();
+    for item in self.iter_item() {
+      if let Some(item) = item.get() {
+        delta.push(&item.content);
+      }
+    }
+
+    delta.finish()
+  }
+
+  fn _to_delta_at(&self, snapshot: &Snapshot) -> Vec<TextDelta> {
+    let mut delta = DeltaBuilder::default();
+    for content in self.contents_at(snapshot) {
+      delta.push(&content);
+    }
+
+    delta.finish()
+  }
+
+  fn _apply_delta(&mut self, delta: &[TextDelta]) -> JwstCodecResult {
+    let parent = self.as_inner().clone();
+    parent.clone().transact(|| {
+      if let Some((mut store, mut ty)) = parent.write() {
+        let mut pos = TextPosition::new(ty.start.clone());
+
+        for op in delta {
+          match op {
+            TextDelta::Insert { insert, attributes } => {
+              let content = match insert {
+                TextInsert::Text(str) if str.is_empty() => continue,
+                TextInsert::Text(str) => Content::String(str.clone()),
+                TextInsert::Embed(embed) => Content::Embed(embed.clone()),
+              };
+              pos.insert(
+                &mut store,
+                &mut ty,
+                &parent,
+                content,
+                attributes.clone().unwrap_or_default(),
+              )?;
+            }
+            TextDelta::Retain { retain, attributes } => {
+              pos.format(
+                &mut store,
+                &mut ty,
+                &parent,
+                *retain,
+                attributes.as_ref().unwrap_or(&TextAttributes::new()),
+              )?;
+            }
+            TextDelta::Delete { delete } => {
+              pos.delete(&mut store, &mut ty, *delete)?;
+            }
+          }
+        }
+      } else {
+        return Err(JwstCodecError::DocReleased);
+      }
+
+      Ok(())
+    })
+  }
+
+  fn _to_string(&self) -> String {
+    self
+      .iter_item()
+      .filter_map(|item| match &item.get()?.content {
+        Content::String(str) => Some(str.clone()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  fn _to_string_at(&self, snapshot: &Snapshot) -> String {
+    self
+      .contents_at(snapshot)
+      .into_iter()
+      .filter_map(|content| match content {
+        Content::String(str) => Some(str),
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+/// Packs the contents of a text into [TextDelta]s.
+#[derive(Default)]
+struct DeltaBuilder {
+  ops: Vec<TextDelta>,
+  attrs: TextAttributes,
+  str: String,
+}
+
+impl DeltaBuilder {
+  fn push(&mut self, content: &Content) {
+    match content {
+      Content::String(s) => self.str.push_str(s),
+      Content::Embed(embed) => {
+        self.pack_str();
+        self.ops.push(TextDelta::Insert {
+          insert: TextInsert::Embed(embed.clone()),
+          attributes: (!self.attrs.is_empty()).then(|| self.attrs.clone()),
+        });
+      }
+      Content::Format { key, value } => {
+        self.pack_str();
+        update_attributes(&mut self.attrs, key, value);
+      }
+      _ => {}
+    }
+  }
+
+  fn pack_str(&mut self) {
+    if !self.str.is_empty() {
+      self.ops.push(TextDelta::Insert {
+        insert: TextInsert::Text(std::mem::take(&mut self.str)),
+        attributes: (!self.attrs.is_empty()).then(|| self.attrs.clone()),
+      });
+    }
+  }
+
+  fn finish(mut self) -> Vec<TextDelta> {
+    self.pack_str();
+    self.ops
+  }
+}
+
+impl TextType for Text {}
+
 impl Text {
-  #[inline]
+  /// The length of the text in the unit of [Text::offset_kind].
   pub fn len(&self) -> u64 {
-    self.content_len()
+    match self.offset_kind() {
+      OffsetKind::Utf16 => self.content_len(),
+      kind => self.seek_offset(kind, u64::MAX).0,
+    }
   }
 
   #[inline]
@@ -18,26 +231,539 @@ impl Text {
     self.len() == 0
   }
 
+  /// The unit of the indexes and lengths taken and returned by the text, see
+  /// [DocOptions::with_offset_kind].
+  pub fn offset_kind(&self) -> OffsetKind {
+    self
+      .0
+      .store()
+      .map(|store| store.offset_kind)
+      .unwrap_or_default()
+  }
+
+  /// Convert the offset in the text from one unit to another, [None] if it's
+  /// beyond the end of the text.
+  pub fn convert_offset(&self, offset: u64, from: OffsetKind, to: OffsetKind) -> Option<u64> {
+    OffsetCursor::new(self, from, to).advance(offset)
+  }
+
+  /// walk to the offset in the unit of `kind`, starting from the closest
+  /// search marker before it that knows its offset in the unit, returns the
+  /// offset reached, which is the length of the text if it's shorter, and the
+  /// index of it in the utf-16 code units
+  fn seek_offset(&self, kind: OffsetKind, offset: u64) -> (u64, u64) {
+    {
+      // the markers are cached behind the read lock, same as the ones of the
+      // utf-16 indexes, see [MarkerList::find_marker]
+      let Some(ty) = self.0.ty() else {
+        return (0, 0);
+      };
+
+      if let Some(markers) = &ty.markers {
+        let (mut item_ref, mut reached, mut index) = markers
+          .find_offset_marker(offset)
+          .and_then(|marker| Some((marker.ptr, marker.offset?, marker.index)))
+          .unwrap_or_else(|| (ty.start.clone(), 0, 0));
+
+        while let Some(item) = item_ref.get() {
+          if item.indexable() {
+            let len = kind.content_len(&item.content);
+            if reached + len > offset {
+              markers.mark_offset(item_ref.clone(), index, reached);
+              let rest = match &item.content {
+                Content::String(str) => kind.convert(str, offset - reached, OffsetKind::Utf16),
+                _ => offset - reached,
+              };
+              return (offset, index + rest);
+            }
+            reached += len;
+            index += item.len();
+          }
+          item_ref = item.right.clone();
+        }
+
+        return (reached, index);
+      }
+    }
+
+    // the markers are disabled once the text has moved items, walk the items
+    // in the order they are shown
+    OffsetCursor::new(self, kind, OffsetKind::Utf16).seek(offset)
+  }
+
+  /// the index in the utf-16 code units the items are measured in
+  fn utf16_index(&self, char_index: u64) -> JwstCodecResult<u64> {
+    match self.offset_kind() {
+      OffsetKind::Utf16 => Ok(char_index),
+      kind => match self.seek_offset(kind, char_index) {
+        (reached, index) if reached == char_index => Ok(index),
+        _ => Err(JwstCodecError::IndexOutOfBound(char_index)),
+      },
+    }
+  }
+
+  /// the range `[index, index + len)` in the utf-16 code units
+  fn utf16_range(&self, char_index: u64, len: u64) -> JwstCodecResult<(u64, u64)> {
+    match self.offset_kind() {
+      OffsetKind::Utf16 => Ok((char_index, len)),
+      kind => {
+        let index = self.utf16_index(char_index)?;
+        // the range is allowed to exceed the end as the utf-16 one does
+        let end = char_index.saturating_add(len);
+        let (reached, end_index) = self.seek_offset(kind, end);
+
+        Ok((index, end_index + (end - reached) - index))
+      }
+    }
+  }
+
   #[inline]
   pub fn insert<T: ToString>(&mut self, char_index: u64, str: T) -> JwstCodecResult {
-    self.insert_at(char_index, Content::String(str.to_string()))
+    let index = self.utf16_index(char_index)?;
+    self.insert_at(index, Content::String(str.to_string()))
+  }
+
+  /// Insert a string with the given formatting attributes.
+  ///
+  /// Attributes that are active at the insert position but not listed in
+  /// `attrs` are not inherited by the inserted string, same as yjs.
+  #[inline]
+  pub fn insert_with_attributes<T: ToString>(
+    &mut self,
+    char_index: u64,
+    str: T,
+    attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    let index = self.utf16_index(char_index)?;
+    self._insert_with_attributes(index, Content::String(str.to_string()), attrs)
+  }
+
+  /// Insert an embed, e.g. an image or an inline reference, which occupies
+  /// one position in the text.
+  #[inline]
+  pub fn insert_embed<T: Into<Any>>(
+    &mut self,
+    char_index: u64,
+    embed: T,
+    attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    let index = self.utf16_index(char_index)?;
+    self._insert_with_attributes(index, Content::Embed(embed.into()), attrs)
   }
 
   #[inline]
   pub fn remove(&mut self, char_index: u64, len: u64) -> JwstCodecResult {
-    self.remove_at(char_index, len)
+    let (index, len) = self.utf16_range(char_index, len)?;
+    self.remove_at(index, len)
+  }
+
+  /// Apply the formatting attributes to the range `[index, index + len)`.
+  #[inline]
+  pub fn format(&mut self, char_index: u64, len: u64, attrs: TextAttributes) -> JwstCodecResult {
+    let (index, len) = self.utf16_range(char_index, len)?;
+    self._format(index, len, attrs)
+  }
+
+  /// Export the content as a Quill-compatible delta.
+  #[inline]
+  pub fn to_delta(&self) -> Vec<TextDelta> {
+    self._to_delta()
+  }
+
+  /// The delta of the text as of the given [Snapshot].
+  pub fn to_delta_at(&self, snapshot: &Snapshot) -> Vec<TextDelta> {
+    self._to_delta_at(snapshot)
+  }
+
+  /// The content of the text as of the given [Snapshot].
+  pub fn to_string_at(&self, snapshot: &Snapshot) -> String {
+    self._to_string_at(snapshot)
+  }
+
+  /// Apply a Quill-compatible delta to the text, the lengths of the retains
+  /// and deletes are in the unit of [Text::offset_kind].
+  pub fn apply_delta(&mut self, delta: &[TextDelta]) -> JwstCodecResult {
+    match self.offset_kind() {
+      OffsetKind::Utf16 => self._apply_delta(delta),
+      kind => {
+        // the retains and deletes walk through the content before the delta
+        // applied, the inserts take no place of it
+        let delta = {
+          let mut cursor = OffsetCursor::new(self, kind, OffsetKind::Utf16);
+          delta
+            .iter()
+            .map(|op| match op {
+              TextDelta::Retain { retain, attributes } => TextDelta::Retain {
+                retain: cursor.advance_saturating(*retain),
+                attributes: attributes.clone(),
+              },
+              TextDelta::Delete { delete } => TextDelta::Delete {
+                delete: cursor.advance_saturating(*delete),
+              },
+              op => op.clone(),
+            })
+            .collect::<Vec<_>>()
+        };
+
+        self._apply_delta(&delta)
+      }
+    }
+  }
+
+  /// Create a position at the index that survives concurrent edits.
+  pub fn sticky_index(&self, index: u64, assoc: Assoc) -> Option<StickyIndex> {
+    StickyIndex::from_type(&self.0, self.utf16_index(index).ok()?, assoc)
+  }
+
+  /// The content of the text including the deleted one, split by the users
+  /// inserted and deleted it, see [PermanentUserData].
+  pub fn attributions(&self, users: &PermanentUserData) -> Vec<Attribution<String>> {
+    let index = users.index();
+    let mut attributions = Vec::new();
+    let Some(inner) = self.0.ty() else {
+      return attributions;
+    };
+
+    let mut item_ref = inner.start.clone();
+    while let Some(item) = item_ref.get() {
+      if let Content::String(str) = &item.content {
+        index
+          .attribute(item, str.clone())
+          .push_to(&mut attributions, |last, str| last.push_str(&str));
+      }
+      item_ref = item.right.clone();
+    }
+
+    attributions
+  }
+  /// Subscribe the changes made to the text in each transaction.
+  pub fn observe(&self, cb: impl Fn(&TextEvent) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe(move |event| {
+      if let Event::Text(event) = event {
+        cb(event)
+      }
+    })
+  }
+
+  /// Subscribe the changes made to the text and all its nested types in
+  /// each transaction.
+  pub fn observe_deep(&self, cb: impl Fn(&[Event]) + Send + Sync + 'static) -> ObserverId {
+    self.0.observe_deep(cb)
+  }
+
+  pub fn unobserve(&self, id: ObserverId) {
+    self.0.unobserve(id)
+  }
+}
+
+pub(crate) fn update_attributes(attrs: &mut TextAttributes, key: &str, value: &Any) {
+  if matches!(value, Any::Null | Any::Undefined) {
+    attrs.remove(key);
+  } else {
+    attrs.insert(key.to_string(), value.clone());
+  }
+}
+
+/// compare attribute values, treat missing attributes as `null`
+fn attr_eq(attr: Option<&Any>, value: &Any) -> bool {
+  match (attr.unwrap_or(&Any::Null), value) {
+    (Any::Null | Any::Undefined, Any::Null | Any::Undefined) => true,
+    (attr, value) => attr == value,
+  }
+}
+
+/// A cursor in the text that keeps track of the attributes active at it,
+/// the counterpart of `ItemTextListPosition` in yjs.
+struct TextPosition {
+  left: ItemRef,
+  right: ItemRef,
+  index: u64,
+  attrs: TextAttributes,
+}
+
+impl TextPosition {
+  fn new(start: ItemRef) -> Self {
+    Self {
+      left: Somr::none(),
+      right: start,
+      index: 0,
+      attrs: TextAttributes::new(),
+    }
+  }
+
+  /// walk from the start of the text, search markers are not used because we
+  /// need to collect the active attributes on the way
+  fn find(store: &mut DocStore, ty: &YType, index: u64) -> JwstCodecResult<Self> {
+    let mut pos = Self::new(ty.start.clone());
+    pos.advance(store, index)?;
+
+    Ok(pos)
+  }
+
+  fn forward(&mut self) {
+    let right_ref = self.right.clone();
+    if let Some(right) = right_ref.get() {
+      if !right.deleted() {
+        if let Content::Format { key, value } = &right.content {
+          update_attributes(&mut self.attrs, key, value);
+        } else if right.countable() {
+          self.index += right.len();
+        }
+      }
+
+      self.left = right_ref.clone();
+      self.right = right.right.clone();
+    }
+  }
+
+  /// move the cursor `count` positions to the right, splitting the item that
+  /// the cursor ends up in the middle of
+  fn advance(&mut self, store: &mut DocStore, mut count: u64) -> JwstCodecResult {
+    while count > 0 {
+      let right_ref = self.right.clone();
+      let Some(right) = right_ref.get() else {
+        break;
+      };
+
+      if right.indexable() {
+        if count < right.len() {
+          store.split_node(right.id, count)?;
+        }
+        count -= right.len();
+      }
+
+      self.forward();
+    }
+
+    Ok(())
+  }
+
+  fn integrate(
+    &mut self,
+    store: &mut DocStore,
+    ty: &mut YType,
+    parent: &YTypeRef,
+    content: Content,
+  ) -> JwstCodecResult {
+    let item = store.create_item(
+      content,
+      self.left.clone(),
+      self.right.clone(),
+      Some(Parent::Type(parent.clone())),
+      None,
+    );
+    let item_ref = item.clone();
+
+    store.integrate(Node::Item(item), 0, Some(ty))?;
+
+    self.right = item_ref;
+    self.forward();
+
+    Ok(())
+  }
+
+  fn insert(
+    &mut self,
+    store: &mut DocStore,
+    ty: &mut YType,
+    parent: &YTypeRef,
+    content: Content,
+    mut attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    // attributes active at the position but not given are removed
+    for key in self.attrs.keys() {
+      attrs.entry(key.clone()).or_insert(Any::Null);
+    }
+
+    self.minimize_attribute_changes(&attrs);
+    let negated_attrs = self.insert_attributes(store, ty, parent, &attrs)?;
+
+    if let Some(markers) = &ty.markers {
+      markers.update_marker_changes(self.index, content.clock_len() as i64);
+    }
+    self.integrate(store, ty, parent, content)?;
+
+    self.insert_negated_attributes(store, ty, parent, negated_attrs)
+  }
+
+  fn format(
+    &mut self,
+    store: &mut DocStore,
+    ty: &mut YType,
+    parent: &YTypeRef,
+    mut len: u64,
+    attrs: &TextAttributes,
+  ) -> JwstCodecResult {
+    self.minimize_attribute_changes(attrs);
+    let mut negated_attrs = self.insert_attributes(store, ty, parent, attrs)?;
+
+    // walk through the range, remove the formats that will be overwritten and
+    // also check the formats right after the range to avoid redundant negated
+    // attributes
+    loop {
+      let right_ref = self.right.clone();
+      let Some(right) = right_ref.get() else {
+        break;
+      };
+
+      let is_format = matches!(right.content, Content::Format { .. });
+      if len == 0 && (negated_attrs.is_empty() || !(right.deleted() || is_format)) {
+        break;
+      }
+
+      if !right.deleted() {
+        if let Content::Format { key, value } = &right.content {
+          if let Some(attr) = attrs.get(key) {
+            if attr_eq(Some(attr), value) {
+              negated_attrs.remove(key);
+            } else {
+              if len == 0 {
+                break;
+              }
+              negated_attrs.insert(key.clone(), value.clone());
+            }
+            store.delete_item(right, Some(ty));
+          } else {
+            self.attrs.insert(key.clone(), value.clone());
+          }
+        } else if right.countable() {
+          if len < right.len() {
+            store.split_node(right.id, len)?;
+          }
+          len -= right.len();
+        }
+      }
+
+      self.forward();
+    }
+
+    // quill assumes that the content always ends with a newline, insert the
+    // missing newlines if the range is longer than the text
+    if len > 0 {
+      if let Some(markers) = &ty.markers {
+        markers.update_marker_changes(self.index, len as i64);
+      }
+      self.integrate(
+        store,
+        ty,
+        parent,
+        Content::String("\n".repeat(len as usize)),
+      )?;
+    }
+
+    self.insert_negated_attributes(store, ty, parent, negated_attrs)
+  }
+
+  fn delete(&mut self, store: &mut DocStore, ty: &mut YType, len: u64) -> JwstCodecResult {
+    let mut remaining = len;
+
+    while remaining > 0 {
+      let right_ref = self.right.clone();
+      let Some(right) = right_ref.get() else {
+        break;
+      };
+
+      if right.indexable() {
+        if remaining < right.len() {
+          store.split_node(right.id, remaining)?;
+        }
+        remaining -= right.len();
+        store.delete_item(right, Some(ty));
+      }
+
+      self.forward();
+    }
+
+    if let Some(markers) = &ty.markers {
+      markers.update_marker_changes(self.index, -((len - remaining) as i64));
+    }
+
+    Ok(())
+  }
+
+  /// skip the formats that already match the given attributes
+  fn minimize_attribute_changes(&mut self, attrs: &TextAttributes) {
+    loop {
+      let matched = self.right.get().is_some_and(|right| {
+        right.deleted()
+          || matches!(&right.content, Content::Format { key, value } if attr_eq(attrs.get(key), value))
+      });
+
+      if !matched {
+        break;
+      }
+
+      self.forward();
+    }
+  }
+
+  /// insert the format items that differ from the current attributes,
+  /// returns the attributes to restore after the formatted content
+  fn insert_attributes(
+    &mut self,
+    store: &mut DocStore,
+    ty: &mut YType,
+    parent: &YTypeRef,
+    attrs: &TextAttributes,
+  ) -> JwstCodecResult<TextAttributes> {
+    let mut negated_attrs = TextAttributes::new();
+
+    for (key, value) in attrs {
+      let current = self.attrs.get(key).cloned().unwrap_or(Any::Null);
+      if !attr_eq(Some(&current), value) {
+        negated_attrs.insert(key.clone(), current);
+        self.integrate(
+          store,
+          ty,
+          parent,
+          Content::Format {
+            key: key.clone(),
+            value: value.clone(),
+          },
+        )?;
+      }
+    }
+
+    Ok(negated_attrs)
+  }
+
+  fn insert_negated_attributes(
+    &mut self,
+    store: &mut DocStore,
+    ty: &mut YType,
+    parent: &YTypeRef,
+    mut negated_attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    // the following formats may already restore the attributes
+    loop {
+      let right_ref = self.right.clone();
+      let Some(right) = right_ref.get() else {
+        break;
+      };
+
+      if !right.deleted() {
+        match &right.content {
+          Content::Format { key, value } if negated_attrs.get(key) == Some(value) => {
+            negated_attrs.remove(key);
+          }
+          _ => break,
+        }
+      }
+
+      self.forward();
+    }
+
+    for (key, value) in negated_attrs {
+      self.integrate(store, ty, parent, Content::Format { key, value })?;
+    }
+
+    Ok(())
   }
 }
 
 impl Display for Text {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    self.iter_item().try_for_each(|item| {
-      if let Content::String(str) = &item.get().unwrap().content {
-        write!(f, "{}", str)
-      } else {
-        Ok(())
-      }
-    })
+    write!(f, "{}", self._to_string())
   }
 }
 
@@ -50,15 +776,95 @@ impl serde::Serialize for Text {
   }
 }
 
+impl From<TextDelta> for Any {
+  fn from(value: TextDelta) -> Self {
+    let mut op = HashMap::new();
+    let attributes = match value {
+      TextDelta::Insert { insert, attributes } => {
+        op.insert(
+          "insert".to_string(),
+          match insert {
+            TextInsert::Text(str) => Any::String(str),
+            TextInsert::Embed(embed) => embed,
+          },
+        );
+        attributes
+      }
+      TextDelta::Retain { retain, attributes } => {
+        op.insert("retain".to_string(), Any::from(retain));
+        attributes
+      }
+      TextDelta::Delete { delete } => {
+        op.insert("delete".to_string(), Any::from(delete));
+        None
+      }
+    };
+
+    if let Some(attributes) = attributes {
+      op.insert("attributes".to_string(), Any::Object(attributes));
+    }
+
+    Any::Object(op)
+  }
+}
+
+impl TryFrom<Any> for TextDelta {
+  type Error = JwstCodecError;
+
+  fn try_from(value: Any) -> Result<Self, Self::Error> {
+    fn to_len(value: &Any) -> JwstCodecResult<u64> {
+      match value {
+        Any::Integer(len) if *len >= 0 => Ok(*len as u64),
+        Any::BigInt64(len) if *len >= 0 => Ok(*len as u64),
+        Any::Float32(len) if len.0 >= 0.0 => Ok(len.0 as u64),
+        Any::Float64(len) if len.0 >= 0.0 => Ok(len.0 as u64),
+        _ => Err(JwstCodecError::UnexpectedType("non-negative number")),
+      }
+    }
+
+    let Any::Object(mut op) = value else {
+      return Err(JwstCodecError::UnexpectedType("object"));
+    };
+
+    let attributes = match op.remove("attributes") {
+      Some(Any::Object(attributes)) => Some(attributes),
+      Some(Any::Null | Any::Undefined) | None => None,
+      Some(_) => return Err(JwstCodecError::UnexpectedType("object")),
+    };
+
+    if let Some(insert) = op.remove("insert") {
+      Ok(TextDelta::Insert {
+        insert: match insert {
+          Any::String(str) => TextInsert::Text(str),
+          embed => TextInsert::Embed(embed),
+        },
+        attributes,
+      })
+    } else if let Some(retain) = op.get("retain") {
+      Ok(TextDelta::Retain {
+        retain: to_len(retain)?,
+        attributes,
+      })
+    } else if let Some(delete) = op.get("delete") {
+      Ok(TextDelta::Delete {
+        delete: to_len(delete)?,
+      })
+    } else {
+      Err(JwstCodecError::UnexpectedType("insert, retain or delete"))
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use rand::{Rng, SeedableRng};
   use rand_chacha::ChaCha20Rng;
   use yrs::{Options, Text, Transact};
 
+  use super::{TextAttributes, TextDelta, TextInsert};
   #[cfg(not(loom))]
   use crate::sync::{Arc, AtomicUsize, Ordering};
-  use crate::{loom_model, sync::thread, Doc};
+  use crate::{loom_model, sync::thread, Any, Doc, JwstCodecResult};
 
   #[test]
   fn test_manipulate_text() {
@@ -234,6 +1040,204 @@ mod tests {
     });
   }
 
+  fn attrs(attrs: &[(&str, Any)]) -> TextAttributes {
+    attrs
+      .iter()
+      .map(|(key, value)| (key.to_string(), value.clone()))
+      .collect()
+  }
+
+  #[test]
+  fn test_format_text() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+
+      text.insert(0, "hello world").unwrap();
+      text.format(0, 5, attrs(&[("bold", Any::True)])).unwrap();
+      text
+        .insert_with_attributes(11, "!", attrs(&[("italic", Any::True)]))
+        .unwrap();
+
+      assert_eq!(text.to_string(), "hello world!");
+      assert_eq!(text.len(), 12);
+      assert_eq!(
+        text.to_delta(),
+        vec![
+          TextDelta::Insert {
+            insert: TextInsert::Text("hello".into()),
+            attributes: Some(attrs(&[("bold", Any::True)])),
+          },
+          TextDelta::Insert {
+            insert: TextInsert::Text(" world".into()),
+            attributes: None,
+          },
+          TextDelta::Insert {
+            insert: TextInsert::Text("!".into()),
+            attributes: Some(attrs(&[("italic", Any::True)])),
+          },
+        ]
+      );
+
+      // remove the bold attribute, formats should be cleaned up
+      text.format(0, 5, attrs(&[("bold", Any::Null)])).unwrap();
+      // formats don't occupy positions
+      text.insert(5, ",").unwrap();
+      text.remove(0, 1).unwrap();
+
+      assert_eq!(
+        text.to_delta(),
+        vec![
+          TextDelta::Insert {
+            insert: TextInsert::Text("ello, world".into()),
+            attributes: None,
+          },
+          TextDelta::Insert {
+            insert: TextInsert::Text("!".into()),
+            attributes: Some(attrs(&[("italic", Any::True)])),
+          },
+        ]
+      );
+    });
+  }
+
+  #[test]
+  fn test_apply_delta() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+
+      let delta: Vec<TextDelta> = serde_json::from_value(serde_json::json!([
+        { "insert": "hello " },
+        { "insert": "world", "attributes": { "link": "https://affine.pro" } },
+        { "insert": { "image": "blob" } },
+      ]))
+      .unwrap();
+      text.apply_delta(&delta).unwrap();
+
+      assert_eq!(text.to_string(), "hello world");
+      assert_eq!(text.len(), 12);
+      assert_eq!(
+        serde_json::to_value(text.to_delta()).unwrap(),
+        serde_json::json!([
+          { "insert": "hello " },
+          { "insert": "world", "attributes": { "link": "https://affine.pro" } },
+          { "insert": { "image": "blob" } },
+        ])
+      );
+
+      let delta: Vec<TextDelta> = serde_json::from_value(serde_json::json!([
+        { "retain": 1 },
+        { "delete": 4 },
+        { "retain": 6, "attributes": { "bold": true } },
+      ]))
+      .unwrap();
+      text.apply_delta(&delta).unwrap();
+
+      assert_eq!(
+        serde_json::to_value(text.to_delta()).unwrap(),
+        serde_json::json!([
+          { "insert": "h" },
+          { "insert": " ", "attributes": { "bold": true } },
+          { "insert": "world", "attributes": { "bold": true, "link": "https://affine.pro" } },
+          { "insert": { "image": "blob" } },
+        ])
+      );
+
+      let any = text
+        .to_delta()
+        .into_iter()
+        .map(Any::from)
+        .collect::<Vec<_>>();
+      assert_eq!(
+        any
+          .into_iter()
+          .map(TextDelta::try_from)
+          .collect::<JwstCodecResult<Vec<_>>>()
+          .unwrap(),
+        text.to_delta()
+      );
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_format_compatibility() {
+    use yrs::{types::Attrs, updates::decoder::Decode, GetString, ReadTxn, Update};
+
+    loom_model!({
+      // yrs -> y-octo
+      let binary = {
+        let doc = yrs::Doc::new();
+        let text = doc.get_or_insert_text("text");
+        let mut trx = doc.transact_mut();
+        text.insert(&mut trx, 0, "hello world");
+        text.format(
+          &mut trx,
+          6,
+          5,
+          Attrs::from([("bold".into(), yrs::Any::Bool(true))]),
+        );
+        text.insert_with_attributes(
+          &mut trx,
+          0,
+          "> ",
+          Attrs::from([("italic".into(), yrs::Any::Bool(true))]),
+        );
+
+        trx.encode_update_v1()
+      };
+
+      let doc = Doc::try_from_binary_v1(binary).unwrap();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      assert_eq!(text.to_string(), "> hello world");
+      assert_eq!(
+        serde_json::to_value(text.to_delta()).unwrap(),
+        serde_json::json!([
+          { "insert": "> ", "attributes": { "italic": true } },
+          { "insert": "hello " },
+          { "insert": "world", "attributes": { "bold": true } },
+        ])
+      );
+
+      // y-octo -> yrs
+      text.format(0, 8, attrs(&[("bold", Any::True)])).unwrap();
+      let binary = doc.encode_update_v1().unwrap();
+
+      let doc = yrs::Doc::new();
+      let text = doc.get_or_insert_text("text");
+      let mut trx = doc.transact_mut();
+      trx
+        .apply_update(Update::decode_v1(&binary).unwrap())
+        .unwrap();
+
+      assert_eq!(text.get_string(&trx), "> hello world");
+      let diff = text
+        .diff(&trx, yrs::types::text::YChange::identity)
+        .into_iter()
+        .map(|diff| {
+          let mut keys = diff
+            .attributes
+            .map(|attrs| attrs.keys().map(|key| key.to_string()).collect::<Vec<_>>())
+            .unwrap_or_default();
+          keys.sort();
+          (diff.insert.to_string(&trx), keys)
+        })
+        .collect::<Vec<_>>();
+      assert_eq!(
+        diff,
+        vec![
+          (
+            "> ".to_string(),
+            vec!["bold".to_string(), "italic".to_string()]
+          ),
+          ("hello world".to_string(), vec!["bold".to_string()]),
+        ]
+      );
+      assert!(trx.store().pending_update().is_none());
+    });
+  }
+
   #[test]
   #[cfg_attr(miri, ignore)]
   fn test_recover_from_yjs_encoder() {
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use super::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
   Any(Any),
   Doc(Doc),
@@ -23,6 +23,13 @@ impl Value {
     }
   }
 
+  pub fn to_doc(&self) -> Option<Doc> {
+    match self {
+      Value::Doc(doc) => Some(doc.clone()),
+      _ => None,
+    }
+  }
+
   pub fn to_array(&self) -> Option<Array> {
     match self {
       Value::Array(array) => Some(array.clone()),
@@ -44,6 +51,48 @@ impl Value {
     }
   }
 
+  pub fn to_xml_element(&self) -> Option<XMLElement> {
+    match self {
+      Value::XMLElement(element) => Some(element.clone()),
+      _ => None,
+    }
+  }
+
+  pub fn to_xml_fragment(&self) -> Option<XMLFragment> {
+    match self {
+      Value::XMLFragment(fragment) => Some(fragment.clone()),
+      _ => None,
+    }
+  }
+
+  pub fn to_xml_text(&self) -> Option<XMLText> {
+    match self {
+      Value::XMLText(text) => Some(text.clone()),
+      _ => None,
+    }
+  }
+
+  pub fn to_xml_hook(&self) -> Option<XMLHook> {
+    match self {
+      Value::XMLHook(hook) => Some(hook.clone()),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn from_type(ty: YTypeRef, kind: YTypeKind) -> Self {
+    match kind {
+      YTypeKind::Array => Value::Array(Array::from_unchecked(ty)),
+      YTypeKind::Map => Value::Map(Map::from_unchecked(ty)),
+      YTypeKind::Text => Value::Text(Text::from_unchecked(ty)),
+      YTypeKind::XMLElement => Value::XMLElement(XMLElement::from_unchecked(ty)),
+      YTypeKind::XMLFragment => Value::XMLFragment(XMLFragment::from_unchecked(ty)),
+      YTypeKind::XMLHook => Value::XMLHook(XMLHook::from_unchecked(ty)),
+      YTypeKind::XMLText => Value::XMLText(XMLText::from_unchecked(ty)),
+      // actually unreachable
+      YTypeKind::Unknown => Value::Any(Any::Undefined),
+    }
+  }
+
   pub fn from_vec<T: Into<Any>>(el: Vec<T>) -> Self {
     Value::Any(Any::Array(
       el.into_iter().map(|item| item.into()).collect::<Vec<_>>(),
@@ -74,23 +123,12 @@ impl From<&Content> for Value {
       )),
       Content::Binary(buf) => Value::Any(Any::Binary(buf.clone())),
       Content::Embed(v) => Value::Any(v.clone()),
-      Content::Type(ty) => match ty.ty().unwrap().kind {
-        YTypeKind::Array => Value::Array(Array::from_unchecked(ty.clone())),
-        YTypeKind::Map => Value::Map(Map::from_unchecked(ty.clone())),
-        YTypeKind::Text => Value::Text(Text::from_unchecked(ty.clone())),
-        YTypeKind::XMLElement => Value::XMLElement(XMLElement::from_unchecked(ty.clone())),
-        YTypeKind::XMLFragment => Value::XMLFragment(XMLFragment::from_unchecked(ty.clone())),
-        YTypeKind::XMLHook => Value::XMLHook(XMLHook::from_unchecked(ty.clone())),
-        YTypeKind::XMLText => Value::XMLText(XMLText::from_unchecked(ty.clone())),
-        // actually unreachable
-        YTypeKind::Unknown => Value::Any(Any::Undefined),
-      },
-      Content::Doc { guid: _, opts } => Value::Doc(
-        DocOptions::try_from(opts.clone())
-          .expect("Failed to parse doc options")
-          .build(),
-      ),
-      Content::Format { .. } => unimplemented!(),
+      Content::Type(ty) => Value::from_type(ty.clone(), ty.ty().unwrap().kind),
+      Content::Doc { .. } => Value::Doc(value.subdoc().unwrap()),
+      // formats are markers of text ranges, they don't carry a value
+      Content::Format { .. } => Value::Any(Any::Undefined),
+      // moves only change where other items are shown
+      Content::Move(_) => Value::Any(Any::Undefined),
       // actually unreachable
       Content::Deleted(_) => Value::Any(Any::Undefined),
     }
@@ -104,6 +142,7 @@ impl From<Value> for Content {
       Value::Doc(doc) => Content::Doc {
         guid: doc.guid().to_owned(),
         opts: Any::from(doc.options().clone()),
+        doc: std::sync::Arc::new(Some(doc).into()),
       },
       Value::Array(v) => Content::Type(v.0),
       Value::Map(v) => Content::Type(v.0),
@@ -133,6 +172,9 @@ impl Display for Value {
     match self {
       Value::Any(any) => write!(f, "{}", any),
       Value::Text(text) => write!(f, "{}", text),
+      Value::XMLElement(element) => write!(f, "{}", element),
+      Value::XMLFragment(fragment) => write!(f, "{}", fragment),
+      Value::XMLText(text) => write!(f, "{}", text),
       _ => write!(f, ""),
     }
   }
@@ -148,10 +190,10 @@ impl serde::Serialize for Value {
       Self::Array(array) => array.serialize(serializer),
       Self::Map(map) => map.serialize(serializer),
       Self::Text(text) => text.serialize(serializer),
-      // Self::XMLElement(xml_element) => xml_element.serialize(serializer),
-      // Self::XMLFragment(xml_fragment) => xml_fragment.serialize(serializer),
-      // Self::XMLHook(xml_hook) => xml_hook.serialize(serializer),
-      // Self::XMLText(xml_text) => xml_text.serialize(serializer),
+      Self::XMLElement(xml_element) => xml_element.serialize(serializer),
+      Self::XMLFragment(xml_fragment) => xml_fragment.serialize(serializer),
+      Self::XMLHook(xml_hook) => xml_hook.serialize(serializer),
+      Self::XMLText(xml_text) => xml_text.serialize(serializer),
       // Self::Doc(doc) => doc.serialize(serializer),
       _ => serializer.serialize_none(),
     }
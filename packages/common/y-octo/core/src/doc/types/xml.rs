@@ -1,14 +1,577 @@
-use super::list::ListType;
+use std::fmt::Display;
+
+use super::{list::ListType, map::MapType, text::TextType, *};
 use crate::impl_type;
 
 impl_type!(XMLElement);
 impl ListType for XMLElement {}
+impl MapType for XMLElement {}
 
 impl_type!(XMLFragment);
 impl ListType for XMLFragment {}
 
 impl_type!(XMLText);
 impl ListType for XMLText {}
+impl TextType for XMLText {}
 
 impl_type!(XMLHook);
-impl ListType for XMLHook {}
+impl MapType for XMLHook {}
+
+/// implement the methods shared by the xml types that can hold children,
+/// the children can be [XMLElement], [XMLText] or [XMLHook]
+macro_rules! impl_xml_children {
+  ($name: ident) => {
+    impl $name {
+      #[inline]
+      pub fn len(&self) -> u64 {
+        self.content_len()
+      }
+
+      #[inline]
+      pub fn is_empty(&self) -> bool {
+        self.len() == 0
+      }
+
+      pub fn get(&self, index: u64) -> Option<Value> {
+        let (item, _) = self.get_item_at(index)?;
+
+        item.get().map(|item| Value::from(&item.content))
+      }
+
+      pub fn first_child(&self) -> Option<Value> {
+        self.children().next()
+      }
+
+      pub fn children(&self) -> XMLChildren {
+        XMLChildren(self.iter_item())
+      }
+
+      /// All the descendants of the node in document order (depth first).
+      pub fn descendants(&self) -> Vec<Value> {
+        let mut nodes = Vec::new();
+        collect_descendants(self.children(), &mut nodes);
+        nodes
+      }
+
+      pub fn insert<V: Into<Value>>(&mut self, index: u64, node: V) -> JwstCodecResult {
+        let node = node.into();
+        if !matches!(
+          node,
+          Value::XMLElement(_) | Value::XMLText(_) | Value::XMLHook(_)
+        ) {
+          return Err(JwstCodecError::UnexpectedType(
+            "XMLElement, XMLText or XMLHook",
+          ));
+        }
+
+        self.insert_at(index, node.into())
+      }
+
+      pub fn push<V: Into<Value>>(&mut self, node: V) -> JwstCodecResult {
+        self.insert(self.len(), node)
+      }
+
+      pub fn remove(&mut self, index: u64, len: u64) -> JwstCodecResult {
+        self.remove_at(index, len)
+      }
+
+      fn children_to_xml_string(&self) -> String {
+        self.children().map(|child| xml_string(&child)).collect()
+      }
+    }
+  };
+}
+
+/// implement the methods shared by the xml types that can be a child of
+/// another xml type
+macro_rules! impl_xml_node {
+  ($name: ident) => {
+    impl $name {
+      pub fn parent(&self) -> Option<Value> {
+        let ty = self.as_inner().ty()?;
+        let item = ty.item.get()?;
+
+        match &item.parent {
+          Some(Parent::Type(parent)) => Some(Value::from(&Content::Type(parent.clone()))),
+          _ => None,
+        }
+      }
+
+      pub fn prev_sibling(&self) -> Option<Value> {
+        sibling(self.as_inner(), |item| item.left.clone())
+      }
+
+      pub fn next_sibling(&self) -> Option<Value> {
+        sibling(self.as_inner(), |item| item.right.clone())
+      }
+    }
+  };
+}
+
+impl_xml_children!(XMLFragment);
+impl_xml_children!(XMLElement);
+impl_xml_node!(XMLElement);
+impl_xml_node!(XMLText);
+impl_xml_node!(XMLHook);
+
+impl XMLFragment {
+  pub fn to_xml_string(&self) -> String {
+    self.children_to_xml_string()
+  }
+}
+
+impl XMLElement {
+  /// The tag name of the element.
+  pub fn node_name(&self) -> String {
+    self
+      .as_inner()
+      .ty()
+      .and_then(|ty| ty.name.clone())
+      .unwrap_or_default()
+  }
+
+  #[inline(always)]
+  pub fn get_attribute(&self, key: &str) -> Option<Value> {
+    self._get(key)
+  }
+
+  #[inline(always)]
+  pub fn insert_attribute<V: Into<Value>>(&mut self, key: String, value: V) -> JwstCodecResult {
+    self._insert(key, value)
+  }
+
+  #[inline(always)]
+  pub fn contains_attribute(&self, key: &str) -> bool {
+    self._contains_key(key)
+  }
+
+  #[inline(always)]
+  pub fn remove_attribute(&mut self, key: &str) {
+    self._remove(key)
+  }
+
+  #[inline(always)]
+  pub fn attributes(&self) -> EntriesIterator {
+    self._entries()
+  }
+
+  /// Serialize the element in the same format as `Y.XmlElement#toString`,
+  /// attributes are sorted by key.
+  pub fn to_xml_string(&self) -> String {
+    let node_name = self.node_name().to_lowercase();
+
+    let mut attrs = self
+      .attributes()
+      .map(|(key, value)| (key.to_string(), attribute_string(&value)))
+      .collect::<Vec<_>>();
+    attrs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let attrs = attrs
+      .into_iter()
+      .map(|(key, value)| format!(" {key}=\"{value}\""))
+      .collect::<String>();
+
+    format!(
+      "<{node_name}{attrs}>{}</{node_name}>",
+      self.children_to_xml_string()
+    )
+  }
+}
+
+impl XMLText {
+  #[inline]
+  pub fn len(&self) -> u64 {
+    self.content_len()
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  #[inline]
+  pub fn insert<T: ToString>(&mut self, char_index: u64, str: T) -> JwstCodecResult {
+    self.insert_at(char_index, Content::String(str.to_string()))
+  }
+
+  #[inline]
+  pub fn insert_with_attributes<T: ToString>(
+    &mut self,
+    char_index: u64,
+    str: T,
+    attrs: TextAttributes,
+  ) -> JwstCodecResult {
+    self._insert_with_attributes(char_index, Content::String(str.to_string()), attrs)
+  }
+
+  #[inline]
+  pub fn remove(&mut self, char_index: u64, len: u64) -> JwstCodecResult {
+    self.remove_at(char_index, len)
+  }
+
+  #[inline]
+  pub fn format(&mut self, char_index: u64, len: u64, attrs: TextAttributes) -> JwstCodecResult {
+    self._format(char_index, len, attrs)
+  }
+
+  #[inline]
+  pub fn to_delta(&self) -> Vec<TextDelta> {
+    self._to_delta()
+  }
+
+  #[inline]
+  pub fn apply_delta(&mut self, delta: &[TextDelta]) -> JwstCodecResult {
+    self._apply_delta(delta)
+  }
+
+  /// The plain text content without formats.
+  #[inline]
+  pub fn text(&self) -> String {
+    self._to_string()
+  }
+
+  /// Serialize the text in the same format as `Y.XmlText#toString`, the
+  /// formats are rendered as nested tags, e.g. `{"a": {"href": "..."}}`
+  /// becomes `<a href="...">text</a>`.
+  pub fn to_xml_string(&self) -> String {
+    self
+      .to_delta()
+      .into_iter()
+      .map(|delta| {
+        let TextDelta::Insert { insert, attributes } = delta else {
+          return String::new();
+        };
+
+        let mut nodes = attributes
+          .unwrap_or_default()
+          .into_iter()
+          .map(|(node_name, attrs)| {
+            let mut attrs = match attrs {
+              Any::Object(attrs) => attrs
+                .into_iter()
+                .map(|(key, value)| (key, attribute_string(&Value::Any(value))))
+                .collect::<Vec<_>>(),
+              _ => vec![],
+            };
+            attrs.sort_by(|a, b| a.0.cmp(&b.0));
+            (node_name, attrs)
+          })
+          .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut str = String::new();
+        for (node_name, attrs) in &nodes {
+          str.push_str(&format!("<{node_name}"));
+          for (key, value) in attrs {
+            str.push_str(&format!(" {key}=\"{value}\""));
+          }
+          str.push('>');
+        }
+        match insert {
+          TextInsert::Text(text) => str.push_str(&text),
+          TextInsert::Embed(embed) => str.push_str(&attribute_string(&Value::Any(embed))),
+        }
+        for (node_name, _) in nodes.iter().rev() {
+          str.push_str(&format!("</{node_name}>"));
+        }
+
+        str
+      })
+      .collect()
+  }
+}
+
+impl XMLHook {
+  /// The name of the hook.
+  pub fn hook_name(&self) -> String {
+    self
+      .as_inner()
+      .ty()
+      .and_then(|ty| ty.name.clone())
+      .unwrap_or_default()
+  }
+
+  #[inline(always)]
+  pub fn get(&self, key: &str) -> Option<Value> {
+    self._get(key)
+  }
+
+  #[inline(always)]
+  pub fn insert<V: Into<Value>>(&mut self, key: String, value: V) -> JwstCodecResult {
+    self._insert(key, value)
+  }
+
+  #[inline(always)]
+  pub fn remove(&mut self, key: &str) {
+    self._remove(key)
+  }
+
+  #[inline(always)]
+  pub fn len(&self) -> u64 {
+    self._len()
+  }
+
+  #[inline(always)]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  #[inline(always)]
+  pub fn entries(&self) -> EntriesIterator {
+    self._entries()
+  }
+}
+
+pub struct XMLChildren<'a>(ListIterator<'a>);
+
+impl Iterator for XMLChildren<'_> {
+  type Item = Value;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    for item in self.0.by_ref() {
+      if let Some(item) = item.get() {
+        if item.countable() {
+          return Some(Value::from(&item.content));
+        }
+      }
+    }
+
+    None
+  }
+}
+
+fn collect_descendants(children: XMLChildren, nodes: &mut Vec<Value>) {
+  for child in children {
+    let grand_children = match &child {
+      Value::XMLElement(element) => Some(element.clone()),
+      _ => None,
+    };
+
+    nodes.push(child);
+
+    if let Some(element) = grand_children {
+      collect_descendants(element.children(), nodes);
+    }
+  }
+}
+
+fn sibling(ty: &YTypeRef, next: impl Fn(&Item) -> ItemRef) -> Option<Value> {
+  let ty = ty.ty()?;
+  let mut cur = ty.item.get().map(&next)?;
+
+  while let Some(item) = cur.get() {
+    if item.indexable() {
+      return Some(Value::from(&item.content));
+    }
+    cur = next(item);
+  }
+
+  None
+}
+
+fn attribute_string(value: &Value) -> String {
+  match value {
+    Value::Any(Any::String(str)) => str.clone(),
+    value => xml_string(value),
+  }
+}
+
+fn xml_string(value: &Value) -> String {
+  match value {
+    Value::XMLElement(element) => element.to_xml_string(),
+    Value::XMLFragment(fragment) => fragment.to_xml_string(),
+    Value::XMLText(text) => text.to_xml_string(),
+    value => value.to_string(),
+  }
+}
+
+impl Display for XMLElement {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.to_xml_string())
+  }
+}
+
+impl Display for XMLFragment {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.to_xml_string())
+  }
+}
+
+impl Display for XMLText {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.to_xml_string())
+  }
+}
+
+impl serde::Serialize for XMLElement {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_xml_string())
+  }
+}
+
+impl serde::Serialize for XMLFragment {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_xml_string())
+  }
+}
+
+impl serde::Serialize for XMLText {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_xml_string())
+  }
+}
+
+impl serde::Serialize for XMLHook {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(Some(self.len() as usize))?;
+    for (key, value) in self.entries() {
+      map.serialize_entry(&key, &value)?;
+    }
+    map.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use yrs::{
+    types::Attrs, updates::decoder::Decode, GetString, Text as _, Transact, Update, Xml as _,
+    XmlElementPrelim, XmlFragment as _, XmlTextPrelim,
+  };
+
+  use super::*;
+  use crate::{loom_model, Doc};
+
+  #[test]
+  fn test_xml_manipulate() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut fragment = doc.get_or_create_xml_fragment("prosemirror").unwrap();
+
+      let mut paragraph = doc.create_xml_element("paragraph").unwrap();
+      fragment.push(paragraph.clone()).unwrap();
+      paragraph.insert_attribute("align".into(), "left").unwrap();
+
+      let mut text = doc.create_xml_text().unwrap();
+      paragraph.push(text.clone()).unwrap();
+      text.insert(0, "hello world").unwrap();
+      text
+        .format(
+          0,
+          5,
+          TextAttributes::from_iter([("bold".into(), Any::Object(Default::default()))]),
+        )
+        .unwrap();
+
+      let heading = doc.create_xml_element("heading").unwrap();
+      fragment.insert(0, heading.clone()).unwrap();
+
+      assert_eq!(paragraph.node_name(), "paragraph");
+      assert_eq!(
+        paragraph.get_attribute("align"),
+        Some(Value::Any(Any::String("left".into())))
+      );
+      assert_eq!(text.text(), "hello world");
+      assert_eq!(fragment.len(), 2);
+      assert_eq!(
+        fragment.to_xml_string(),
+        "<heading></heading><paragraph align=\"left\"><bold>hello</bold> world</paragraph>"
+      );
+
+      // traversal
+      assert_eq!(
+        fragment.first_child(),
+        Some(Value::XMLElement(heading.clone()))
+      );
+      assert_eq!(
+        heading.next_sibling(),
+        Some(Value::XMLElement(paragraph.clone()))
+      );
+      assert_eq!(
+        paragraph.prev_sibling(),
+        Some(Value::XMLElement(heading.clone()))
+      );
+      assert_eq!(text.parent(), Some(Value::XMLElement(paragraph.clone())));
+      assert_eq!(
+        fragment.descendants(),
+        vec![
+          Value::XMLElement(heading.clone()),
+          Value::XMLElement(paragraph.clone()),
+          Value::XMLText(text.clone()),
+        ]
+      );
+
+      // only xml nodes are allowed as children
+      assert!(fragment.push("text").is_err());
+
+      paragraph.remove_attribute("align");
+      fragment.remove(0, 1).unwrap();
+      assert_eq!(
+        fragment.to_xml_string(),
+        "<paragraph><bold>hello</bold> world</paragraph>"
+      );
+      assert_eq!(paragraph.prev_sibling(), None);
+    });
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore)]
+  fn test_xml_compatibility() {
+    loom_model!({
+      // yrs -> y-octo
+      let binary = {
+        let doc = yrs::Doc::new();
+        let fragment = doc.get_or_insert_xml_fragment("prosemirror");
+        let mut trx = doc.transact_mut();
+        let paragraph = fragment.push_back(&mut trx, XmlElementPrelim::empty("paragraph"));
+        paragraph.insert_attribute(&mut trx, "align", "left");
+        let text = paragraph.push_back(&mut trx, XmlTextPrelim::new("hello world"));
+        text.format(
+          &mut trx,
+          6,
+          5,
+          Attrs::from([(
+            "a".into(),
+            yrs::Any::from(std::collections::HashMap::from([(
+              "href".to_string(),
+              yrs::Any::from("https://affine.pro"),
+            )])),
+          )]),
+        );
+        fragment.insert(&mut trx, 0, XmlElementPrelim::empty("heading"));
+
+        trx.encode_update_v1()
+      };
+
+      let doc = Doc::try_from_binary_v1(binary).unwrap();
+      let fragment = doc.get_or_create_xml_fragment("prosemirror").unwrap();
+      let expected = "<heading></heading><paragraph align=\"left\">hello <a \
+                      href=\"https://affine.pro\">world</a></paragraph>";
+      assert_eq!(fragment.to_xml_string(), expected);
+
+      // y-octo -> yrs
+      let mut paragraph = fragment
+        .get(1)
+        .and_then(|value| value.to_xml_element())
+        .unwrap();
+      let mut text = doc.create_xml_text().unwrap();
+      paragraph.insert(0, text.clone()).unwrap();
+      text.insert(0, "> ").unwrap();
+
+      let binary = doc.encode_update_v1().unwrap();
+      let doc = yrs::Doc::new();
+      let fragment = doc.get_or_insert_xml_fragment("prosemirror");
+      let mut trx = doc.transact_mut();
+      trx
+        .apply_update(Update::decode_v1(&binary).unwrap())
+        .unwrap();
+
+      assert_eq!(
+        fragment.get_string(&trx),
+        "<heading></heading><paragraph align=\"left\">> hello <a \
+         href=\"https://affine.pro\">world</a></paragraph>"
+      );
+    });
+  }
+}
@@ -0,0 +1,921 @@
+use std::{
+  collections::BTreeMap,
+  mem,
+  ops::Range,
+  sync::atomic::{AtomicUsize, Ordering},
+  time::{Duration, Instant},
+};
+
+use super::{
+  store::{ChangeRecorder, StoreRef},
+  transaction::transact,
+  *,
+};
+
+const DEFAULT_CAPTURE_TIMEOUT: Duration = Duration::from_millis(500);
+
+static UNDO_MANAGER_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone)]
+pub struct UndoManagerOptions {
+  /// Changes made within this duration after the previous one are merged into
+  /// the same stack item.
+  pub capture_timeout: Duration,
+  /// Origins of the changes to be tracked, changes made without origin are
+  /// always tracked.
+  pub tracked_origins: HashSet<Origin>,
+}
+
+impl Default for UndoManagerOptions {
+  fn default() -> Self {
+    Self {
+      capture_timeout: DEFAULT_CAPTURE_TIMEOUT,
+      tracked_origins: HashSet::default(),
+    }
+  }
+}
+
+/// A group of local changes that can be reverted at once.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StackItem {
+  insertions: DeleteSet,
+  deletions: DeleteSet,
+}
+
+impl StackItem {
+  /// The ids of items inserted by the changes.
+  pub fn insertions(&self) -> &DeleteSet {
+    &self.insertions
+  }
+
+  /// The ids of items deleted by the changes.
+  pub fn deletions(&self) -> &DeleteSet {
+    &self.deletions
+  }
+
+  fn is_empty(&self) -> bool {
+    self.insertions.is_empty() && self.deletions.is_empty()
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.insertions.merge(&other.insertions);
+    self.deletions.merge(&other.deletions);
+  }
+}
+
+/// Tracks the local changes made to a set of types and reverts them on
+/// demand.
+///
+/// Changes made within [UndoManagerOptions::capture_timeout] are grouped into
+/// one [StackItem]. Changes applied from remote updates are never tracked.
+///
+/// ```
+/// use y_octo::{Doc, UndoManager};
+///
+/// let doc = Doc::new();
+/// let mut text = doc.get_or_create_text("text").unwrap();
+/// let mut undo_manager = UndoManager::new(&doc, text.clone()).unwrap();
+///
+/// text.insert(0, "hello").unwrap();
+/// undo_manager.undo().unwrap();
+/// assert_eq!(text.to_string(), "");
+///
+/// undo_manager.redo().unwrap();
+/// assert_eq!(text.to_string(), "hello");
+/// ```
+pub struct UndoManager {
+  store: StoreRef,
+  scope: Vec<YTypeRef>,
+  options: UndoManagerOptions,
+  origin: Origin,
+  changes: ChangeRecorder,
+  undo_stack: Vec<StackItem>,
+  redo_stack: Vec<StackItem>,
+  last_change: Option<(u64, Instant)>,
+  redone: RedoneMap,
+}
+
+unsafe impl Send for UndoManager {}
+unsafe impl Sync for UndoManager {}
+
+impl UndoManager {
+  pub fn new<T: Into<Value>>(doc: &Doc, scope: T) -> JwstCodecResult<Self> {
+    Self::with_options(doc, scope, UndoManagerOptions::default())
+  }
+
+  pub fn with_options<T: Into<Value>>(
+    doc: &Doc,
+    scope: T,
+    options: UndoManagerOptions,
+  ) -> JwstCodecResult<Self> {
+    let changes = ChangeRecorder::default();
+    doc.store.write().unwrap().add_change_recorder(&changes);
+
+    let mut manager = Self {
+      store: doc.store.clone(),
+      scope: Vec::new(),
+      options,
+      origin: Origin::from(format!(
+        "undo_manager_{}",
+        UNDO_MANAGER_ID.fetch_add(1, Ordering::Relaxed)
+      )),
+      changes,
+      undo_stack: Vec::new(),
+      redo_stack: Vec::new(),
+      last_change: None,
+      redone: RedoneMap::new(),
+    };
+    manager.expand_scope(scope)?;
+
+    Ok(manager)
+  }
+
+  /// Track the changes made to another type.
+  pub fn expand_scope<T: Into<Value>>(&mut self, scope: T) -> JwstCodecResult {
+    let ty = match scope.into() {
+      Value::Array(v) => v.0,
+      Value::Map(v) => v.0,
+      Value::Text(v) => v.0,
+      Value::XMLElement(v) => v.0,
+      Value::XMLFragment(v) => v.0,
+      Value::XMLHook(v) => v.0,
+      Value::XMLText(v) => v.0,
+      _ => return Err(JwstCodecError::UnexpectedType("shared type")),
+    };
+
+    if !self.scope.iter().any(|s| s.inner.ptr_eq(&ty.inner)) {
+      self.scope.push(ty);
+    }
+
+    Ok(())
+  }
+
+  pub fn include_origin<O: Into<Origin>>(&mut self, origin: O) {
+    self.options.tracked_origins.insert(origin.into());
+  }
+
+  pub fn exclude_origin<O: Into<Origin>>(&mut self, origin: O) {
+    self.options.tracked_origins.remove(&origin.into());
+  }
+
+  /// The origin of the changes made by [UndoManager::undo] and
+  /// [UndoManager::redo].
+  pub fn origin(&self) -> &Origin {
+    &self.origin
+  }
+
+  pub fn can_undo(&mut self) -> bool {
+    self.flush();
+    !self.undo_stack.is_empty()
+  }
+
+  pub fn can_redo(&mut self) -> bool {
+    self.flush();
+    !self.redo_stack.is_empty()
+  }
+
+  pub fn undo_stack(&mut self) -> &[StackItem] {
+    self.flush();
+    &self.undo_stack
+  }
+
+  pub fn redo_stack(&mut self) -> &[StackItem] {
+    self.flush();
+    &self.redo_stack
+  }
+
+  /// Make sure the next change goes into a new stack item.
+  pub fn stop_capturing(&mut self) {
+    self.flush();
+    self.last_change = None;
+  }
+
+  pub fn clear(&mut self) {
+    self.flush();
+    self.undo_stack.clear();
+    self.redo_stack.clear();
+    self.last_change = None;
+  }
+
+  /// Revert the last stack item, returns whether anything has been changed.
+  pub fn undo(&mut self) -> JwstCodecResult<bool> {
+    self.flush();
+    self.last_change = None;
+
+    while let Some(item) = self.undo_stack.pop() {
+      let reverted = self.revert(&item)?;
+      if !reverted.is_empty() {
+        self.redo_stack.push(reverted);
+        return Ok(true);
+      }
+    }
+
+    Ok(false)
+  }
+
+  /// Revert the last undone stack item, returns whether anything has been
+  /// changed.
+  pub fn redo(&mut self) -> JwstCodecResult<bool> {
+    self.flush();
+    self.last_change = None;
+
+    while let Some(item) = self.redo_stack.pop() {
+      let reverted = self.revert(&item)?;
+      if !reverted.is_empty() {
+        self.undo_stack.push(reverted);
+        return Ok(true);
+      }
+    }
+
+    Ok(false)
+  }
+
+  fn is_tracked(&self, origin: Option<&Origin>) -> bool {
+    match origin {
+      None => true,
+      Some(origin) => self.options.tracked_origins.contains(origin),
+    }
+  }
+
+  /// move the recorded changes into the undo stack
+  fn flush(&mut self) {
+    let changes = mem::take(&mut *self.changes.lock().unwrap());
+    if changes.is_empty() {
+      return;
+    }
+
+    let store = self.store.read().unwrap();
+    for change in changes {
+      if !self.is_tracked(change.origin.as_ref()) {
+        continue;
+      }
+
+      let item = StackItem {
+        insertions: self.filter_scope(&store, &change.insertions),
+        deletions: self.filter_scope(&store, &change.deletions),
+      };
+
+      if item.is_empty() {
+        continue;
+      }
+
+      self.redo_stack.clear();
+      match (self.last_change, self.undo_stack.last_mut()) {
+        (Some((last_batch, last_time)), Some(last_item))
+          if change.batch == last_batch
+            || change.time.saturating_duration_since(last_time) < self.options.capture_timeout =>
+        {
+          last_item.merge(item);
+        }
+        _ => self.undo_stack.push(item),
+      }
+      self.last_change = Some((change.batch, change.time));
+    }
+  }
+
+  fn filter_scope(&self, store: &DocStore, set: &DeleteSet) -> DeleteSet {
+    let mut filtered = DeleteSet::default();
+
+    for (client, ranges) in set.iter() {
+      let Some(nodes) = store.items.get(client) else {
+        continue;
+      };
+
+      for range in ranges {
+        // ranges may cover items from different types, check them one by one
+        let Some(mut idx) = DocStore::get_node_index(nodes, range.start) else {
+          continue;
+        };
+        while let Some(node) = nodes.get(idx) {
+          let clock = node.clock();
+          if clock >= range.end {
+            break;
+          }
+          if node
+            .as_item()
+            .get()
+            .is_some_and(|item| in_scope(&self.scope, item))
+          {
+            filtered.add_range(
+              *client,
+              clock.max(range.start)..(clock + node.len()).min(range.end),
+            );
+          }
+          idx += 1;
+        }
+      }
+    }
+
+    filtered
+  }
+
+  fn revert(&mut self, stack_item: &StackItem) -> JwstCodecResult<StackItem> {
+    let batch = transact(&self.store, Some(self.origin.clone()), || {
+      let mut store = self.store.write().unwrap();
+      let batch = store.change_batch;
+      Revert {
+        store_ref: &self.store,
+        store: &mut store,
+        scope: &self.scope,
+        redone: &mut self.redone,
+      }
+      .apply(stack_item)
+      .map(|_| batch)
+    })?;
+
+    // the observers of the commit and other threads may have made changes
+    // after reverting, they are left to be captured as usual
+    let mut reverted = StackItem::default();
+    self.changes.lock().unwrap().retain(|change| {
+      if change.batch != batch {
+        return true;
+      }
+      reverted.insertions.merge(&change.insertions);
+      reverted.deletions.merge(&change.deletions);
+      false
+    });
+
+    Ok(reverted)
+  }
+}
+
+/// Maps the restored items to their copies, keyed by the client and the clock
+/// of the restored item, valued by the id of the copy and the length.
+type RedoneMap = BTreeMap<(Client, Clock), (Id, u64)>;
+
+struct Revert<'a> {
+  store_ref: &'a StoreRef,
+  store: &'a mut DocStore,
+  scope: &'a [YTypeRef],
+  redone: &'a mut RedoneMap,
+}
+
+impl Revert<'_> {
+  fn apply(mut self, stack_item: &StackItem) -> JwstCodecResult {
+    let mut items_to_redo = Vec::new();
+    let mut redo_ids = HashSet::default();
+    for (client, ranges) in stack_item.deletions.iter() {
+      for range in ranges {
+        for item_ref in self.items_in_range(*client, range)? {
+          if let Some(item) = item_ref.get() {
+            if item.deleted()
+              && !matches!(item.content, Content::Deleted(_))
+              && !stack_item.insertions.contains(&item.id)
+              && in_scope(self.scope, item)
+            {
+              redo_ids.insert(item.id);
+              items_to_redo.push(item_ref);
+            }
+          }
+        }
+      }
+    }
+
+    let mut items_to_delete = Vec::new();
+    for (client, ranges) in stack_item.insertions.iter() {
+      for range in ranges {
+        self.collect_insertions(*client, range, &mut items_to_delete)?;
+      }
+    }
+    let delete_ids = items_to_delete
+      .iter()
+      .filter_map(|item| item.get().map(|item| item.id))
+      .collect::<HashSet<_>>();
+
+    let mut restored = Vec::new();
+    for item in &items_to_redo {
+      if let Some(new_item) = self.redo_item(item, &redo_ids, &delete_ids)? {
+        restored.push(new_item);
+      }
+    }
+
+    for item in items_to_delete.iter().rev() {
+      if let Some(item) = item.get() {
+        if !item.deleted() {
+          self.store.delete_item(item, None);
+        }
+      }
+    }
+
+    // search markers only follow the local list operations, reset them for the touched types
+    for item in items_to_redo
+      .iter()
+      .chain(items_to_delete.iter())
+      .chain(restored.iter())
+    {
+      if let Some(Parent::Type(ty)) = item.get().and_then(|item| item.parent.as_ref()) {
+        if let Some(ty) = ty.ty() {
+          if let Some(markers) = &ty.markers {
+            markers.borrow_mut().clear();
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// find the copy of a restored item
+  fn follow_redone(&self, id: Id) -> Option<Id> {
+    self
+      .redone
+      .range(..=(id.client, id.clock))
+      .next_back()
+      .and_then(|(&(client, clock), &(copy, len))| {
+        (client == id.client && id.clock < clock + len)
+          .then(|| Id::new(copy.client, copy.clock + id.clock - clock))
+      })
+  }
+
+  /// follow the restored items to their latest copies
+  fn resolve(&mut self, mut item: ItemRef) -> JwstCodecResult<ItemRef> {
+    while let Some(id) = item.get().and_then(|item| self.follow_redone(item.id)) {
+      match self.store.split_at_and_get_right(id)? {
+        Node::Item(copy) => item = copy,
+        _ => break,
+      }
+    }
+
+    Ok(item)
+  }
+
+  /// collect the living items inserted in the range, the items deleted and
+  /// restored by other stack items are replaced by their copies
+  fn collect_insertions(
+    &mut self,
+    client: Client,
+    range: Range<u64>,
+    items: &mut Vec<ItemRef>,
+  ) -> JwstCodecResult {
+    for item_ref in self.items_in_range(client, range)? {
+      let Some(item) = item_ref.get() else {
+        continue;
+      };
+
+      if item.deleted() {
+        if let Some(copy) = self.follow_redone(item.id) {
+          self.collect_insertions(copy.client, copy.clock..copy.clock + item.len(), items)?;
+        }
+      } else if in_scope(self.scope, item) {
+        items.push(item_ref);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// split the items at the range boundaries and collect the items in range
+  fn items_in_range(&mut self, client: Client, range: Range<u64>) -> JwstCodecResult<Vec<ItemRef>> {
+    if range.is_empty() || self.store.get_node(Id::new(client, range.start)).is_none() {
+      return Ok(Vec::new());
+    }
+
+    self
+      .store
+      .split_at_and_get_right(Id::new(client, range.start))?;
+    if self
+      .store
+      .get_node(Id::new(client, range.end - 1))
+      .is_some()
+    {
+      self
+        .store
+        .split_at_and_get_left(Id::new(client, range.end - 1))?;
+    }
+
+    let mut items = Vec::new();
+    if let Some(nodes) = self.store.items.get(&client) {
+      if let Some(mut idx) = DocStore::get_node_index(nodes, range.start) {
+        while let Some(node) = nodes.get(idx) {
+          if node.clock() >= range.end {
+            break;
+          }
+          if let Node::Item(item) = node {
+            items.push(item.clone());
+          }
+          idx += 1;
+        }
+      }
+    }
+
+    Ok(items)
+  }
+
+  /// Restore a deleted item by integrating a copy of it at its original
+  /// position, returns [None] if the position no longer exists.
+  fn redo_item(
+    &mut self,
+    item_ref: &ItemRef,
+    redo_ids: &HashSet<Id>,
+    delete_ids: &HashSet<Id>,
+  ) -> JwstCodecResult<Option<ItemRef>> {
+    let Some(item) = item_ref.get() else {
+      return Ok(None);
+    };
+
+    // an item is restored at most once, later changes are tracked on the copy
+    if self.follow_redone(item.id).is_some() {
+      return Ok(None);
+    }
+
+    let Some(Parent::Type(parent)) = &item.parent else {
+      return Ok(None);
+    };
+
+    let mut parent = parent.clone();
+    let mut parent_redone = false;
+    let parent_item = parent.ty().map(|ty| ty.item.clone()).unwrap_or_default();
+
+    // the parent was deleted, it should be restored before its children
+    if parent_item.get().is_some_and(|p| p.deleted()) {
+      let mut new_parent = self.resolve(parent_item)?;
+      if new_parent.get().is_some_and(|p| p.deleted()) {
+        let restorable = new_parent.get().is_some_and(|p| redo_ids.contains(&p.id));
+        new_parent = match restorable {
+          true => match self.redo_item(&new_parent, redo_ids, delete_ids)? {
+            Some(new_parent) => new_parent,
+            None => return Ok(None),
+          },
+          false => return Ok(None),
+        };
+      }
+
+      match new_parent.get() {
+        Some(Item {
+          content: Content::Type(ty),
+          ..
+        }) => {
+          parent = ty.clone();
+          parent_redone = true;
+        }
+        _ => return Ok(None),
+      }
+    }
+
+    let (left, right) = if let Some(parent_sub) = &item.parent_sub {
+      if parent_redone {
+        let left = parent
+          .ty()
+          .and_then(|ty| ty.map.get(parent_sub).cloned())
+          .unwrap_or_default();
+        (left, Somr::none())
+      } else {
+        // the value has been overwritten, only restore it if the newer values are gone
+        let mut left = item_ref.clone();
+        loop {
+          let next = left.get().map(|l| l.right.clone()).unwrap_or_default();
+          match next.get() {
+            Some(n) if n.deleted() || delete_ids.contains(&n.id) => left = next,
+            Some(_) => return Ok(None),
+            None => break,
+          }
+        }
+        (left, Somr::none())
+      }
+    } else if parent_redone {
+      // place the item between the siblings that have been restored
+      let mut left = item.left.clone();
+      while let Some(l) = left.get() {
+        if let Some(copy) = self.follow_redone(l.last_id()) {
+          left = self.store.split_at_and_get_left(copy)?.as_item();
+          break;
+        }
+        left = l.left.clone();
+      }
+
+      let mut right = item.right.clone();
+      while let Some(r) = right.get() {
+        if let Some(copy) = self.follow_redone(r.id) {
+          right = self.store.split_at_and_get_right(copy)?.as_item();
+          break;
+        }
+        right = r.right.clone();
+      }
+
+      (left, right)
+    } else {
+      (item.left.clone(), item_ref.clone())
+    };
+
+    let content = match &item.content {
+      Content::Type(ty) => {
+        let (kind, name) = ty
+          .ty()
+          .map(|ty| (ty.kind(), ty.name.clone()))
+          .unwrap_or_default();
+        Content::Type(YTypeBuilder::dangling(
+          self.store_ref,
+          self.store,
+          kind,
+          name,
+        ))
+      }
+      content => content.clone(),
+    };
+
+    let new_item = self.store.create_item(
+      content,
+      left,
+      right,
+      Some(Parent::Type(parent)),
+      item.parent_sub.clone(),
+    );
+    let new_item_ref = new_item.clone();
+    let new_id = new_item.get().unwrap().id;
+    self.store.integrate(Node::Item(new_item), 0, None)?;
+    self
+      .redone
+      .insert((item.id.client, item.id.clock), (new_id, item.len()));
+
+    Ok(Some(new_item_ref))
+  }
+}
+
+fn in_scope(scope: &[YTypeRef], item: &Item) -> bool {
+  let mut parent = item.parent.clone();
+  while let Some(Parent::Type(ty)) = parent {
+    if scope.iter().any(|s| s.inner.ptr_eq(&ty.inner)) {
+      return true;
+    }
+    parent = ty
+      .ty()
+      .and_then(|ty| ty.item.get().and_then(|item| item.parent.clone()));
+  }
+
+  false
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    loom_model,
+    sync::{AtomicBool, Ordering},
+    Any,
+  };
+
+  fn no_timeout() -> UndoManagerOptions {
+    UndoManagerOptions {
+      capture_timeout: Duration::ZERO,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_undo_text() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+
+      text.insert(0, "hello").unwrap();
+      text.insert(5, " world").unwrap();
+      text.remove(0, 6).unwrap();
+      assert_eq!(text.to_string(), "world");
+
+      assert!(undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "hello world");
+      assert!(undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "hello");
+      assert!(undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "");
+      assert!(!undo_manager.undo().unwrap());
+
+      assert!(undo_manager.redo().unwrap());
+      assert!(undo_manager.redo().unwrap());
+      assert!(undo_manager.redo().unwrap());
+      assert_eq!(text.to_string(), "world");
+      assert!(!undo_manager.redo().unwrap());
+
+      // redo stack is cleared by new changes
+      undo_manager.undo().unwrap();
+      text.insert(0, "!").unwrap();
+      assert!(!undo_manager.can_redo());
+      assert_eq!(text.to_string(), "!hello world");
+    });
+  }
+
+  #[test]
+  fn test_changes_of_observers_are_not_reverted() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+
+      // append a mark with an untracked origin once the first undo is committed
+      let marked = AtomicBool::new(false);
+      let origin = undo_manager.origin().clone();
+      let mark_doc = doc.clone();
+      let mark_text = text.clone();
+      let id = doc.observe_update(move |_, o| {
+        if o == Some(&origin) && !marked.swap(true, Ordering::SeqCst) {
+          mark_doc.transact("mark", |_| {
+            let mut text = mark_text.clone();
+            text.insert(text.len(), "!").unwrap();
+          });
+        }
+      });
+
+      text.insert(0, "hello").unwrap();
+      assert!(undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "!");
+
+      // the mark is not a part of the reverted changes
+      assert!(undo_manager.redo().unwrap());
+      assert_eq!(text.to_string(), "!hello");
+      assert!(undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "!");
+
+      doc.unobserve(id);
+    });
+  }
+
+  #[test]
+  fn test_capture_timeout() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut undo_manager = UndoManager::new(&doc, text.clone()).unwrap();
+
+      text.insert(0, "a").unwrap();
+      text.insert(1, "b").unwrap();
+      assert_eq!(undo_manager.undo_stack().len(), 1);
+
+      undo_manager.stop_capturing();
+      text.insert(2, "c").unwrap();
+      assert_eq!(undo_manager.undo_stack().len(), 2);
+
+      undo_manager.undo().unwrap();
+      assert_eq!(text.to_string(), "ab");
+      undo_manager.undo().unwrap();
+      assert_eq!(text.to_string(), "");
+    });
+  }
+
+  #[test]
+  fn test_tracked_origins() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+
+      doc.with_origin("remote", |_| text.insert(0, "a")).unwrap();
+      assert!(!undo_manager.can_undo());
+
+      undo_manager.include_origin("local");
+      doc.with_origin("local", |_| text.insert(1, "b")).unwrap();
+      text.insert(2, "c").unwrap();
+
+      undo_manager.undo().unwrap();
+      undo_manager.undo().unwrap();
+      assert!(!undo_manager.undo().unwrap());
+      assert_eq!(text.to_string(), "a");
+
+      // changes made by other undo managers are not tracked
+      let mut other = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+      undo_manager.redo().unwrap();
+      assert!(!other.can_undo());
+      assert_eq!(text.to_string(), "ab");
+    });
+  }
+
+  #[test]
+  fn test_scope() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+
+      map.insert("key".into(), "value").unwrap();
+      assert!(!undo_manager.can_undo());
+
+      undo_manager.expand_scope(map.clone()).unwrap();
+      map.insert("key".into(), "new value").unwrap();
+      text.insert(0, "text").unwrap();
+
+      undo_manager.undo().unwrap();
+      undo_manager.undo().unwrap();
+      assert_eq!(text.to_string(), "");
+      assert_eq!(
+        map.get("key").unwrap().to_any(),
+        Some(Any::String("value".into()))
+      );
+
+      assert_eq!(
+        undo_manager.expand_scope(1),
+        Err(JwstCodecError::UnexpectedType("shared type"))
+      );
+    });
+  }
+
+  #[test]
+  fn test_undo_map() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut map = doc.get_or_create_map("map").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, map.clone(), no_timeout()).unwrap();
+
+      map.insert("a".into(), 1).unwrap();
+      map.insert("a".into(), 2).unwrap();
+      map.remove("a");
+      assert!(!map.contains_key("a"));
+
+      undo_manager.undo().unwrap();
+      assert_eq!(map.get("a").unwrap().to_string(), "2");
+      undo_manager.undo().unwrap();
+      assert_eq!(map.get("a").unwrap().to_string(), "1");
+      undo_manager.undo().unwrap();
+      assert!(!map.contains_key("a"));
+
+      undo_manager.redo().unwrap();
+      undo_manager.redo().unwrap();
+      assert_eq!(map.get("a").unwrap().to_string(), "2");
+    });
+  }
+
+  #[test]
+  fn test_undo_nested_types() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, array.clone(), no_timeout()).unwrap();
+
+      array.push("first").unwrap();
+      array.push(doc.create_map().unwrap()).unwrap();
+      let mut map = array.get(1).unwrap().to_map().unwrap();
+      map.insert("key".into(), "value").unwrap();
+      let mut text = doc.create_text().unwrap();
+      map.insert("text".into(), text.clone()).unwrap();
+      text = map.get("text").unwrap().to_text().unwrap();
+      text.insert(0, "nested").unwrap();
+      undo_manager.stop_capturing();
+
+      array.remove(1, 1).unwrap();
+      assert_eq!(array.len(), 1);
+
+      undo_manager.undo().unwrap();
+      assert_eq!(array.len(), 2);
+      let map = array.get(1).unwrap().to_map().unwrap();
+      assert_eq!(
+        map.get("key").unwrap().to_any(),
+        Some(Any::String("value".into()))
+      );
+      assert_eq!(map.get("text").unwrap().to_string(), "nested");
+
+      // the restored types are synchronized to other docs
+      let mut new_doc = Doc::new();
+      new_doc
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      let new_array = new_doc.get_or_create_array("array").unwrap();
+      let new_map = new_array.get(1).unwrap().to_map().unwrap();
+      assert_eq!(new_map.get("text").unwrap().to_string(), "nested");
+    });
+  }
+
+  #[test]
+  fn test_remote_changes_are_not_tracked() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+      text.insert(0, "local").unwrap();
+
+      let mut remote = Doc::new();
+      remote
+        .apply_update_from_binary_v1(doc.encode_update_v1().unwrap())
+        .unwrap();
+      remote
+        .get_or_create_text("text")
+        .unwrap()
+        .insert(5, " remote")
+        .unwrap();
+
+      let mut doc = doc;
+      doc
+        .apply_update_from_binary_v1(remote.encode_update_v1().unwrap())
+        .unwrap();
+      assert_eq!(text.to_string(), "local remote");
+
+      undo_manager.undo().unwrap();
+      assert_eq!(text.to_string(), " remote");
+      assert!(!undo_manager.can_undo());
+    });
+  }
+
+  #[test]
+  fn test_dropped_undo_manager_stops_recording() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let undo_manager = UndoManager::with_options(&doc, text.clone(), no_timeout()).unwrap();
+      text.insert(0, "hello").unwrap();
+      assert_eq!(doc.store.read().unwrap().change_recorders.len(), 1);
+
+      drop(undo_manager);
+      text.insert(5, " world").unwrap();
+      assert!(doc.store.read().unwrap().change_recorders.is_empty());
+    });
+  }
+}
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use super::*;
 
 pub fn encode_awareness_as_message(awareness: AwarenessStates) -> JwstCodecResult<Vec<u8>> {
@@ -16,6 +18,96 @@ pub fn encode_update_as_message(update: Vec<u8>) -> JwstCodecResult<Vec<u8>> {
   Ok(buffer)
 }
 
+pub fn convert_update_v1_to_v2<T: AsRef<[u8]>>(update: T) -> JwstCodecResult<Vec<u8>> {
+  Update::decode_v1(update)?.encode_v2()
+}
+
+pub fn convert_update_v2_to_v1<T: AsRef<[u8]>>(update: T) -> JwstCodecResult<Vec<u8>> {
+  Update::decode_v2(update)?.encode_v1()
+}
+
+/// Compute the state vector of an update without applying it to a doc. Same
+/// as yjs, only the structs contiguous from the clock 0 are counted.
+pub fn encode_state_vector_from_update_v1<T: AsRef<[u8]>>(update: T) -> JwstCodecResult<Vec<u8>> {
+  let mut state = StateVector::default();
+  let mut stopped = HashSet::new();
+
+  Update::read_structs(&mut RawDecoder::new(update.as_ref()), |node| {
+    let Id { client, clock } = node.id();
+    if stopped.contains(&client) {
+      return Ok(());
+    }
+
+    if node.is_skip() || clock != state.get(&client) {
+      stopped.insert(client);
+    } else {
+      state.insert(client, clock + node.len());
+    }
+
+    Ok(())
+  })?;
+
+  let mut encoder = RawEncoder::default();
+  state.write(&mut encoder)?;
+  Ok(encoder.into_inner())
+}
+
+/// Read the clock ranges of the structs in an update without applying it to
+/// a doc.
+pub fn parse_update_meta<T: AsRef<[u8]>>(update: T) -> JwstCodecResult<UpdateMeta> {
+  let mut meta = UpdateMeta::default();
+
+  Update::read_structs(&mut RawDecoder::new(update.as_ref()), |node| {
+    let Id { client, clock } = node.id();
+    meta.from.entry(client).or_insert(clock);
+    if !node.is_skip() {
+      meta.to.insert(client, clock + node.len());
+    }
+
+    Ok(())
+  })?;
+
+  Ok(meta)
+}
+
+/// Drop the structs of an update already covered by the given state vector,
+/// without applying it to a doc. The delete set is kept as is.
+pub fn diff_update_v1<T: AsRef<[u8]>>(
+  update: T,
+  state_vector: &StateVector,
+) -> JwstCodecResult<Vec<u8>> {
+  let mut structs = ClientMap::<VecDeque<Node>>::new();
+
+  let delete_set = Update::read_structs(&mut RawDecoder::new(update.as_ref()), |node| {
+    let Id { client, clock } = node.id();
+    let known = state_vector.get(&client);
+    if clock + node.len() <= known {
+      return Ok(());
+    }
+
+    let node = if clock < known {
+      node.split_at(known - clock)?.1
+    } else {
+      node
+    };
+
+    let nodes = structs.entry(client).or_default();
+    // the leading skips are not needed
+    if !nodes.is_empty() || !node.is_skip() {
+      nodes.push_back(node);
+    }
+
+    Ok(())
+  })?;
+
+  Update {
+    structs,
+    delete_set,
+    ..Update::default()
+  }
+  .encode_v1()
+}
+
 pub fn merge_updates_v1<V: AsRef<[u8]>, I: IntoIterator<Item = V>>(
   updates: I,
 ) -> JwstCodecResult<Update> {
@@ -26,3 +118,116 @@ pub fn merge_updates_v1<V: AsRef<[u8]>, I: IntoIterator<Item = V>>(
 
   Ok(Update::merge(updates))
 }
+
+#[cfg(test)]
+mod tests {
+  use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    ReadTxn, Text as _, Transact,
+  };
+
+  use super::*;
+
+  fn yrs_update() -> (yrs::Doc, Vec<u8>, Vec<u8>) {
+    let doc = yrs::Doc::with_client_id(1);
+    let text = doc.get_or_insert_text("text");
+    text.insert(&mut doc.transact_mut(), 0, "hello");
+    let sv = doc.transact().state_vector().encode_v1();
+    text.insert(&mut doc.transact_mut(), 5, " world");
+    text.remove_range(&mut doc.transact_mut(), 0, 1);
+
+    let update = doc
+      .transact()
+      .encode_state_as_update_v1(&Default::default());
+    (doc, update, sv)
+  }
+
+  #[test]
+  fn test_encode_state_vector_from_update() {
+    let (doc, update, _) = yrs_update();
+
+    assert_eq!(
+      encode_state_vector_from_update_v1(&update).unwrap(),
+      doc.transact().state_vector().encode_v1()
+    );
+    assert_eq!(
+      encode_state_vector_from_update_v1(&update).unwrap(),
+      yrs::encode_state_vector_from_update_v1(&update).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_parse_update_meta() {
+    let (_, update, sv) = yrs_update();
+    let sv = StateVector::read(&mut RawDecoder::new(&sv)).unwrap();
+
+    let meta = parse_update_meta(&update).unwrap();
+    assert_eq!(meta.from, StateVector::from([(1, 0)]));
+    assert_eq!(meta.to, StateVector::from([(1, 11)]));
+
+    let diff = diff_update_v1(&update, &sv).unwrap();
+    let meta = parse_update_meta(&diff).unwrap();
+    assert_eq!(meta.from, StateVector::from([(1, 5)]));
+    assert_eq!(meta.to, StateVector::from([(1, 11)]));
+
+    // only the structs contiguous from the clock 0 are counted
+    assert_eq!(
+      StateVector::read(&mut RawDecoder::new(
+        &encode_state_vector_from_update_v1(&diff).unwrap()
+      ))
+      .unwrap(),
+      StateVector::default()
+    );
+  }
+
+  #[test]
+  fn test_diff_update() {
+    let (doc, update, sv) = yrs_update();
+    let state_vector = StateVector::read(&mut RawDecoder::new(&sv)).unwrap();
+
+    let diff = diff_update_v1(&update, &state_vector).unwrap();
+    let expected = doc
+      .transact()
+      .encode_state_as_update_v1(&yrs::StateVector::decode_v1(&sv).unwrap());
+    assert_eq!(
+      Update::decode_v1(&diff).unwrap().encode_v1().unwrap(),
+      Update::decode_v1(expected).unwrap().encode_v1().unwrap()
+    );
+    assert_eq!(
+      diff_update_v1(&update, &StateVector::default()).unwrap(),
+      update
+    );
+  }
+
+  #[test]
+  fn test_diff_update_slice_struct() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      let mut text = doc.get_or_create_text("text").unwrap();
+      text.insert(0, "hello world").unwrap();
+      let update = doc.encode_update_v1().unwrap();
+
+      // the doc has the first 3 chars of the item only
+      let prefix = Doc::with_client(1);
+      prefix
+        .get_or_create_text("text")
+        .unwrap()
+        .insert(0, "hel")
+        .unwrap();
+      let mut partial = Doc::with_client(2);
+      partial
+        .apply_update_from_binary_v1(prefix.encode_update_v1().unwrap())
+        .unwrap();
+      let partial_text = partial.get_or_create_text("text").unwrap();
+      assert_eq!(partial_text.to_string(), "hel");
+
+      let diff = diff_update_v1(&update, &partial.get_state_vector()).unwrap();
+      assert_eq!(
+        parse_update_meta(&diff).unwrap().from,
+        StateVector::from([(1, 3)])
+      );
+      partial.apply_update_from_binary_v1(diff).unwrap();
+      assert_eq!(partial_text.to_string(), "hello world");
+    });
+  }
+}
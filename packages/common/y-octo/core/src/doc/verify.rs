@@ -0,0 +1,438 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use serde::Serialize;
+
+use super::{store::StoreRef, transaction::transact, *};
+
+/// A broken invariant of the doc store, found by [Doc::verify].
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreIssue {
+  /// the struct does not start at the clock the previous one ends at
+  ClockGap {
+    client: Client,
+    expect: Clock,
+    actually: Clock,
+  },
+  /// the left or right item of the item does not link back to it
+  BrokenLink { id: Id },
+  /// the origin of the item does not exist in the store
+  MissingOrigin { id: Id, origin: Id },
+  /// the item does not belong to an existing type
+  MissingParent { id: Id },
+  /// the length of the type does not match the items in it
+  LengthMismatch {
+    ty: ParentInspection,
+    expect: u64,
+    actually: u64,
+  },
+  /// the search marker points to an item out of the type or at a wrong index
+  InvalidMarker { ty: ParentInspection, index: u64 },
+}
+
+/// A struct dropped by [Doc::apply_update_with_repair], it's integrated as a
+/// gc struct to keep the clocks continuous.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DroppedStruct {
+  pub id: Id,
+  pub len: u64,
+  pub reason: String,
+}
+
+/// What is lost or changed by [Doc::apply_update_with_repair].
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct RepairReport {
+  pub dropped: Vec<DroppedStruct>,
+  /// the items whose parent is invalid and moved into the parent of their
+  /// neighbors
+  pub reparented: Vec<Id>,
+  /// the deleted ranges failed to apply
+  pub failed_deletions: BTreeMap<Client, Vec<Range<Clock>>>,
+  /// the smallest missing clock of each client the pending structs depend on
+  pub missing: BTreeMap<Client, Clock>,
+  /// the issues left in the doc after repairing
+  pub issues: Vec<StoreIssue>,
+}
+
+impl RepairReport {
+  /// whether nothing is lost
+  pub fn is_clean(&self) -> bool {
+    self.dropped.is_empty()
+      && self.reparented.is_empty()
+      && self.failed_deletions.is_empty()
+      && self.missing.is_empty()
+      && self.issues.is_empty()
+  }
+
+  pub(crate) fn drop_struct(&mut self, id: Id, len: u64, reason: impl ToString) {
+    self.dropped.push(DroppedStruct {
+      id,
+      len,
+      reason: reason.to_string(),
+    });
+  }
+
+  pub(crate) fn fail_deletion(&mut self, client: Client, range: Range<Clock>) {
+    self.failed_deletions.entry(client).or_default().push(range);
+  }
+}
+
+impl DocStore {
+  pub fn verify(&self) -> Vec<StoreIssue> {
+    let mut issues = Vec::new();
+    let mut types = self.types.values().cloned().collect::<Vec<_>>();
+
+    for (client, nodes) in self.items.iter() {
+      let mut expect = 0;
+      for node in nodes {
+        if node.clock() != expect {
+          issues.push(StoreIssue::ClockGap {
+            client: *client,
+            expect,
+            actually: node.clock(),
+          });
+        }
+        expect = node.clock() + node.len();
+
+        if let Node::Item(item_ref) = node {
+          let item = unsafe { item_ref.get_unchecked() };
+          self.verify_item(item_ref, item, &mut issues);
+
+          if let Content::Type(ty) = &item.content {
+            if !item.deleted() {
+              types.push(ty.clone());
+            }
+          }
+        }
+      }
+    }
+
+    for ty in types {
+      Self::verify_type(&ty, &mut issues);
+    }
+
+    issues
+  }
+
+  fn verify_item(&self, item_ref: &ItemRef, item: &Item, issues: &mut Vec<StoreIssue>) {
+    let left_broken = item
+      .left
+      .get()
+      .is_some_and(|left| !left.right.ptr_eq(item_ref));
+    let right_broken = item
+      .right
+      .get()
+      .is_some_and(|right| !right.left.ptr_eq(item_ref));
+    if left_broken || right_broken {
+      issues.push(StoreIssue::BrokenLink { id: item.id });
+    }
+
+    for origin in [item.origin_left_id, item.origin_right_id]
+      .into_iter()
+      .flatten()
+    {
+      if self.get_state(origin.client) <= origin.clock {
+        issues.push(StoreIssue::MissingOrigin {
+          id: item.id,
+          origin,
+        });
+      }
+    }
+
+    let parent_exists = match &item.parent {
+      Some(Parent::Type(ty)) => ty
+        .ty()
+        .is_some_and(|ty| ty.root_name.is_some() || ty.item.is_some()),
+      _ => false,
+    };
+    if !parent_exists {
+      issues.push(StoreIssue::MissingParent { id: item.id });
+    }
+  }
+
+  fn verify_type(ty: &YTypeRef, issues: &mut Vec<StoreIssue>) {
+    let Some(inner) = ty.ty() else {
+      return;
+    };
+
+    let mut len = 0;
+    // the index of each item in the type
+    let mut indexes = Vec::new();
+    let mut item_ref = inner.start.clone();
+    while let Some(item) = item_ref.get() {
+      indexes.push((item_ref.clone(), len));
+      if item.indexable() {
+        len += item.len();
+      }
+      item_ref = item.right.clone();
+    }
+
+    let parent = || {
+      inner
+        .item
+        .get()
+        .map(|item| ParentInspection::Item(item.id))
+        .or_else(|| inner.root_name.clone().map(ParentInspection::Root))
+        .unwrap_or(ParentInspection::Unknown)
+    };
+
+    if len != inner.len {
+      issues.push(StoreIssue::LengthMismatch {
+        ty: parent(),
+        expect: len,
+        actually: inner.len,
+      });
+    }
+
+    if let Some(markers) = &inner.markers {
+      for marker in markers.borrow().iter() {
+        let valid = indexes
+          .iter()
+          .any(|(item, index)| item.ptr_eq(&marker.ptr) && *index == marker.index);
+        if !valid {
+          issues.push(StoreIssue::InvalidMarker {
+            ty: parent(),
+            index: marker.index,
+          });
+        }
+      }
+    }
+  }
+
+  /// Integrate the struct, the struct that can't be integrated is replaced by
+  /// a gc struct and reported instead of failing the whole update.
+  pub(crate) fn integrate_with_repair(
+    &mut self,
+    mut node: Node,
+    offset: u64,
+    store_ref: StoreRef,
+    report: &mut RepairReport,
+  ) -> JwstCodecResult {
+    let id = Id::new(node.client(), node.clock() + offset);
+    let len = node.len() - offset;
+
+    if let Node::Item(item) = &mut node {
+      debug_assert!(item.is_owned());
+      let mut item = unsafe { item.get_mut_unchecked() };
+
+      if let Err(e) = self.repair(&mut item, store_ref) {
+        report.drop_struct(id, len, e);
+        return self.integrate(Node::new_gc(id, len), 0, None);
+      }
+
+      if item.parent.is_none() {
+        let neighbor = [item.left.clone(), item.right.clone()]
+          .into_iter()
+          .find_map(|neighbor| {
+            let neighbor = ItemRef::from(neighbor);
+            let neighbor = neighbor.get()?;
+            matches!(neighbor.parent, Some(Parent::Type(_)))
+              .then(|| (neighbor.parent.clone(), neighbor.parent_sub.clone()))
+          });
+
+        match neighbor {
+          Some((parent, parent_sub)) => {
+            item.parent = parent;
+            item.parent_sub = parent_sub;
+            report.reparented.push(id);
+          }
+          None => {
+            report.drop_struct(id, len, JwstCodecError::InvalidParent);
+            return self.integrate(Node::new_gc(id, len), 0, None);
+          }
+        }
+      }
+    }
+
+    if let Err(e) = self.integrate(node, offset, None) {
+      report.drop_struct(id, len, e);
+      return self.integrate(Node::new_gc(id, len), 0, None);
+    }
+
+    Ok(())
+  }
+}
+
+impl Doc {
+  /// Check the invariants of the store, returns the broken ones.
+  ///
+  /// ```
+  /// use y_octo::Doc;
+  ///
+  /// let doc = Doc::new();
+  /// let mut text = doc.get_or_create_text("text").unwrap();
+  /// text.insert(0, "hello").unwrap();
+  ///
+  /// assert!(doc.verify().is_empty());
+  /// ```
+  pub fn verify(&self) -> Vec<StoreIssue> {
+    self.store.read().unwrap().verify()
+  }
+
+  /// Apply the update as much as possible, the structs and deletions that
+  /// can't be applied are dropped and reported instead of failing the whole
+  /// update.
+  pub fn apply_update_with_repair(&mut self, update: Update) -> JwstCodecResult<RepairReport> {
+    let mut report = RepairReport::default();
+
+    let store_ref = self.store.clone();
+    transact(&store_ref, None, || {
+      let mut store = store_ref.write().unwrap();
+
+      let recorders = std::mem::take(&mut store.change_recorders);
+      let result = self.integrate_update(&mut store, update, Some(&mut report));
+      store.change_recorders = recorders;
+
+      result
+    })?;
+
+    report.missing = self
+      .pending_state()
+      .iter()
+      .map(|(client, clock)| (*client, *clock))
+      .collect();
+    report.issues = self.verify();
+
+    Ok(report)
+  }
+
+  /// Rebuild the doc from its structs with [Doc::apply_update_with_repair],
+  /// the doc itself is not modified.
+  pub fn repair(&self) -> JwstCodecResult<(Doc, RepairReport)> {
+    let update = Update::decode_v1(self.encode_update_v1()?)?;
+    let mut doc = Doc::with_options(self.options().clone());
+    let report = doc.apply_update_with_repair(update)?;
+
+    Ok((doc, report))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::VecDeque;
+
+  use super::*;
+  use crate::loom_model;
+
+  #[test]
+  fn test_verify() {
+    loom_model!({
+      let doc = Doc::new();
+      let mut text = doc.get_or_create_text("text").unwrap();
+      let mut array = doc.get_or_create_array("array").unwrap();
+      let mut map = doc.get_or_create_map("map").unwrap();
+
+      for i in 0..20 {
+        text.insert(i, "hello").unwrap();
+        array.insert(i / 2, i).unwrap();
+      }
+      text.remove(3, 10).unwrap();
+      array.remove(5, 5).unwrap();
+      array.move_to(0, 6).unwrap();
+      map
+        .insert_json("nested".into(), serde_json::json!({ "list": [1, 2, 3] }))
+        .unwrap();
+      map.insert("key".into(), "value").unwrap();
+      map.remove("key");
+
+      assert_eq!(doc.verify(), vec![]);
+
+      text.0.ty_mut().unwrap().len += 1;
+      assert_eq!(
+        doc.verify(),
+        vec![StoreIssue::LengthMismatch {
+          ty: ParentInspection::Root("text".into()),
+          expect: text.len() - 1,
+          actually: text.len(),
+        }]
+      );
+    });
+  }
+
+  #[test]
+  #[cfg_attr(any(miri, loom), ignore)]
+  fn test_verify_fixtures() {
+    let updates = [
+      include_bytes!("../fixtures/basic.bin").to_vec(),
+      include_bytes!("../fixtures/database.bin").to_vec(),
+      include_bytes!("../fixtures/large.bin").to_vec(),
+      include_bytes!("../fixtures/with-subdoc.bin").to_vec(),
+      include_bytes!("../fixtures/edge-case-left-right-same-node.bin").to_vec(),
+    ];
+
+    for update in updates {
+      let doc = Doc::try_from_binary_v1(&update).unwrap();
+      assert_eq!(doc.verify(), vec![]);
+
+      let (repaired, report) = doc.repair().unwrap();
+      assert!(report.is_clean(), "{report:?}");
+      assert_eq!(repaired.get_state_vector(), doc.get_state_vector());
+    }
+  }
+
+  #[test]
+  fn test_repair() {
+    loom_model!({
+      let item = |id: Id, origin_left: Option<Id>, parent: Option<Parent>, content: &str| {
+        Node::Item(Somr::new(
+          ItemBuilder::new()
+            .id(id)
+            .left_id(origin_left)
+            .right_id(None)
+            .parent(parent)
+            .parent_sub(None)
+            .content(Content::String(content.into()))
+            .build(),
+        ))
+      };
+
+      let update = || Update {
+        structs: ClientMap::from_iter([(
+          1,
+          VecDeque::from([
+            item(
+              Id::new(1, 0),
+              None,
+              Some(Parent::String("text".into())),
+              "hello",
+            ),
+            // the parent is not a type and there is no neighbor to borrow it from
+            item(Id::new(1, 5), None, Some(Parent::Id(Id::new(1, 0))), "x"),
+            // the parent is not a type, but the left item is in a type
+            item(
+              Id::new(1, 6),
+              Some(Id::new(1, 4)),
+              Some(Parent::Id(Id::new(1, 0))),
+              "!",
+            ),
+          ]),
+        )]),
+        ..Default::default()
+      };
+
+      // the broken items are silently dropped by default
+      let mut doc = Doc::new();
+      doc.apply_update(update()).unwrap();
+      assert_eq!(doc.get_or_create_text("text").unwrap().to_string(), "hello");
+
+      let mut doc = Doc::new();
+      let report = doc.apply_update_with_repair(update()).unwrap();
+      assert_eq!(
+        doc.get_or_create_text("text").unwrap().to_string(),
+        "hello!"
+      );
+      assert_eq!(
+        report.dropped,
+        vec![DroppedStruct {
+          id: Id::new(1, 5),
+          len: 1,
+          reason: JwstCodecError::InvalidParent.to_string(),
+        }]
+      );
+      assert_eq!(report.reparented, vec![Id::new(1, 6)]);
+      assert!(report.missing.is_empty());
+      assert_eq!(report.issues, vec![]);
+      assert_eq!(doc.get_state_vector().get(&1), 7);
+    });
+  }
+}
@@ -5,18 +5,28 @@ mod protocol;
 mod sync;
 
 pub use codec::*;
+pub(crate) use doc::Item;
 pub use doc::{
-  encode_awareness_as_message, encode_update_as_message, merge_updates_v1, Any, Array, Awareness,
-  AwarenessEvent, Client, ClientMap, Clock, CrdtRead, CrdtReader, CrdtWrite, CrdtWriter, Doc,
-  DocOptions, HashMap as AHashMap, HashMapExt, History, HistoryOptions, Id, Map, RawDecoder,
-  RawEncoder, StateVector, StoreHistory, Text, Update, Value,
+  convert_update_v1_to_v2, convert_update_v2_to_v1, diff_update_v1, encode_awareness_as_message,
+  encode_state_vector_from_update_v1, encode_update_as_message, inspect_structs_v1,
+  inspect_update_v1, merge_updates_v1, parse_update_meta, Any, Array, ArrayDelta, ArrayEvent,
+  Assoc, Attribution, Awareness, AwarenessEvent, Client, ClientIdEvent, ClientMap, Clock,
+  ContentInspection, CrdtRead, CrdtReader, CrdtWrite, CrdtWriter, DirectoryPersistence, Doc,
+  DocOptions, DocPersistence, DocStoreStatus, DroppedStruct, EntryChange, Event, GcOptions,
+  GcStats, HashMap as AHashMap, HashMapExt, History, HistoryOptions, Id, IndexScope, Map, MapEvent,
+  MemoryPersistence, ObserverId, OffsetKind, Origin, ParentInspection, PathSegment,
+  PendingInspection, PermanentUserData, PersistentDoc, RawDecoder, RawDecoderV2, RawEncoder,
+  RawEncoderV2, RelativePosition, RepairReport, Snapshot, StackItem, StateVector, StickyIndex,
+  StoreHistory, StoreIssue, StructInspection, StructKind, SubdocsEvent, Text, TextAttributes,
+  TextDelta, TextEvent, TextInsert, Transaction, TypePath, UndoManager, UndoManagerOptions, Update,
+  UpdateFilter, UpdateInspection, UpdateMeta, Value, XMLElement, XMLFragment, XMLHook, XMLText,
+  AWARENESS_OUTDATED_TIMEOUT,
 };
-pub(crate) use doc::{Content, Item};
 use log::{debug, warn};
 use nom::IResult;
 pub use protocol::{
   read_sync_message, write_sync_message, AwarenessState, AwarenessStates, DocMessage, SyncMessage,
-  SyncMessageScanner,
+  SyncMessageScanner, SyncProtocol,
 };
 use thiserror::Error;
 
@@ -58,10 +68,22 @@ pub enum JwstCodecError {
   RootStructNotFound(String),
   #[error("Index {0} out of bound")]
   IndexOutOfBound(u64),
+  #[error("Can not move {start}..={end}, the elements are not shown by the same move")]
+  InvalidMoveRange { start: u64, end: u64 },
   #[error("Document has been released")]
   DocReleased,
   #[error("Unexpected type, expect {0}")]
   UnexpectedType(&'static str),
+  #[error("Garbage collection must be disabled to restore the snapshot")]
+  GarbageCollectionEnabled,
+  #[error("Document is not forked from another one")]
+  NotForked,
+  #[error("Persistence error: {0}")]
+  Persistence(String),
+  #[error("Invalid awareness state: {0}")]
+  InvalidAwarenessState(String),
+  #[error("Invalid sync message: {0}")]
+  InvalidSyncMessage(String),
 }
 
 pub type JwstCodecResult<T = ()> = Result<T, JwstCodecError>;
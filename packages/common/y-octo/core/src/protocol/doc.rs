@@ -21,23 +21,46 @@ const DOC_MESSAGE_STEP1: u64 = 0;
 const DOC_MESSAGE_STEP2: u64 = 1;
 const DOC_MESSAGE_UPDATE: u64 = 2;
 
+impl DocMessage {
+  /// The first step of the sync, requests the updates missing from the state
+  /// vector.
+  pub fn step1(state_vector: &StateVector) -> JwstCodecResult<Self> {
+    Ok(Self::Step1(state_vector.encode_v1()?))
+  }
+
+  /// Decode the state vector of a [DocMessage::Step1].
+  pub fn state_vector(&self) -> Option<JwstCodecResult<StateVector>> {
+    match self {
+      Self::Step1(sv) => Some(StateVector::decode_v1(sv)),
+      _ => None,
+    }
+  }
+
+  /// Decode the update of a [DocMessage::Step2] or [DocMessage::Update].
+  pub fn update(&self) -> Option<JwstCodecResult<Update>> {
+    match self {
+      Self::Step2(update) | Self::Update(update) => Some(Update::decode_v1(update)),
+      _ => None,
+    }
+  }
+}
+
 pub fn read_doc_message(input: &[u8]) -> IResult<&[u8], DocMessage> {
   let (tail, step) = read_var_u64(input)?;
 
   match step {
     DOC_MESSAGE_STEP1 => {
+      // decoded on demand, see [DocMessage::state_vector]
       let (tail, sv) = read_var_buffer(tail)?;
-      // TODO: decode state vector
       Ok((tail, DocMessage::Step1(sv.into())))
     }
     DOC_MESSAGE_STEP2 => {
+      // decoded on demand, see [DocMessage::update]
       let (tail, update) = read_var_buffer(tail)?;
-      // TODO: decode update
       Ok((tail, DocMessage::Step2(update.into())))
     }
     DOC_MESSAGE_UPDATE => {
       let (tail, update) = read_var_buffer(tail)?;
-      // TODO: decode update
       Ok((tail, DocMessage::Update(update.into())))
     }
     _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Tag))),
@@ -0,0 +1,168 @@
+use super::*;
+
+/// Run the sync protocol of y-websocket for a doc and its awareness: the
+/// incoming messages are applied to them and the responses are returned.
+pub struct SyncProtocol {
+  doc: Doc,
+  awareness: Awareness,
+}
+
+impl SyncProtocol {
+  pub fn new(doc: Doc, awareness: Awareness) -> Self {
+    Self { doc, awareness }
+  }
+
+  pub fn doc(&self) -> &Doc {
+    &self.doc
+  }
+
+  pub fn awareness(&self) -> &Awareness {
+    &self.awareness
+  }
+
+  pub fn awareness_mut(&mut self) -> &mut Awareness {
+    &mut self.awareness
+  }
+
+  /// The messages to send once connected, the state vector of the doc to
+  /// request the missing updates, and the known awareness states.
+  pub fn start(&self) -> JwstCodecResult<Vec<SyncMessage>> {
+    let mut messages = vec![SyncMessage::Doc(DocMessage::step1(
+      &self.doc.get_state_vector(),
+    )?)];
+
+    let states = self.awareness.get_states();
+    if !states.is_empty() {
+      messages.push(SyncMessage::Awareness(states.clone()));
+    }
+
+    Ok(messages)
+  }
+
+  /// Handle an incoming message, a [DocMessage::Step1] is answered with the
+  /// missing updates and an [SyncMessage::AwarenessQuery] with the awareness
+  /// states, the updates are applied.
+  pub fn handle(&mut self, message: SyncMessage) -> JwstCodecResult<Vec<SyncMessage>> {
+    match message {
+      SyncMessage::Doc(message) => {
+        if let Some(state_vector) = message.state_vector() {
+          let update = self.doc.encode_state_as_update_v1(&state_vector?)?;
+          return Ok(vec![SyncMessage::Doc(DocMessage::Step2(update))]);
+        }
+
+        if let Some(update) = message.update() {
+          self.doc.apply_update(update?)?;
+        }
+      }
+      SyncMessage::Awareness(states) => self.awareness.apply_update(states),
+      SyncMessage::AwarenessQuery => {
+        return Ok(vec![SyncMessage::Awareness(
+          self.awareness.get_states().clone(),
+        )]);
+      }
+      SyncMessage::Auth(_) => {}
+    }
+
+    Ok(Vec::new())
+  }
+
+  /// Handle the encoded messages received in a binary frame, the encoded
+  /// responses are returned.
+  pub fn handle_binary(&mut self, buffer: &[u8]) -> JwstCodecResult<Vec<Vec<u8>>> {
+    let mut responses = Vec::new();
+    for message in SyncMessageScanner::new(buffer) {
+      let message = message.map_err(|e| JwstCodecError::InvalidSyncMessage(e.to_string()))?;
+      for response in self.handle(message)? {
+        let mut buffer = Vec::new();
+        write_sync_message(&mut buffer, &response)
+          .map_err(|e| JwstCodecError::InvalidWriteBuffer(e.to_string()))?;
+        responses.push(buffer);
+      }
+    }
+
+    Ok(responses)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// deliver the messages back and forth until there's nothing to respond
+  fn exchange(from: &mut SyncProtocol, to: &mut SyncProtocol, mut messages: Vec<SyncMessage>) {
+    let (mut from, mut to) = (from, to);
+    while !messages.is_empty() {
+      messages = messages
+        .into_iter()
+        .map(|message| to.handle(message).unwrap())
+        .collect::<Vec<_>>()
+        .concat();
+      std::mem::swap(&mut from, &mut to);
+    }
+  }
+
+  #[test]
+  fn test_sync_protocol() {
+    loom_model!({
+      let doc = Doc::with_client(1);
+      doc
+        .get_or_create_text("text")
+        .unwrap()
+        .insert(0, "hello")
+        .unwrap();
+      let mut awareness = Awareness::new(1);
+      awareness.set_local_state("{\"name\":\"alice\"}".into());
+      let mut server = SyncProtocol::new(doc, awareness);
+
+      let mut awareness = Awareness::new(2);
+      awareness.set_local_state("{\"name\":\"bob\"}".into());
+      let mut client = SyncProtocol::new(Doc::with_client(2), awareness);
+      client
+        .doc()
+        .get_or_create_array("array")
+        .unwrap()
+        .push(1)
+        .unwrap();
+
+      let messages = client.start().unwrap();
+      exchange(&mut client, &mut server, messages);
+      let messages = server.start().unwrap();
+      exchange(&mut server, &mut client, messages);
+
+      for peer in [&server, &client] {
+        let doc = peer.doc();
+        assert_eq!(doc.get_or_create_text("text").unwrap().to_string(), "hello");
+        assert_eq!(doc.get_or_create_array("array").unwrap().len(), 1);
+        assert_eq!(peer.awareness().get_states().len(), 2);
+      }
+
+      // the updates made later are broadcast as they are
+      let update = {
+        let doc = client.doc();
+        let sv = doc.get_state_vector();
+        doc
+          .get_or_create_text("text")
+          .unwrap()
+          .insert(5, " world")
+          .unwrap();
+        doc.encode_state_as_update_v1(&sv).unwrap()
+      };
+      let mut buffer = Vec::new();
+      write_sync_message(&mut buffer, &SyncMessage::Doc(DocMessage::Update(update))).unwrap();
+      write_sync_message(&mut buffer, &SyncMessage::AwarenessQuery).unwrap();
+      let responses = server.handle_binary(&buffer).unwrap();
+      assert_eq!(
+        server.doc().get_or_create_text("text").unwrap().to_string(),
+        "hello world"
+      );
+      assert_eq!(responses.len(), 1);
+      let (_, response) = read_sync_message(&responses[0]).unwrap();
+      assert!(matches!(response, SyncMessage::Awareness(states) if states.len() == 2));
+
+      assert!(matches!(
+        server.handle_binary(&[9]),
+        Err(JwstCodecError::InvalidSyncMessage(_))
+      ));
+    });
+  }
+}
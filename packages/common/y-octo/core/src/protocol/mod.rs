@@ -1,5 +1,6 @@
 mod awareness;
 mod doc;
+mod handler;
 mod scanner;
 mod sync;
 
@@ -12,6 +13,7 @@ use awareness::{read_awareness, write_awareness};
 pub use awareness::{AwarenessState, AwarenessStates};
 pub use doc::DocMessage;
 use doc::{read_doc_message, write_doc_message};
+pub use handler::SyncProtocol;
 use log::debug;
 use nom::{
   error::{Error, ErrorKind},
@@ -1,21 +1,20 @@
+#[cfg(all(test, not(loom)))]
+pub(crate) use std::sync::{atomic::AtomicUsize, MutexGuard};
 #[allow(unused)]
 #[cfg(not(loom))]
 pub(crate) use std::sync::{
   atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU8, Ordering},
-  Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+  Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
 };
 pub use std::sync::{Arc, Weak};
-#[cfg(all(test, not(loom)))]
-pub(crate) use std::{
-  sync::{atomic::AtomicUsize, MutexGuard},
-  thread,
-};
+#[cfg(not(loom))]
+pub(crate) use std::thread;
 
 #[cfg(loom)]
 pub(crate) use loom::{
   sync::{
     atomic::{AtomicBool, AtomicU16, AtomicU8, AtomicUsize, Ordering},
-    Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
   },
   thread,
 };
@@ -42,7 +42,11 @@ export declare class YText {
   get len(): number
   get isEmpty(): boolean
   insert(index: number, str: string): void
+  insertWithAttributes(index: number, str: string, attributes: Record<string, any>): void
+  format(index: number, len: number, attributes: Record<string, any>): void
   remove(index: number, len: number): void
   get length(): number
   toString(): string
+  toDelta(): Array<{ insert?: string | object; retain?: number; delete?: number; attributes?: Record<string, any> }>
+  applyDelta(delta: Array<{ insert?: string | object; retain?: number; delete?: number; attributes?: Record<string, any> }>): void
 }
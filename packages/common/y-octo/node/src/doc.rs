@@ -2,7 +2,7 @@ use napi::{
   bindgen_prelude::Uint8Array,
   threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
 };
-use y_octo::{CrdtRead, Doc as YDoc, History, RawDecoder, StateVector};
+use y_octo::{CrdtRead, Doc as YDoc, DocOptions, History, OffsetKind, RawDecoder, StateVector};
 
 use super::*;
 
@@ -15,12 +15,14 @@ pub struct Doc {
 impl Doc {
   #[napi(constructor)]
   pub fn new(client_id: Option<i64>) -> Self {
+    // the text indexes are the ones of javascript strings
+    let mut options = DocOptions::new().with_offset_kind(OffsetKind::Utf16);
+    if let Some(client_id) = client_id {
+      options = options.with_client_id(client_id as u64);
+    }
+
     Self {
-      doc: if let Some(client_id) = client_id {
-        YDoc::with_client(client_id as u64)
-      } else {
-        YDoc::default()
-      },
+      doc: options.build(),
     }
   }
 
@@ -1,4 +1,5 @@
-use y_octo::Text;
+use napi::bindgen_prelude::{Array as JsArray, Env, Object};
+use y_octo::{Any, Text, TextAttributes, TextDelta};
 
 use super::*;
 
@@ -37,6 +38,27 @@ impl YText {
       .map_err(anyhow::Error::from)
   }
 
+  #[napi(ts_args_type = "index: number, str: string, attributes: Record<string, any>")]
+  pub fn insert_with_attributes(
+    &mut self,
+    index: i64,
+    str: String,
+    attributes: Object,
+  ) -> Result<()> {
+    self
+      .text
+      .insert_with_attributes(index as u64, str, get_text_attributes(attributes)?)
+      .map_err(anyhow::Error::from)
+  }
+
+  #[napi(ts_args_type = "index: number, len: number, attributes: Record<string, any>")]
+  pub fn format(&mut self, index: i64, len: i64, attributes: Object) -> Result<()> {
+    self
+      .text
+      .format(index as u64, len as u64, get_text_attributes(attributes)?)
+      .map_err(anyhow::Error::from)
+  }
+
   #[napi]
   pub fn remove(&mut self, index: i64, len: i64) -> Result<()> {
     self
@@ -55,6 +77,41 @@ impl YText {
   pub fn to_string(&self) -> String {
     self.text.to_string()
   }
+
+  #[napi(
+    ts_return_type = "Array<{ insert?: string | object; retain?: number; delete?: number; \
+                           attributes?: Record<string, any> }>"
+  )]
+  pub fn to_delta<'env>(&'env self, env: &'env Env) -> Result<JsArray<'env>> {
+    let mut js_array = env.create_array(0)?;
+    for op in self.text.to_delta() {
+      js_array.insert(get_js_unknown_from_any(env, op.into())?)?;
+    }
+    Ok(js_array)
+  }
+
+  #[napi(
+    ts_args_type = "delta: Array<{ insert?: string | object; retain?: number; delete?: \
+                         number; attributes?: Record<string, any> }>"
+  )]
+  pub fn apply_delta(&mut self, delta: Object) -> Result<()> {
+    let Any::Array(ops) = get_any_from_js_object(delta)? else {
+      return Err(anyhow::Error::msg("Delta must be an array"));
+    };
+    let delta = ops
+      .into_iter()
+      .map(TextDelta::try_from)
+      .collect::<Result<Vec<_>, _>>()?;
+
+    self.text.apply_delta(&delta).map_err(anyhow::Error::from)
+  }
+}
+
+fn get_text_attributes(attributes: Object) -> Result<TextAttributes> {
+  match get_any_from_js_object(attributes)? {
+    Any::Object(attributes) => Ok(attributes),
+    _ => Err(anyhow::Error::msg("Attributes must be an object")),
+  }
 }
 
 #[cfg(test)]
@@ -79,4 +136,17 @@ mod tests {
     text.remove(5, 6).unwrap();
     assert_eq!(text.to_string(), "hello");
   }
+
+  #[test]
+  fn test_text_utf16_index() {
+    let doc = Doc::new(None);
+    let mut text = doc.get_or_create_text("text".into()).unwrap();
+    text.insert(0, "a😀中".into()).unwrap();
+    // same as `"a😀中".length` in javascript
+    assert_eq!(text.len(), 4);
+    text.insert(3, "b".into()).unwrap();
+    assert_eq!(text.to_string(), "a😀b中");
+    text.remove(1, 2).unwrap();
+    assert_eq!(text.to_string(), "ab中");
+  }
 }
@@ -29,6 +29,13 @@ pub fn get_js_unknown_from_any(env: &Env, any: Any) -> Result<Unknown> {
       )?;
       Ok(js_array.to_unknown())
     }
+    Any::Object(object) => {
+      let mut js_object = Object::new(env)?;
+      for (key, value) in object {
+        js_object.set(key, get_js_unknown_from_any(env, value)?)?;
+      }
+      Ok(js_object.to_unknown())
+    }
     _ => Null.into_unknown(env),
   }
 }
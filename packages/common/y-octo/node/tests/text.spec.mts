@@ -37,6 +37,31 @@ test('text test', { concurrency: false }, async t => {
     equal(text.toString(), '');
   });
 
+  await t.test('text formatting', () => {
+    let text = doc.getOrCreateText('text');
+    text.insert(0, 'hello world');
+    text.format(0, 5, { bold: true });
+    text.insertWithAttributes(11, '!', { link: 'https://affine.pro' });
+    equal(text.toString(), 'hello world!');
+    deepEqual(text.toDelta(), [
+      { insert: 'hello', attributes: { bold: true } },
+      { insert: ' world' },
+      { insert: '!', attributes: { link: 'https://affine.pro' } },
+    ]);
+  });
+
+  await t.test('text delta', () => {
+    let text = doc.getOrCreateText('text');
+    text.applyDelta([{ insert: 'abc' }, { insert: 'def', attributes: { italic: true } }]);
+    text.applyDelta([{ retain: 1 }, { delete: 1 }, { retain: 1, attributes: { bold: true } }]);
+    equal(text.toString(), 'acdef');
+    deepEqual(text.toDelta(), [
+      { insert: 'a' },
+      { insert: 'c', attributes: { bold: true } },
+      { insert: 'def', attributes: { italic: true } },
+    ]);
+  });
+
   await t.test('sub text should can edit', () => {
     let map = doc.getOrCreateMap('map');
     let sub = doc.createText();
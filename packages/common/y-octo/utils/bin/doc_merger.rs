@@ -5,8 +5,8 @@ use std::{
   time::Instant,
 };
 
-use clap::Parser;
-use y_octo::Doc;
+use clap::{Parser, Subcommand};
+use y_octo::{inspect_structs_v1, inspect_update_v1, Doc};
 
 /// ybinary merger
 #[derive(Parser, Debug)]
@@ -15,6 +15,19 @@ struct Args {
   /// Path of the ybinary to read
   #[arg(short, long)]
   path: String,
+
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Print the structs, delete set and pending state of the ybinary as json
+  Inspect {
+    /// Merge all the ybinaries into a doc and inspect the doc instead
+    #[arg(short, long)]
+    merge: bool,
+  },
 }
 
 fn load_path(path: &str) -> Result<Vec<Vec<u8>>, Error> {
@@ -35,7 +48,7 @@ fn load_path(path: &str) -> Result<Vec<Vec<u8>>, Error> {
     paths.sort();
 
     for path in paths {
-      println!("read {:?}", path);
+      eprintln!("read {:?}", path);
       updates.push(read(path)?);
     }
     Ok(updates)
@@ -48,7 +61,50 @@ fn load_path(path: &str) -> Result<Vec<Vec<u8>>, Error> {
 
 fn main() {
   let args = Args::parse();
-  jwst_merge(&args.path);
+  match args.command {
+    Some(Command::Inspect { merge }) => jwst_inspect(&args.path, merge),
+    None => jwst_merge(&args.path),
+  }
+}
+
+fn jwst_inspect(path: &str, merge: bool) {
+  let updates = load_path(path).unwrap();
+
+  let inspections = if merge {
+    let mut doc = Doc::default();
+    for (i, update) in updates.iter().enumerate() {
+      if let Err(e) = doc.apply_update_from_binary_v1(update.clone()) {
+        eprintln!("failed to apply update{i}: {e}");
+        print_corrupt_structs(update);
+        return;
+      }
+    }
+    vec![doc.inspect()]
+  } else {
+    let mut inspections = Vec::with_capacity(updates.len());
+    for (i, update) in updates.iter().enumerate() {
+      match inspect_update_v1(update) {
+        Ok(inspection) => inspections.push(inspection),
+        Err(e) => {
+          eprintln!("failed to decode update{i}: {e}");
+          print_corrupt_structs(update);
+          return;
+        }
+      }
+    }
+    inspections
+  };
+
+  println!("{}", serde_json::to_string_pretty(&inspections).unwrap());
+}
+
+/// print the structs read before the decoding of the update fails
+fn print_corrupt_structs(update: &[u8]) {
+  let (structs, result) = inspect_structs_v1(update);
+  if let Err(e) = result {
+    eprintln!("{} structs read before: {e}", structs.len());
+  }
+  println!("{}", serde_json::to_string_pretty(&structs).unwrap());
 }
 
 fn jwst_merge(path: &str) {
@@ -0,0 +1,126 @@
+use std::{
+  fs::{read, write},
+  io::{Error, ErrorKind},
+  path::PathBuf,
+  process::exit,
+};
+
+use clap::Parser;
+use y_octo::{Doc, RepairReport, Update};
+
+/// ybinary verifier and repairer
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  /// Path of the ybinary, or the directory of ybinaries to merge
+  #[arg(short, long)]
+  path: String,
+
+  /// Only check the doc, exit with an error if any issue is found
+  #[arg(short, long)]
+  check: bool,
+
+  /// Path to write the repaired ybinary to
+  #[arg(short, long)]
+  output: Option<String>,
+}
+
+fn load_path(path: &str) -> Result<Vec<(PathBuf, Vec<u8>)>, Error> {
+  let path = PathBuf::from(path);
+  if path.is_dir() {
+    let mut paths = path
+      .read_dir()?
+      .filter_map(|entry| {
+        let entry = entry.ok()?;
+        if entry.path().is_file() {
+          Some(entry.path())
+        } else {
+          None
+        }
+      })
+      .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+      .into_iter()
+      .map(|path| read(&path).map(|update| (path, update)))
+      .collect()
+  } else if path.is_file() {
+    Ok(vec![(path.clone(), read(path)?)])
+  } else {
+    Err(Error::new(ErrorKind::NotFound, "not a file or directory"))
+  }
+}
+
+fn main() {
+  let args = Args::parse();
+  let updates = load_path(&args.path).unwrap();
+
+  if args.check {
+    jwst_check(updates);
+  } else {
+    jwst_repair(updates, args.output);
+  }
+}
+
+fn jwst_check(updates: Vec<(PathBuf, Vec<u8>)>) {
+  let mut doc = Doc::default();
+  for (path, update) in updates {
+    if let Err(e) = doc.apply_update_from_binary_v1(update) {
+      eprintln!("failed to apply {path:?}: {e}");
+      exit(1);
+    }
+  }
+
+  let issues = doc.verify();
+  println!("{}", serde_json::to_string_pretty(&issues).unwrap());
+  if !issues.is_empty() {
+    exit(1);
+  }
+}
+
+fn jwst_repair(updates: Vec<(PathBuf, Vec<u8>)>, output: Option<String>) {
+  let mut doc = Doc::default();
+  let mut reports = Vec::new();
+  for (path, update) in updates {
+    let update = match Update::decode_v1(update) {
+      Ok(update) => update,
+      Err(e) => {
+        // nothing can be recovered from an update failed to decode
+        eprintln!("skip {path:?}, failed to decode: {e}");
+        continue;
+      }
+    };
+
+    match doc.apply_update_with_repair(update) {
+      Ok(report) => reports.push(report),
+      Err(e) => eprintln!("skip {path:?}, failed to repair: {e}"),
+    }
+  }
+
+  // the missing dependencies and issues are of the final doc
+  let report = reports
+    .into_iter()
+    .reduce(|mut report, next| {
+      report.dropped.extend(next.dropped);
+      report.reparented.extend(next.reparented);
+      for (client, ranges) in next.failed_deletions {
+        report
+          .failed_deletions
+          .entry(client)
+          .or_default()
+          .extend(ranges);
+      }
+      report.missing = next.missing;
+      report.issues = next.issues;
+      report
+    })
+    .unwrap_or_else(RepairReport::default);
+  println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+  if let Some(output) = output {
+    let binary = doc.encode_update_v1().unwrap();
+    write(&output, &binary).unwrap();
+    eprintln!("write {} bytes to {output}", binary.len());
+  }
+}